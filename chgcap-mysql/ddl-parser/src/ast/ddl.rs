@@ -1,8 +1,84 @@
 use std::fmt;
 
-use crate::ast::value::escape_single_quote_string;
+use crate::ast::value::{escape_single_quote_string, Value};
 
-use super::{display_comma_separated, Ident};
+use super::{display_comma_separated, ColumnDef, Ident, ObjectName};
+
+/// An `ALTER TABLE` operation. The operations that don't change the columns, the primary key or
+/// the name of the table, e.g. `ADD INDEX`, are not represented unless they're constraints.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum AlterTableOperation {
+    /// `ADD [COLUMN] col_name column_definition [FIRST | AFTER col_name]`
+    AddColumn {
+        column_def: ColumnDef,
+        position: Option<ColumnPosition>,
+    },
+    /// `ADD <table_constraint>`
+    AddConstraint(TableConstraint),
+    /// `DROP [COLUMN] col_name`
+    DropColumn { column_name: Ident },
+    /// `DROP PRIMARY KEY`
+    DropPrimaryKey,
+    /// `MODIFY [COLUMN] col_name column_definition [FIRST | AFTER col_name]`
+    ModifyColumn {
+        column_def: ColumnDef,
+        position: Option<ColumnPosition>,
+    },
+    /// `CHANGE [COLUMN] old_col_name new_col_name column_definition [FIRST | AFTER col_name]`
+    ChangeColumn {
+        old_name: Ident,
+        column_def: ColumnDef,
+        position: Option<ColumnPosition>,
+    },
+    /// `RENAME COLUMN old_col_name TO new_col_name`
+    RenameColumn { old_name: Ident, new_name: Ident },
+    /// `ALTER [COLUMN] col_name {SET DEFAULT ... | DROP DEFAULT | SET {VISIBLE | INVISIBLE}}`
+    AlterColumn {
+        column_name: Ident,
+        op: AlterColumnOperation,
+    },
+    /// `RENAME [TO | AS] new_tbl_name`
+    RenameTable { table_name: ObjectName },
+    /// `CONVERT TO CHARACTER SET charset_name [COLLATE collation_name]`
+    ConvertToCharacterSet {
+        charset: Ident,
+        collation: Option<Ident>,
+    },
+    /// A table option, e.g. `[DEFAULT] CHARACTER SET [=] charset_name`.
+    TableOption(TableOption),
+}
+
+/// The position of an added or a modified column.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum ColumnPosition {
+    /// `FIRST`
+    First,
+    /// `AFTER col_name`
+    After(Ident),
+}
+
+/// An `ALTER TABLE ... ALTER [COLUMN]` operation.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum AlterColumnOperation {
+    /// `SET DEFAULT {literal | (expr)}`
+    SetDefault(Value),
+    /// `DROP DEFAULT`
+    DropDefault,
+    /// `SET VISIBLE`
+    SetVisible,
+    /// `SET INVISIBLE`
+    SetInvisible,
+}
+
+/// A table option of `CREATE TABLE` or `ALTER TABLE`. The other options, e.g. `ENGINE`, are not
+/// represented.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum TableOption {
+    /// `[DEFAULT] {CHARACTER SET | CHARSET} [=] charset_name`
+    CharacterSet(Ident),
+    /// `[DEFAULT] COLLATE [=] collation_name`
+    Collate(Ident),
+}
 
 /// A table-level constraint, specified in a `CREATE TABLE` or an
 /// `ALTER TABLE ADD <constraint>` statement.
@@ -10,6 +86,18 @@ use super::{display_comma_separated, Ident};
 pub enum TableConstraint {
     /// `[CONSTRAINT [symbol]] PRIMARY KEY [index_type] (key_part,...) [index_option] ...`
     PrimaryKeys { columns: Vec<Ident> },
+    /// `[CONSTRAINT [symbol]] UNIQUE [INDEX | KEY] [index_name] [index_type] (key_part,...) [index_option] ...`
+    Unique { columns: Vec<Ident> },
+    /// `{INDEX | KEY | FULLTEXT [INDEX | KEY] | SPATIAL [INDEX | KEY]} [index_name] [index_type] (key_part,...) [index_option] ...`
+    Index { columns: Vec<Ident> },
+    /// `[CONSTRAINT [symbol]] FOREIGN KEY [index_name] (col_name,...) reference_definition`
+    ForeignKey {
+        columns: Vec<Ident>,
+        foreign_table: ObjectName,
+        referred_columns: Vec<Ident>,
+    },
+    /// `[CONSTRAINT [symbol]] CHECK (expr) [[NOT] ENFORCED]`
+    Check { expr: String },
 }
 
 impl fmt::Display for TableConstraint {
@@ -18,6 +106,24 @@ impl fmt::Display for TableConstraint {
             TableConstraint::PrimaryKeys { columns } => {
                 write!(f, "PRIMARY KEY ({})", display_comma_separated(columns))
             }
+            TableConstraint::Unique { columns } => {
+                write!(f, "UNIQUE ({})", display_comma_separated(columns))
+            }
+            TableConstraint::Index { columns } => {
+                write!(f, "INDEX ({})", display_comma_separated(columns))
+            }
+            TableConstraint::ForeignKey {
+                columns,
+                foreign_table,
+                referred_columns,
+            } => write!(
+                f,
+                "FOREIGN KEY ({}) REFERENCES {}({})",
+                display_comma_separated(columns),
+                foreign_table,
+                display_comma_separated(referred_columns)
+            ),
+            TableConstraint::Check { expr } => write!(f, "CHECK ({expr})"),
         }
     }
 }
@@ -34,6 +140,24 @@ pub enum ColumnOption {
     PrimaryKey,
     // COMMENT 'string'
     Comment(String),
+    /// `DEFAULT {literal | (expr)}`, where a function call such as
+    /// `CURRENT_TIMESTAMP(3)` or an expression is kept as an unquoted string.
+    Default(Value),
+    /// `ON UPDATE CURRENT_TIMESTAMP[(fsp)]`
+    OnUpdate(String),
+    /// `AUTO_INCREMENT`
+    AutoIncrement,
+    /// `INVISIBLE`
+    Invisible,
+    /// `CHARACTER SET charset_name`
+    CharacterSet(Ident),
+    /// `COLLATE collation_name`
+    Collate(Ident),
+    /// `[GENERATED ALWAYS] AS (expr) [VIRTUAL | STORED]`
+    Generated {
+        expr: String,
+        stored: bool,
+    },
 }
 
 impl fmt::Display for ColumnOption {
@@ -44,6 +168,17 @@ impl fmt::Display for ColumnOption {
             NotNull => write!(f, "NOT NULL"),
             Comment(v) => write!(f, "COMMENT '{}'", escape_single_quote_string(v)),
             PrimaryKey => write!(f, "PRIMARY KEY"),
+            Default(v) => write!(f, "DEFAULT {v}"),
+            OnUpdate(v) => write!(f, "ON UPDATE {v}"),
+            AutoIncrement => write!(f, "AUTO_INCREMENT"),
+            Invisible => write!(f, "INVISIBLE"),
+            CharacterSet(v) => write!(f, "CHARACTER SET {v}"),
+            Collate(v) => write!(f, "COLLATE {v}"),
+            Generated { expr, stored } => write!(
+                f,
+                "GENERATED ALWAYS AS ({expr}) {}",
+                if *stored { "STORED" } else { "VIRTUAL" }
+            ),
         }
     }
 }
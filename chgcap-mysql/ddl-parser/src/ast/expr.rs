@@ -0,0 +1,69 @@
+use std::fmt;
+
+use crate::ast::value::Value;
+
+use super::{display_comma_separated, Ident};
+
+/// A condition on a single column, which is compared with literals, e.g. the conjuncts of
+/// `tenant_id = 42 AND deleted_at IS NULL`.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum Predicate {
+    /// `col_name {= | <> | != | < | <= | > | >=} literal`
+    Compare {
+        column: Ident,
+        op: CompareOperator,
+        value: Value,
+    },
+    /// `col_name IS [NOT] NULL`
+    IsNull { column: Ident, negated: bool },
+    /// `col_name [NOT] IN (literal, ...)`
+    InList {
+        column: Ident,
+        list: Vec<Value>,
+        negated: bool,
+    },
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Predicate::Compare { column, op, value } => write!(f, "{column} {op} {value}"),
+            Predicate::IsNull { column, negated } => {
+                write!(f, "{column} IS {}NULL", if *negated { "NOT " } else { "" })
+            }
+            Predicate::InList {
+                column,
+                list,
+                negated,
+            } => write!(
+                f,
+                "{column} {}IN ({})",
+                if *negated { "NOT " } else { "" },
+                display_comma_separated(list)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum CompareOperator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl fmt::Display for CompareOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CompareOperator::Eq => "=",
+            CompareOperator::NotEq => "<>",
+            CompareOperator::Lt => "<",
+            CompareOperator::LtEq => "<=",
+            CompareOperator::Gt => ">",
+            CompareOperator::GtEq => ">=",
+        })
+    }
+}
@@ -1,12 +1,15 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use self::{
     data_type::DataType,
-    ddl::{ColumnOption, TableConstraint},
+    ddl::{AlterTableOperation, ColumnOption, TableConstraint, TableOption},
 };
 
 pub mod data_type;
 pub mod ddl;
+pub mod expr;
 pub mod value;
 
 /// A top-level statement (SELECT, INSERT, CREATE, etc.)
@@ -14,20 +17,62 @@ pub mod value;
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum Statement {
     /// ```sql
-    /// CREATE TABLE
+    /// CREATE [TEMPORARY] TABLE
     /// ```
     CreateTable {
+        temporary: bool,
         if_not_exists: bool,
         /// Table name
         name: ObjectName,
         /// Optional schema
         columns: Vec<ColumnDef>,
         constraints: Vec<TableConstraint>,
+        /// The supported table options, e.g. `DEFAULT CHARSET=utf8mb4`.
+        table_options: Vec<TableOption>,
+        /// Whether the table is created from a query, i.e. `[AS] SELECT ...`, which may add
+        /// columns that are not in the statement.
+        with_query: bool,
+    },
+    /// ```sql
+    /// CREATE [TEMPORARY] TABLE ... LIKE
+    /// ```
+    CreateTableLike {
+        temporary: bool,
+        if_not_exists: bool,
+        name: ObjectName,
+        like: ObjectName,
+    },
+    /// ```sql
+    /// ALTER TABLE
+    /// ```
+    AlterTable {
+        name: ObjectName,
+        /// The supported operations, in the order of the statement.
+        operations: Vec<AlterTableOperation>,
     },
+    /// ```sql
+    /// DROP [TEMPORARY] TABLE
+    /// ```
+    DropTable {
+        temporary: bool,
+        if_exists: bool,
+        names: Vec<ObjectName>,
+    },
+    /// ```sql
+    /// RENAME TABLE
+    /// ```
+    RenameTable {
+        /// The `(old_name, new_name)` pairs.
+        renames: Vec<(ObjectName, ObjectName)>,
+    },
+    /// ```sql
+    /// DROP {DATABASE | SCHEMA}
+    /// ```
+    DropDatabase { if_exists: bool, name: Ident },
 }
 
 /// A name of a table, view, custom type, etc., possibly multi-part, i.e. db.schema.obj
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct ObjectName(pub Vec<Ident>);
 
 impl fmt::Display for ObjectName {
@@ -37,7 +82,7 @@ impl fmt::Display for ObjectName {
 }
 
 /// An identifier, decomposed into its value or character data and the quote style.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct Ident {
     /// The value of the identifier without quotes.
     pub value: String,
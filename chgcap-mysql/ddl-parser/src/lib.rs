@@ -4,4 +4,4 @@ pub mod ast;
 mod dialect;
 mod keywords;
 pub mod parser;
-mod tokenizer;
+pub mod tokenizer;
@@ -10,23 +10,62 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::vec;
+use std::{fmt, vec};
 
 use log::debug;
 
 use crate::ast::data_type::{
     CharLengthUnits, CharacterLength, DataType, ExactNumberInfo, TimezoneInfo,
 };
-use crate::ast::ddl::{ColumnOption, TableConstraint};
+use crate::ast::ddl::{
+    AlterColumnOperation, AlterTableOperation, ColumnOption, ColumnPosition, TableConstraint,
+    TableOption,
+};
+use crate::ast::expr::{CompareOperator, Predicate};
+use crate::ast::value::Value;
 use crate::ast::ColumnDef;
 use crate::keywords::{self};
-use crate::tokenizer::{Location, Word};
+use crate::tokenizer::{Location, TokenizerError, Word};
 use crate::{
     ast::{Ident, ObjectName, Statement},
     keywords::Keyword,
     tokenizer::{Token, TokenWithLocation, Tokenizer},
 };
 
+macro_rules! parser_err {
+    ($($arg:tt)*) => {
+        Err(ParserError::ParserError(format!($($arg)*)))
+    };
+}
+
+/// Parser error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserError {
+    TokenizerError(String),
+    ParserError(String),
+}
+
+impl From<TokenizerError> for ParserError {
+    fn from(e: TokenizerError) -> Self {
+        ParserError::TokenizerError(e.to_string())
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sql parser error: {}",
+            match self {
+                ParserError::TokenizerError(s) => s,
+                ParserError::ParserError(s) => s,
+            }
+        )
+    }
+}
+
+impl std::error::Error for ParserError {}
+
 pub const TRAILING_COMMAS: bool = false;
 
 pub struct Parser {
@@ -42,9 +81,11 @@ impl Parser {
     ///
     /// Example:
     /// ```
-    /// use sqlparser::{parser::{Parser, ParserError}};
+    /// use chgcap_mysql_ddl_parser::parser::{Parser, ParserError};
     /// fn main() -> Result<(), ParserError> {
-    ///   let statements = Parser::parse_sql("CREATE TABLE t (v INT);")?;
+    ///   let statement = Parser::parse_sql("CREATE TABLE t (v INT);")?;
+    ///   assert!(statement.is_some());
+    ///   Ok(())
     /// }
     /// ```
     fn new() -> Self {
@@ -58,12 +99,10 @@ impl Parser {
     /// parse the resulting tokens
     ///
     /// See example on [`Parser::new()`] for an example
-    fn try_with_sql(self, sql: &str) -> Option<Self> {
+    fn try_with_sql(self, sql: &str) -> Result<Self, ParserError> {
         debug!("Parsing sql '{}'...", sql);
-        Tokenizer::new(sql)
-            .tokenize_with_location()
-            .map(|tokens| self.with_tokens_with_locations(tokens))
-            .ok()
+        let tokens = Tokenizer::new(sql).tokenize_with_location()?;
+        Ok(self.with_tokens_with_locations(tokens))
     }
 
     /// Reset this parser to parse the specified token stream
@@ -76,7 +115,9 @@ impl Parser {
     /// Convenience method to parse a string with one or more SQL
     /// statements into produce an Abstract Syntax Tree (AST).
     /// NOTE: We assume that the upstream database has validated the query.
-    /// Therefore, the user can simply skip the query if this function returns `None`.
+    /// Therefore, the user can simply skip the query if this function returns `Ok(None)`,
+    /// i.e. the statement is not supported. An error is returned if the parser fails on
+    /// the query.
     ///
     /// Example
     /// ```
@@ -85,99 +126,521 @@ impl Parser {
     ///   Parser::parse_sql("CREATE TABLE t (v INT)").unwrap();
     /// }
     /// ```
-    pub fn parse_sql(sql: &str) -> Option<Statement> {
-        Parser::new()
-            .try_with_sql(sql)
-            .as_mut()
-            .and_then(|p| p.parse_statement())
+    pub fn parse_sql(sql: &str) -> Result<Option<Statement>, ParserError> {
+        Parser::new().try_with_sql(sql)?.parse_statement()
+    }
+
+    /// Parse a conjunction of predicates on single columns, e.g. a row filter such as
+    /// `tenant_id = 42 AND status IN ('new', 'paid')`.
+    ///
+    /// Example
+    /// ```
+    /// use chgcap_mysql_ddl_parser::parser::Parser;
+    /// let predicates = Parser::parse_predicates("a = 1 AND b IS NOT NULL").unwrap();
+    /// assert_eq!(predicates.len(), 2);
+    /// ```
+    pub fn parse_predicates(sql: &str) -> Result<Vec<Predicate>, ParserError> {
+        let mut parser = Parser::new().try_with_sql(sql)?;
+        let mut predicates = vec![parser.parse_predicate()?];
+        while parser.parse_keyword(Keyword::AND) {
+            predicates.push(parser.parse_predicate()?);
+        }
+        match parser.peek_token().token {
+            Token::EOF => Ok(predicates),
+            token => parser_err!("Expected AND, found {token}"),
+        }
+    }
+
+    /// Parse the names of the tables of a CREATE, ALTER, DROP or RENAME TABLE statement, without
+    /// the rest of the statement, e.g. to tell which tables a statement that [`Parser::parse_sql`]
+    /// fails on changes. The names of the temporary tables are left out, and so are the names of
+    /// the other statements.
+    ///
+    /// Example
+    /// ```
+    /// use chgcap_mysql_ddl_parser::parser::Parser;
+    /// let names = Parser::parse_table_names("ALTER TABLE db.t ADD COLUMN c UNKNOWN_TYPE").unwrap();
+    /// assert_eq!(names[0].to_string(), "db.t");
+    /// ```
+    pub fn parse_table_names(sql: &str) -> Result<Vec<ObjectName>, ParserError> {
+        let mut parser = Parser::new().try_with_sql(sql)?;
+        let keyword = match parser.next_token().token {
+            Token::Word(w) => w.keyword,
+            _ => return Ok(vec![]),
+        };
+        let mut names = vec![];
+        match keyword {
+            Keyword::CREATE | Keyword::DROP => {
+                if parser.parse_keyword(Keyword::TEMPORARY)
+                    || parser
+                        .parse_one_of_keywords(&[Keyword::TABLE, Keyword::TABLES])
+                        .is_none()
+                {
+                    return Ok(vec![]);
+                }
+                if !parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]) {
+                    let _ = parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+                }
+                names.extend(parser.parse_comma_separated(|p| p.parse_object_name())?);
+            }
+            Keyword::ALTER => {
+                let _ = parser.parse_keyword(Keyword::ONLINE);
+                let _ = parser.parse_keyword(Keyword::IGNORE);
+                if !parser.parse_keyword(Keyword::TABLE) {
+                    return Ok(vec![]);
+                }
+                names.push(parser.parse_object_name()?);
+                // The new name of a renamed table, e.g. `RENAME TO t2`, but not `RENAME COLUMN`.
+                loop {
+                    match parser.next_token().token {
+                        Token::EOF => break,
+                        Token::Word(w) if w.keyword == Keyword::RENAME => {
+                            let renames_table = parser
+                                .parse_one_of_keywords(&[
+                                    Keyword::COLUMN,
+                                    Keyword::INDEX,
+                                    Keyword::KEY,
+                                ])
+                                .is_none();
+                            if renames_table {
+                                let _ = parser.parse_one_of_keywords(&[Keyword::TO, Keyword::AS]);
+                                names.push(parser.parse_object_name()?);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Keyword::RENAME => {
+                if parser
+                    .parse_one_of_keywords(&[Keyword::TABLE, Keyword::TABLES])
+                    .is_none()
+                {
+                    return Ok(vec![]);
+                }
+                for (from, to) in parser.parse_comma_separated(|p| {
+                    let from = p.parse_object_name()?;
+                    p.expect_keyword(Keyword::TO)?;
+                    Ok((from, p.parse_object_name()?))
+                })? {
+                    names.extend([from, to]);
+                }
+            }
+            _ => {}
+        }
+        Ok(names)
     }
 
     /// Parse a single top-level statement (such as ALTER, CREATE, etc.),
     /// stopping before the statement separator, if any.
-    pub fn parse_statement(&mut self) -> Option<Statement> {
+    pub fn parse_statement(&mut self) -> Result<Option<Statement>, ParserError> {
         let next_token = self.next_token();
         match &next_token.token {
             Token::Word(w) => match w.keyword {
                 Keyword::CREATE => self.parse_create(),
                 Keyword::ALTER => self.parse_alter(),
                 Keyword::DROP => self.parse_drop(),
-                _ => None,
+                Keyword::RENAME => self.parse_rename(),
+                _ => Ok(None),
             },
-            _ => None,
+            _ => Ok(None),
         }
     }
 
     /// Parse a SQL CREATE statement
-    pub fn parse_create(&mut self) -> Option<Statement> {
+    pub fn parse_create(&mut self) -> Result<Option<Statement>, ParserError> {
+        let temporary = self.parse_keyword(Keyword::TEMPORARY);
         if self.parse_keyword(Keyword::TABLE) {
-            self.parse_create_table()
+            self.parse_create_table(temporary)
+        } else if temporary {
+            Ok(None)
         } else if self.parse_keyword(Keyword::SCHEMA) {
             self.parse_create_schema()
         } else if self.parse_keyword(Keyword::DATABASE) {
             self.parse_create_database()
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_create_table(&mut self) -> Option<Statement> {
+    pub fn parse_create_table(
+        &mut self,
+        temporary: bool,
+    ) -> Result<Option<Statement>, ParserError> {
         let if_not_exists = self.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
-        let table_name = self.parse_object_name();
+        let table_name = self.parse_object_name()?;
 
+        // CREATE TABLE ... { LIKE old_tbl_name | (LIKE old_tbl_name) }
+        let parenthesized = self.peek_token() == Token::LParen
+            && matches!(self.peek_nth_token(1).token, Token::Word(w) if w.keyword == Keyword::LIKE);
+        if parenthesized {
+            self.next_token();
+        }
         if self.parse_keyword(Keyword::LIKE) {
-            // CREATE TABLE ... LIKE is unsupported yet, see https://github.com/neverchanje/chgcap-rs/issues/14 for details.
-            return None;
+            let like = self.parse_object_name()?;
+            if parenthesized {
+                self.expect_token(&Token::RParen)?;
+            }
+            return Ok(Some(Statement::CreateTableLike {
+                temporary,
+                if_not_exists,
+                name: table_name,
+                like,
+            }));
         }
 
         // parse optional column list (schema)
-        let (columns, constraints) = self.parse_columns();
-
-        // Table options and partition options are ending clauses, hence that we don't have to parse them.
-
-        // Parse optional `AS ( query )`
-        if self.parse_keyword(Keyword::AS) {
-            // CREATE TABLE ... AS is not supported.
-            return None;
-        }
+        let (columns, constraints) = self.parse_columns()?;
+        let (table_options, with_query) = self.parse_table_options()?;
 
-        Some(Statement::CreateTable {
+        Ok(Some(Statement::CreateTable {
+            temporary,
             if_not_exists,
             name: table_name,
             columns,
             constraints,
-        })
+            table_options,
+            with_query,
+        }))
     }
 
-    pub fn parse_create_schema(&mut self) -> Option<Statement> {
-        todo!()
+    // [table_options] [partition_options] [IGNORE | REPLACE] [AS] query_expression
+    //
+    // Returns the supported table options, and whether the table is created from a query. The
+    // partition options are skipped.
+    pub fn parse_table_options(&mut self) -> Result<(Vec<TableOption>, bool), ParserError> {
+        let mut options = vec![];
+        let mut partitioned = false;
+        loop {
+            if !partitioned {
+                if let Some(option) = self.parse_optional_table_option()? {
+                    options.push(option);
+                    continue;
+                }
+            }
+            let next_token = self.next_token();
+            match next_token.token {
+                Token::EOF | Token::SemiColon => return Ok((options, false)),
+                Token::Word(w) => match w.keyword {
+                    Keyword::PARTITION => partitioned = true,
+                    // `CREATE TABLE ... START TRANSACTION` is logged for an atomic `CREATE TABLE ... SELECT`.
+                    Keyword::START => return Ok((options, false)),
+                    Keyword::AS
+                    | Keyword::IGNORE
+                    | Keyword::REPLACE
+                    | Keyword::SELECT
+                    | Keyword::TABLE
+                    | Keyword::VALUES
+                    | Keyword::WITH => return Ok((options, true)),
+                    _ => {}
+                },
+                Token::LParen => match self.peek_token().token {
+                    Token::Word(w)
+                        if matches!(
+                            w.keyword,
+                            Keyword::SELECT | Keyword::TABLE | Keyword::VALUES | Keyword::WITH
+                        ) =>
+                    {
+                        return Ok((options, true))
+                    }
+                    _ => {
+                        // Skip a parenthesized option value or partition definition.
+                        self.prev_token();
+                        self.parse_parenthesized_expr()?;
+                    }
+                },
+                _ => {}
+            }
+        }
     }
 
-    pub fn parse_create_database(&mut self) -> Option<Statement> {
-        todo!()
+    // [DEFAULT] {CHARACTER SET | CHARSET} [=] charset_name
+    // [DEFAULT] COLLATE [=] collation_name
+    //
+    // Returns `None` and consumes no tokens if the next tokens are not one of the options above.
+    pub fn parse_optional_table_option(&mut self) -> Result<Option<TableOption>, ParserError> {
+        let index = self.index;
+        let _ = self.parse_keyword(Keyword::DEFAULT);
+        if self.parse_keywords(&[Keyword::CHARACTER, Keyword::SET])
+            || self.parse_keyword(Keyword::CHARSET)
+        {
+            let _ = self.consume_token(&Token::Eq);
+            return Ok(Some(TableOption::CharacterSet(self.expect_identifier()?)));
+        }
+        if self.parse_keyword(Keyword::COLLATE) {
+            let _ = self.consume_token(&Token::Eq);
+            return Ok(Some(TableOption::Collate(self.expect_identifier()?)));
+        }
+        self.index = index;
+        Ok(None)
     }
 
-    pub fn parse_alter(&mut self) -> Option<Statement> {
-        todo!()
+    // The following statements are unsupported yet.
+
+    pub fn parse_create_schema(&mut self) -> Result<Option<Statement>, ParserError> {
+        Ok(None)
     }
 
-    pub fn parse_drop(&mut self) -> Option<Statement> {
-        todo!()
+    pub fn parse_create_database(&mut self) -> Result<Option<Statement>, ParserError> {
+        Ok(None)
+    }
+
+    // ALTER [ONLINE] [IGNORE] TABLE tbl_name [alter_option [, alter_option] ...] [partition_options]
+    pub fn parse_alter(&mut self) -> Result<Option<Statement>, ParserError> {
+        let _ = self.parse_keyword(Keyword::ONLINE);
+        let _ = self.parse_keyword(Keyword::IGNORE);
+        if !self.parse_keyword(Keyword::TABLE) {
+            return Ok(None);
+        }
+        let name = self.parse_object_name()?;
+        let mut operations = vec![];
+        loop {
+            match self.peek_token().token {
+                Token::EOF | Token::SemiColon => break,
+                // The partition options are the last clause.
+                Token::Word(w) if w.keyword == Keyword::PARTITION => break,
+                _ => {}
+            }
+            operations.extend(self.parse_alter_table_operation()?);
+            self.parse_ending_of_alter_table_operation()?;
+        }
+        Ok(Some(Statement::AlterTable { name, operations }))
     }
 
-    pub fn parse_columns(&mut self) -> (Vec<ColumnDef>, Vec<TableConstraint>) {
+    /// Parse an `ALTER TABLE` operation. Returns no operation if it's not represented by
+    /// [`AlterTableOperation`], in which case the rest of it is left to
+    /// [`Parser::parse_ending_of_alter_table_operation`].
+    pub fn parse_alter_table_operation(&mut self) -> Result<Vec<AlterTableOperation>, ParserError> {
+        let operation = if self.parse_keyword(Keyword::ADD) {
+            if self.parse_keyword(Keyword::PARTITION) {
+                return Ok(vec![]);
+            }
+            if let Some(constraint) = self.parse_optional_table_constraint()? {
+                AlterTableOperation::AddConstraint(constraint)
+            } else {
+                let _ = self.parse_keyword(Keyword::COLUMN);
+                if self.consume_token(&Token::LParen) {
+                    // ADD [COLUMN] (col_name column_definition,...)
+                    let mut operations = vec![];
+                    loop {
+                        if let Some(constraint) = self.parse_optional_table_constraint()? {
+                            self.parse_ending_of_create_definition()?;
+                            operations.push(AlterTableOperation::AddConstraint(constraint));
+                        } else {
+                            operations.push(AlterTableOperation::AddColumn {
+                                column_def: self.parse_column_def()?,
+                                position: None,
+                            });
+                        }
+                        if !self.consume_token(&Token::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect_token(&Token::RParen)?;
+                    return Ok(operations);
+                }
+                AlterTableOperation::AddColumn {
+                    column_def: self.parse_column_def()?,
+                    position: self.parse_optional_column_position()?,
+                }
+            }
+        } else if self.parse_keyword(Keyword::DROP) {
+            if self.parse_keywords(&[Keyword::PRIMARY, Keyword::KEY]) {
+                AlterTableOperation::DropPrimaryKey
+            } else if self
+                .parse_one_of_keywords(&[
+                    Keyword::INDEX,
+                    Keyword::KEY,
+                    Keyword::FOREIGN,
+                    Keyword::CHECK,
+                    Keyword::CONSTRAINT,
+                    Keyword::PARTITION,
+                ])
+                .is_some()
+            {
+                return Ok(vec![]);
+            } else {
+                let _ = self.parse_keyword(Keyword::COLUMN);
+                AlterTableOperation::DropColumn {
+                    column_name: self.expect_identifier()?,
+                }
+            }
+        } else if self.parse_keyword(Keyword::MODIFY) {
+            let _ = self.parse_keyword(Keyword::COLUMN);
+            AlterTableOperation::ModifyColumn {
+                column_def: self.parse_column_def()?,
+                position: self.parse_optional_column_position()?,
+            }
+        } else if self.parse_keyword(Keyword::CHANGE) {
+            let _ = self.parse_keyword(Keyword::COLUMN);
+            AlterTableOperation::ChangeColumn {
+                old_name: self.expect_identifier()?,
+                column_def: self.parse_column_def()?,
+                position: self.parse_optional_column_position()?,
+            }
+        } else if self.parse_keyword(Keyword::RENAME) {
+            if self.parse_keyword(Keyword::COLUMN) {
+                let old_name = self.expect_identifier()?;
+                self.expect_keyword(Keyword::TO)?;
+                AlterTableOperation::RenameColumn {
+                    old_name,
+                    new_name: self.expect_identifier()?,
+                }
+            } else if self
+                .parse_one_of_keywords(&[Keyword::INDEX, Keyword::KEY])
+                .is_some()
+            {
+                return Ok(vec![]);
+            } else {
+                let _ = self.parse_one_of_keywords(&[Keyword::TO, Keyword::AS]);
+                AlterTableOperation::RenameTable {
+                    table_name: self.parse_object_name()?,
+                }
+            }
+        } else if self.parse_keyword(Keyword::ALTER) {
+            if self
+                .parse_one_of_keywords(&[Keyword::INDEX, Keyword::CHECK, Keyword::CONSTRAINT])
+                .is_some()
+            {
+                return Ok(vec![]);
+            }
+            let _ = self.parse_keyword(Keyword::COLUMN);
+            let column_name = self.expect_identifier()?;
+            let op = if self.parse_keywords(&[Keyword::SET, Keyword::DEFAULT]) {
+                AlterColumnOperation::SetDefault(self.parse_default_value()?)
+            } else if self.parse_keywords(&[Keyword::DROP, Keyword::DEFAULT]) {
+                AlterColumnOperation::DropDefault
+            } else if self.parse_keywords(&[Keyword::SET, Keyword::VISIBLE]) {
+                AlterColumnOperation::SetVisible
+            } else if self.parse_keywords(&[Keyword::SET, Keyword::INVISIBLE]) {
+                AlterColumnOperation::SetInvisible
+            } else {
+                return parser_err!(
+                    "Expected SET or DROP after ALTER COLUMN, found: {}",
+                    self.peek_token()
+                );
+            };
+            AlterTableOperation::AlterColumn { column_name, op }
+        } else if self.parse_keywords(&[Keyword::CONVERT, Keyword::TO]) {
+            if !self.parse_keywords(&[Keyword::CHARACTER, Keyword::SET])
+                && !self.parse_keyword(Keyword::CHARSET)
+            {
+                return parser_err!(
+                    "Expected CHARACTER SET after CONVERT TO, found: {}",
+                    self.peek_token()
+                );
+            }
+            let charset = self.expect_identifier()?;
+            let collation = if self.parse_keyword(Keyword::COLLATE) {
+                Some(self.expect_identifier()?)
+            } else {
+                None
+            };
+            AlterTableOperation::ConvertToCharacterSet { charset, collation }
+        } else if let Some(option) = self.parse_optional_table_option()? {
+            AlterTableOperation::TableOption(option)
+        } else {
+            return Ok(vec![]);
+        };
+        Ok(vec![operation])
+    }
+
+    // [FIRST | AFTER col_name]
+    pub fn parse_optional_column_position(
+        &mut self,
+    ) -> Result<Option<ColumnPosition>, ParserError> {
+        if self.parse_keyword(Keyword::FIRST) {
+            Ok(Some(ColumnPosition::First))
+        } else if self.parse_keyword(Keyword::AFTER) {
+            Ok(Some(ColumnPosition::After(self.expect_identifier()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Skips the rest of an `ALTER TABLE` operation, e.g. the options of an added index, and
+    // consumes the comma before the next operation, if any.
+    pub fn parse_ending_of_alter_table_operation(&mut self) -> Result<(), ParserError> {
+        loop {
+            match self.peek_token().token {
+                Token::EOF | Token::SemiColon => return Ok(()),
+                Token::Word(w) if w.keyword == Keyword::PARTITION => return Ok(()),
+                Token::Comma => {
+                    self.next_token();
+                    return Ok(());
+                }
+                Token::LParen => {
+                    self.parse_parenthesized_expr()?;
+                }
+                Token::RParen => return parser_err!("Unexpected ')' in ALTER TABLE"),
+                _ => {
+                    self.next_token();
+                }
+            }
+        }
+    }
+
+    // DROP [TEMPORARY] TABLE [IF EXISTS] tbl_name [, tbl_name] ... [RESTRICT | CASCADE]
+    // DROP {DATABASE | SCHEMA} [IF EXISTS] db_name
+    pub fn parse_drop(&mut self) -> Result<Option<Statement>, ParserError> {
+        let temporary = self.parse_keyword(Keyword::TEMPORARY);
+        if self
+            .parse_one_of_keywords(&[Keyword::TABLE, Keyword::TABLES])
+            .is_some()
+        {
+            let if_exists = self.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+            let names = self.parse_comma_separated(|p| p.parse_object_name())?;
+            Ok(Some(Statement::DropTable {
+                temporary,
+                if_exists,
+                names,
+            }))
+        } else if !temporary
+            && self
+                .parse_one_of_keywords(&[Keyword::DATABASE, Keyword::SCHEMA])
+                .is_some()
+        {
+            let if_exists = self.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+            Ok(Some(Statement::DropDatabase {
+                if_exists,
+                name: self.expect_identifier()?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // RENAME {TABLE | TABLES} tbl_name TO new_tbl_name [, tbl_name2 TO new_tbl_name2] ...
+    pub fn parse_rename(&mut self) -> Result<Option<Statement>, ParserError> {
+        if self
+            .parse_one_of_keywords(&[Keyword::TABLE, Keyword::TABLES])
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let renames = self.parse_comma_separated(|p| {
+            let old_name = p.parse_object_name()?;
+            p.expect_keyword(Keyword::TO)?;
+            Ok((old_name, p.parse_object_name()?))
+        })?;
+        Ok(Some(Statement::RenameTable { renames }))
+    }
+
+    pub fn parse_columns(&mut self) -> Result<(Vec<ColumnDef>, Vec<TableConstraint>), ParserError> {
         let mut columns = vec![];
         let mut constraints = vec![];
         if !self.consume_token(&Token::LParen) || self.consume_token(&Token::RParen) {
-            return (columns, constraints);
+            return Ok((columns, constraints));
         }
 
         loop {
-            if let Some(constraint) = self.parse_optional_table_constraint() {
+            if let Some(constraint) = self.parse_optional_table_constraint()? {
+                self.parse_ending_of_create_definition()?;
                 constraints.push(constraint);
             } else if let Token::Word(_) = self.peek_token().token {
-                columns.push(self.parse_column_def());
+                columns.push(self.parse_column_def()?);
             } else {
-                panic!(
+                return parser_err!(
                     "Expected column name or constraint definition, found: {}",
                     self.peek_token()
                 );
@@ -188,56 +651,126 @@ impl Parser {
                 break;
             }
             if !comma {
-                panic!(
+                return parser_err!(
                     "Expected ',' or ')' after column definition, found: {}",
                     self.peek_token()
                 );
             }
         }
 
-        (columns, constraints)
+        Ok((columns, constraints))
     }
 
-    /// Parse single table constraint.
-    pub fn parse_optional_table_constraint(&mut self) -> Option<TableConstraint> {
+    /// Parse single table constraint or index definition. Returns `None` and consumes no tokens
+    /// if the next create definition is a column definition.
+    pub fn parse_optional_table_constraint(
+        &mut self,
+    ) -> Result<Option<TableConstraint>, ParserError> {
+        let index = self.index;
         // [CONSTRAINT [symbol]]
-        if self.parse_keyword(Keyword::CONSTRAINT) {
+        if self.parse_keyword(Keyword::CONSTRAINT) && !self.peek_constraint_keyword() {
             self.parse_optional_identifier();
         }
 
         // PRIMARY KEY [index_type] (key_part,...) [index_option] ...
         if self.parse_keywords(&[Keyword::PRIMARY, Keyword::KEY]) {
             self.parse_optional_index_type();
-            let columns = self.parse_parenthesized_column_list(false);
-            self.parse_ending_of_create_definition();
-            return Some(TableConstraint::PrimaryKeys { columns });
+            let columns = self.parse_key_parts()?;
+            return Ok(Some(TableConstraint::PrimaryKeys { columns }));
         }
 
         // UNIQUE [INDEX | KEY] [index_name] [index_type] (key_part,...) [index_option] ...
         if self.parse_keyword(Keyword::UNIQUE) {
             let _ = self.parse_one_of_keywords(&[Keyword::INDEX, Keyword::KEY]);
-            self.parse_optional_identifier();
+            self.parse_optional_index_name();
             self.parse_optional_index_type();
-            self.parse_parenthesized_column_list(false);
-            self.parse_ending_of_create_definition();
+            let columns = self.parse_key_parts()?;
+            return Ok(Some(TableConstraint::Unique { columns }));
         }
 
         // FOREIGN KEY [index_name] (col_name,...) reference_definition
         if self.parse_keywords(&[Keyword::FOREIGN, Keyword::KEY]) {
-            self.parse_optional_identifier();
-            self.parse_parenthesized_column_list(false);
-            self.expect_reference_definition();
+            self.parse_optional_index_name();
+            let columns = self.parse_parenthesized_column_list(false)?;
+            let (foreign_table, referred_columns) = self.expect_reference_definition()?;
+            return Ok(Some(TableConstraint::ForeignKey {
+                columns,
+                foreign_table,
+                referred_columns,
+            }));
         }
 
         // CHECK (expr) [[NOT] ENFORCED]
         if self.parse_keyword(Keyword::CHECK) {
-            self.expect_token(&Token::LParen);
-            self.parse_parenthesized_expr();
-            self.expect_token(&Token::RParen);
-            self.parse_ending_of_create_definition();
+            let expr = self.parse_parenthesized_expr()?;
+            return Ok(Some(TableConstraint::Check { expr }));
         }
 
-        None
+        // {INDEX | KEY} [index_name] [index_type] (key_part,...) [index_option] ...
+        // {FULLTEXT | SPATIAL} [INDEX | KEY] [index_name] (key_part,...) [index_option] ...
+        if self.index == index {
+            let is_index = if self
+                .parse_one_of_keywords(&[Keyword::FULLTEXT, Keyword::SPATIAL])
+                .is_some()
+            {
+                let _ = self.parse_one_of_keywords(&[Keyword::INDEX, Keyword::KEY]);
+                true
+            } else {
+                self.parse_one_of_keywords(&[Keyword::INDEX, Keyword::KEY])
+                    .is_some()
+            };
+            if is_index {
+                self.parse_optional_index_name();
+                self.parse_optional_index_type();
+                let columns = self.parse_key_parts()?;
+                return Ok(Some(TableConstraint::Index { columns }));
+            }
+        }
+
+        self.index = index;
+        Ok(None)
+    }
+
+    fn peek_constraint_keyword(&self) -> bool {
+        matches!(
+            self.peek_token().token,
+            Token::Word(w) if matches!(
+                w.keyword,
+                Keyword::PRIMARY | Keyword::UNIQUE | Keyword::FOREIGN | Keyword::CHECK
+            )
+        )
+    }
+
+    // An index name is optional, and is followed by either `USING` or the key parts.
+    fn parse_optional_index_name(&mut self) -> Option<Ident> {
+        match self.peek_token().token {
+            Token::Word(w) if w.keyword == Keyword::USING => None,
+            _ => self.parse_optional_identifier(),
+        }
+    }
+
+    // key_part: {col_name [(length)] | (expr)} [ASC | DESC]
+    //
+    // Returns the names of the columns, where the expressions are skipped.
+    pub fn parse_key_parts(&mut self) -> Result<Vec<Ident>, ParserError> {
+        self.expect_token(&Token::LParen)?;
+        let mut columns = vec![];
+        loop {
+            if self.peek_token() == Token::LParen {
+                self.parse_parenthesized_expr()?;
+            } else {
+                columns.push(self.expect_identifier()?);
+                if self.peek_token() == Token::LParen {
+                    self.parse_precision()?;
+                }
+            }
+            let _ = self.parse_one_of_keywords(&[Keyword::ASC, Keyword::DESC]);
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect_token(&Token::RParen)?;
+        Ok(columns)
     }
 
     // index_type:
@@ -252,15 +785,15 @@ impl Parser {
 
     // According to https://dev.mysql.com/doc/refman/8.0/en/create-table.html, a create definition can be
     // a column definition or a table constraints.  Its ending is supposed to be a comma or a right parenthesis.
-    pub fn parse_ending_of_create_definition(&mut self) {
+    pub fn parse_ending_of_create_definition(&mut self) -> Result<(), ParserError> {
         loop {
             let next_token = self.next_token().token;
             if next_token == Token::Comma || next_token == Token::RParen {
                 self.prev_token();
-                break;
+                return Ok(());
             }
             if next_token == Token::EOF {
-                panic!("Expected comma or right parenthesis, found EOF, which is an expected ending of a column definition")
+                return parser_err!("Expected comma or right parenthesis, found EOF, which is an expected ending of a column definition");
             }
         }
     }
@@ -271,30 +804,32 @@ impl Parser {
     //     [ON DELETE reference_option]
     //     [ON UPDATE reference_option]
     //
-    // Returns false if reference definition doesn't exist.
-    #[must_use]
-    pub fn parse_reference_definition(&mut self) -> bool {
+    // Returns the referenced table and columns, or `None` if reference definition doesn't exist.
+    pub fn parse_reference_definition(
+        &mut self,
+    ) -> Result<Option<(ObjectName, Vec<Ident>)>, ParserError> {
         if !self.parse_keyword(Keyword::REFERENCES) {
-            return false;
+            return Ok(None);
         }
-        self.parse_object_name();
-        self.parse_parenthesized_column_list(false);
+        let foreign_table = self.parse_object_name()?;
+        let referred_columns = self.parse_parenthesized_column_list(false)?;
 
         if self.parse_keyword(Keyword::MATCH) {
             let _ = self.parse_one_of_keywords(&[Keyword::FULL, Keyword::PARTIAL, Keyword::SIMPLE]);
         }
 
-        if self.parse_keyword(Keyword::ON)
+        while self.parse_keyword(Keyword::ON)
             && (self.parse_keyword(Keyword::DELETE) || self.parse_keyword(Keyword::UPDATE))
         {
             self.parse_reference_option();
         }
-        true
+        Ok(Some((foreign_table, referred_columns)))
     }
 
-    pub fn expect_reference_definition(&mut self) {
-        if !self.parse_reference_definition() {
-            panic!("Expected REFERENCES, found: {}", self.peek_token());
+    pub fn expect_reference_definition(&mut self) -> Result<(ObjectName, Vec<Ident>), ParserError> {
+        match self.parse_reference_definition()? {
+            Some(reference) => Ok(reference),
+            None => parser_err!("Expected REFERENCES, found: {}", self.peek_token()),
         }
     }
 
@@ -308,30 +843,46 @@ impl Parser {
             || self.parse_keywords(&[Keyword::NO, Keyword::ACTION]);
     }
 
-    pub fn parse_column_def(&mut self) -> ColumnDef {
+    pub fn parse_column_def(&mut self) -> Result<ColumnDef, ParserError> {
         // data_type [NOT NULL | NULL]
-        let name = self.expect_identifier();
-        let data_type = self.parse_data_type();
+        let name = self.expect_identifier()?;
+        let data_type = self.parse_data_type()?;
 
         let mut options = vec![];
-        while let Some(option) = self.parse_optional_column_option() {
-            options.push(option);
+        loop {
+            match self.peek_token().token {
+                Token::Comma | Token::RParen | Token::EOF | Token::SemiColon => break,
+                // The position of a column added or modified by `ALTER TABLE`.
+                Token::Word(w) if matches!(w.keyword, Keyword::FIRST | Keyword::AFTER) => break,
+                _ => {}
+            }
+            let index = self.index;
+            if let Some(option) = self.parse_optional_column_option()? {
+                options.push(option);
+            } else if self.index == index {
+                // Skip an unsupported option, e.g. `UNSIGNED` after `DECIMAL` or `SRID 4326`.
+                if self.peek_token() == Token::LParen {
+                    self.parse_parenthesized_expr()?;
+                } else {
+                    self.next_token();
+                }
+            }
         }
-        ColumnDef {
+        Ok(ColumnDef {
             name,
             data_type,
             options,
-        }
+        })
     }
 
     /// Parse a SQL datatype (in the context of a CREATE TABLE statement for example)
-    pub fn parse_data_type(&mut self) -> DataType {
+    pub fn parse_data_type(&mut self) -> Result<DataType, ParserError> {
         let next_token = self.next_token();
-        match next_token.token {
+        Ok(match next_token.token {
             Token::Word(w) => match w.keyword {
                 Keyword::BOOLEAN => DataType::Boolean,
                 Keyword::BOOL => DataType::Bool,
-                Keyword::FLOAT => DataType::Float(self.parse_optional_precision()),
+                Keyword::FLOAT => DataType::Float(self.parse_optional_precision()?),
                 Keyword::REAL => DataType::Real,
                 Keyword::FLOAT4 => DataType::Float4,
                 Keyword::FLOAT64 => DataType::Float64,
@@ -344,7 +895,7 @@ impl Parser {
                     }
                 }
                 Keyword::TINYINT => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedTinyInt(optional_precision)
                     } else {
@@ -352,7 +903,7 @@ impl Parser {
                     }
                 }
                 Keyword::INT2 => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedInt2(optional_precision)
                     } else {
@@ -360,7 +911,7 @@ impl Parser {
                     }
                 }
                 Keyword::SMALLINT => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedSmallInt(optional_precision)
                     } else {
@@ -368,7 +919,7 @@ impl Parser {
                     }
                 }
                 Keyword::MEDIUMINT => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedMediumInt(optional_precision)
                     } else {
@@ -376,7 +927,7 @@ impl Parser {
                     }
                 }
                 Keyword::INT => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedInt(optional_precision)
                     } else {
@@ -384,7 +935,7 @@ impl Parser {
                     }
                 }
                 Keyword::INT4 => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedInt4(optional_precision)
                     } else {
@@ -393,7 +944,7 @@ impl Parser {
                 }
                 Keyword::INT64 => DataType::Int64,
                 Keyword::INTEGER => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedInteger(optional_precision)
                     } else {
@@ -401,7 +952,7 @@ impl Parser {
                     }
                 }
                 Keyword::BIGINT => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedBigInt(optional_precision)
                     } else {
@@ -409,48 +960,48 @@ impl Parser {
                     }
                 }
                 Keyword::INT8 => {
-                    let optional_precision = self.parse_optional_precision();
+                    let optional_precision = self.parse_optional_precision()?;
                     if self.parse_keyword(Keyword::UNSIGNED) {
                         DataType::UnsignedInt8(optional_precision)
                     } else {
                         DataType::Int8(optional_precision)
                     }
                 }
-                Keyword::VARCHAR => DataType::Varchar(self.parse_optional_character_length()),
-                Keyword::NVARCHAR => DataType::Nvarchar(self.parse_optional_precision()),
+                Keyword::VARCHAR => DataType::Varchar(self.parse_optional_character_length()?),
+                Keyword::NVARCHAR => DataType::Nvarchar(self.parse_optional_precision()?),
                 Keyword::CHARACTER => {
                     if self.parse_keyword(Keyword::VARYING) {
-                        DataType::CharacterVarying(self.parse_optional_character_length())
+                        DataType::CharacterVarying(self.parse_optional_character_length()?)
                     } else if self.parse_keywords(&[Keyword::LARGE, Keyword::OBJECT]) {
-                        DataType::CharacterLargeObject(self.parse_optional_precision())
+                        DataType::CharacterLargeObject(self.parse_optional_precision()?)
                     } else {
-                        DataType::Character(self.parse_optional_character_length())
+                        DataType::Character(self.parse_optional_character_length()?)
                     }
                 }
                 Keyword::CHAR => {
                     if self.parse_keyword(Keyword::VARYING) {
-                        DataType::CharVarying(self.parse_optional_character_length())
+                        DataType::CharVarying(self.parse_optional_character_length()?)
                     } else if self.parse_keywords(&[Keyword::LARGE, Keyword::OBJECT]) {
-                        DataType::CharLargeObject(self.parse_optional_precision())
+                        DataType::CharLargeObject(self.parse_optional_precision()?)
                     } else {
-                        DataType::Char(self.parse_optional_character_length())
+                        DataType::Char(self.parse_optional_character_length()?)
                     }
                 }
-                Keyword::CLOB => DataType::Clob(self.parse_optional_precision()),
-                Keyword::BINARY => DataType::Binary(self.parse_optional_precision()),
-                Keyword::VARBINARY => DataType::Varbinary(self.parse_optional_precision()),
-                Keyword::BLOB => DataType::Blob(self.parse_optional_precision()),
-                Keyword::BYTES => DataType::Bytes(self.parse_optional_precision()),
+                Keyword::CLOB => DataType::Clob(self.parse_optional_precision()?),
+                Keyword::BINARY => DataType::Binary(self.parse_optional_precision()?),
+                Keyword::VARBINARY => DataType::Varbinary(self.parse_optional_precision()?),
+                Keyword::BLOB => DataType::Blob(self.parse_optional_precision()?),
+                Keyword::BYTES => DataType::Bytes(self.parse_optional_precision()?),
                 Keyword::UUID => DataType::Uuid,
                 Keyword::DATE => DataType::Date,
-                Keyword::DATETIME => DataType::Datetime(self.parse_optional_precision()),
+                Keyword::DATETIME => DataType::Datetime(self.parse_optional_precision()?),
                 Keyword::TIMESTAMP => {
-                    let precision = self.parse_optional_precision();
+                    let precision = self.parse_optional_precision()?;
                     let tz = if self.parse_keyword(Keyword::WITH) {
-                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE]);
+                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE])?;
                         TimezoneInfo::WithTimeZone
                     } else if self.parse_keyword(Keyword::WITHOUT) {
-                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE]);
+                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE])?;
                         TimezoneInfo::WithoutTimeZone
                     } else {
                         TimezoneInfo::None
@@ -458,15 +1009,15 @@ impl Parser {
                     DataType::Timestamp(precision, tz)
                 }
                 Keyword::TIMESTAMPTZ => {
-                    DataType::Timestamp(self.parse_optional_precision(), TimezoneInfo::Tz)
+                    DataType::Timestamp(self.parse_optional_precision()?, TimezoneInfo::Tz)
                 }
                 Keyword::TIME => {
-                    let precision = self.parse_optional_precision();
+                    let precision = self.parse_optional_precision()?;
                     let tz = if self.parse_keyword(Keyword::WITH) {
-                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE]);
+                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE])?;
                         TimezoneInfo::WithTimeZone
                     } else if self.parse_keyword(Keyword::WITHOUT) {
-                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE]);
+                        self.expect_keywords(&[Keyword::TIME, Keyword::ZONE])?;
                         TimezoneInfo::WithoutTimeZone
                     } else {
                         TimezoneInfo::None
@@ -474,7 +1025,7 @@ impl Parser {
                     DataType::Time(precision, tz)
                 }
                 Keyword::TIMETZ => {
-                    DataType::Time(self.parse_optional_precision(), TimezoneInfo::Tz)
+                    DataType::Time(self.parse_optional_precision()?, TimezoneInfo::Tz)
                 }
                 // Interval types can be followed by a complicated interval
                 // qualifier that we don't currently support. See
@@ -483,39 +1034,39 @@ impl Parser {
                 Keyword::JSON => DataType::JSON,
                 Keyword::JSONB => DataType::JSONB,
                 Keyword::REGCLASS => DataType::Regclass,
-                Keyword::STRING => DataType::String(self.parse_optional_precision()),
+                Keyword::STRING => DataType::String(self.parse_optional_precision()?),
                 Keyword::TEXT => DataType::Text,
                 Keyword::BYTEA => DataType::Bytea,
                 Keyword::NUMERIC => {
-                    DataType::Numeric(self.parse_exact_number_optional_precision_scale())
+                    DataType::Numeric(self.parse_exact_number_optional_precision_scale()?)
                 }
                 Keyword::DECIMAL => {
-                    DataType::Decimal(self.parse_exact_number_optional_precision_scale())
+                    DataType::Decimal(self.parse_exact_number_optional_precision_scale()?)
                 }
-                Keyword::DEC => DataType::Dec(self.parse_exact_number_optional_precision_scale()),
+                Keyword::DEC => DataType::Dec(self.parse_exact_number_optional_precision_scale()?),
                 Keyword::BIGNUMERIC => {
-                    DataType::BigNumeric(self.parse_exact_number_optional_precision_scale())
+                    DataType::BigNumeric(self.parse_exact_number_optional_precision_scale()?)
                 }
                 Keyword::BIGDECIMAL => {
-                    DataType::BigDecimal(self.parse_exact_number_optional_precision_scale())
+                    DataType::BigDecimal(self.parse_exact_number_optional_precision_scale()?)
                 }
-                Keyword::ENUM => DataType::Enum(self.parse_string_values()),
-                Keyword::SET => DataType::Set(self.parse_string_values()),
+                Keyword::ENUM => DataType::Enum(self.parse_string_values()?),
+                Keyword::SET => DataType::Set(self.parse_string_values()?),
                 _ => {
                     self.prev_token();
-                    let type_name = self.parse_object_name();
-                    if let Some(modifiers) = self.parse_optional_type_modifiers() {
+                    let type_name = self.parse_object_name()?;
+                    if let Some(modifiers) = self.parse_optional_type_modifiers()? {
                         DataType::Custom(type_name, modifiers)
                     } else {
                         DataType::Custom(type_name, vec![])
                     }
                 }
             },
-            _ => panic!("Expected a data type name, found {}", next_token),
-        }
+            _ => return parser_err!("Expected a data type name, found {}", next_token),
+        })
     }
 
-    pub fn parse_optional_type_modifiers(&mut self) -> Option<Vec<String>> {
+    pub fn parse_optional_type_modifiers(&mut self) -> Result<Option<Vec<String>>, ParserError> {
         if self.consume_token(&Token::LParen) {
             let mut modifiers = Vec::new();
             loop {
@@ -531,48 +1082,50 @@ impl Parser {
                     Token::RParen => {
                         break;
                     }
-                    _ => panic!("Expected type modifiers, found {}", next_token),
+                    _ => return parser_err!("Expected type modifiers, found {}", next_token),
                 }
             }
 
-            Some(modifiers)
+            Ok(Some(modifiers))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_precision(&mut self) -> u64 {
-        self.expect_token(&Token::LParen);
-        let n = self.parse_literal_uint();
-        self.expect_token(&Token::RParen);
-        n
+    pub fn parse_precision(&mut self) -> Result<u64, ParserError> {
+        self.expect_token(&Token::LParen)?;
+        let n = self.parse_literal_uint()?;
+        self.expect_token(&Token::RParen)?;
+        Ok(n)
     }
 
-    pub fn parse_optional_precision(&mut self) -> Option<u64> {
+    pub fn parse_optional_precision(&mut self) -> Result<Option<u64>, ParserError> {
         if self.consume_token(&Token::LParen) {
-            let n = self.parse_literal_uint();
-            self.expect_token(&Token::RParen);
-            Some(n)
+            let n = self.parse_literal_uint()?;
+            self.expect_token(&Token::RParen)?;
+            Ok(Some(n))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_optional_character_length(&mut self) -> Option<CharacterLength> {
+    pub fn parse_optional_character_length(
+        &mut self,
+    ) -> Result<Option<CharacterLength>, ParserError> {
         if self.consume_token(&Token::LParen) {
-            let character_length = self.parse_character_length();
-            self.expect_token(&Token::RParen);
-            Some(character_length)
+            let character_length = self.parse_character_length()?;
+            self.expect_token(&Token::RParen)?;
+            Ok(Some(character_length))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_character_length(&mut self) -> CharacterLength {
+    pub fn parse_character_length(&mut self) -> Result<CharacterLength, ParserError> {
         if self.parse_keyword(Keyword::MAX) {
-            return CharacterLength::Max;
+            return Ok(CharacterLength::Max);
         }
-        let length = self.parse_literal_uint();
+        let length = self.parse_literal_uint()?;
         let unit = if self.parse_keyword(Keyword::CHARACTERS) {
             Some(CharLengthUnits::Characters)
         } else if self.parse_keyword(Keyword::OCTETS) {
@@ -580,60 +1133,67 @@ impl Parser {
         } else {
             None
         };
-        CharacterLength::IntegerLength { length, unit }
+        Ok(CharacterLength::IntegerLength { length, unit })
     }
 
-    pub fn parse_optional_precision_scale(&mut self) -> (Option<u64>, Option<u64>) {
+    pub fn parse_optional_precision_scale(
+        &mut self,
+    ) -> Result<(Option<u64>, Option<u64>), ParserError> {
         if self.consume_token(&Token::LParen) {
-            let n = self.parse_literal_uint();
+            let n = self.parse_literal_uint()?;
             let scale = if self.consume_token(&Token::Comma) {
-                Some(self.parse_literal_uint())
+                Some(self.parse_literal_uint()?)
             } else {
                 None
             };
-            self.expect_token(&Token::RParen);
-            (Some(n), scale)
+            self.expect_token(&Token::RParen)?;
+            Ok((Some(n), scale))
         } else {
-            (None, None)
+            Ok((None, None))
         }
     }
 
-    pub fn parse_exact_number_optional_precision_scale(&mut self) -> ExactNumberInfo {
+    pub fn parse_exact_number_optional_precision_scale(
+        &mut self,
+    ) -> Result<ExactNumberInfo, ParserError> {
         if self.consume_token(&Token::LParen) {
-            let precision = self.parse_literal_uint();
+            let precision = self.parse_literal_uint()?;
             let scale = if self.consume_token(&Token::Comma) {
-                Some(self.parse_literal_uint())
+                Some(self.parse_literal_uint()?)
             } else {
                 None
             };
 
-            self.expect_token(&Token::RParen);
+            self.expect_token(&Token::RParen)?;
 
             match scale {
-                None => ExactNumberInfo::Precision(precision),
-                Some(scale) => ExactNumberInfo::PrecisionAndScale(precision, scale),
+                None => Ok(ExactNumberInfo::Precision(precision)),
+                Some(scale) => Ok(ExactNumberInfo::PrecisionAndScale(precision, scale)),
             }
         } else {
-            ExactNumberInfo::None
+            Ok(ExactNumberInfo::None)
         }
     }
 
     /// Parse a parenthesized comma-separated list of unqualified, possibly quoted identifiers
-    pub fn parse_parenthesized_column_list(&mut self, allow_empty: bool) -> Vec<Ident> {
+    pub fn parse_parenthesized_column_list(
+        &mut self,
+        allow_empty: bool,
+    ) -> Result<Vec<Ident>, ParserError> {
         if self.consume_token(&Token::LParen) {
             if allow_empty && self.peek_token().token == Token::RParen {
                 self.next_token();
-                vec![]
+                Ok(vec![])
             } else {
-                let cols = self.parse_comma_separated(|p| p.expect_identifier());
-                self.expect_token(&Token::RParen);
-                cols
+                let cols = self.parse_comma_separated(|p| p.expect_identifier())?;
+                self.expect_token(&Token::RParen)?;
+                Ok(cols)
             }
         } else {
-            panic!(
+            parser_err!(
                 "Expected a list of columns in parentheses, found {}",
                 self.peek_token()
-            );
+            )
         }
     }
 
@@ -653,27 +1213,28 @@ impl Parser {
     // [check_constraint_definition]
     //
     // This method returns `None` if it parses a skippable column option.
-    pub fn parse_optional_column_option(&mut self) -> Option<ColumnOption> {
-        if self.parse_keywords(&[Keyword::NOT, Keyword::NULL]) {
+    pub fn parse_optional_column_option(&mut self) -> Result<Option<ColumnOption>, ParserError> {
+        Ok(if self.parse_keywords(&[Keyword::NOT, Keyword::NULL]) {
             Some(ColumnOption::NotNull)
         } else if self.parse_keyword(Keyword::NULL) {
             Some(ColumnOption::Null)
         } else if self.parse_keyword(Keyword::DEFAULT) {
-            if !self.parse_literal() {
-                self.parse_parenthesized_expr();
-            }
-            None
+            Some(ColumnOption::Default(self.parse_default_value()?))
+        } else if self.parse_keywords(&[Keyword::ON, Keyword::UPDATE]) {
+            Some(ColumnOption::OnUpdate(self.parse_function_call()?))
+        } else if self.parse_keyword(Keyword::AUTO_INCREMENT) {
+            Some(ColumnOption::AutoIncrement)
+        } else if self.parse_keyword(Keyword::INVISIBLE) {
+            Some(ColumnOption::Invisible)
         } else if self
-            .parse_one_of_keywords(&[
-                Keyword::VISIBLE,
-                Keyword::INVISIBLE,
-                Keyword::AUTO_INCREMENT,
-                Keyword::VIRTUAL,
-                Keyword::STORED,
-            ])
+            .parse_one_of_keywords(&[Keyword::VISIBLE, Keyword::VIRTUAL, Keyword::STORED])
             .is_some()
         {
             None
+        } else if self.parse_keywords(&[Keyword::CHARACTER, Keyword::SET])
+            || self.parse_keyword(Keyword::CHARSET)
+        {
+            Some(ColumnOption::CharacterSet(self.expect_identifier()?))
         } else if self.parse_keyword(Keyword::UNIQUE) {
             let _ = self.parse_keyword(Keyword::KEY);
             None
@@ -682,10 +1243,9 @@ impl Parser {
         {
             Some(ColumnOption::PrimaryKey)
         } else if self.parse_keyword(Keyword::COMMENT) {
-            Some(ColumnOption::Comment(self.parse_literal_string()))
+            Some(ColumnOption::Comment(self.parse_literal_string()?))
         } else if self.parse_keyword(Keyword::COLLATE) {
-            self.expect_identifier();
-            None
+            Some(ColumnOption::Collate(self.expect_identifier()?))
         } else if self.parse_keyword(Keyword::COLUMN_FORMAT) {
             let _ =
                 self.parse_one_of_keywords(&[Keyword::FIXED, Keyword::DYNAMIC, Keyword::DEFAULT]);
@@ -694,7 +1254,7 @@ impl Parser {
             || self.parse_keyword(Keyword::SECONDARY_ENGINE_ATTRIBUTE)
         {
             let _ = self.consume_token(&Token::Eq);
-            self.parse_literal_string();
+            self.parse_literal_string()?;
             None
         } else if self.parse_keyword(Keyword::STORAGE) {
             let _ = self.parse_one_of_keywords(&[Keyword::DISK, Keyword::MEMORY]);
@@ -702,11 +1262,72 @@ impl Parser {
         } else if self.parse_keywords(&[Keyword::GENERATED, Keyword::ALWAYS, Keyword::AS])
             || self.parse_keyword(Keyword::AS)
         {
-            self.parse_parenthesized_expr();
-            None
+            let expr = self.parse_parenthesized_expr()?;
+            let stored = self.parse_keyword(Keyword::STORED);
+            if !stored {
+                let _ = self.parse_keyword(Keyword::VIRTUAL);
+            }
+            Some(ColumnOption::Generated { expr, stored })
         } else {
-            let _ = self.parse_reference_definition() || self.parse_check_constraint_definition();
+            let _ = self.parse_reference_definition()?.is_some()
+                || self.parse_check_constraint_definition()?;
             None
+        })
+    }
+
+    // DEFAULT {literal | (expr)}
+    //
+    // MySQL also allows `CURRENT_TIMESTAMP[(fsp)]` without parentheses, and prints the bit values
+    // as `b'...'` and the strings with a charset introducer as `_charset'...'`.
+    pub fn parse_default_value(&mut self) -> Result<Value, ParserError> {
+        let next_token = self.next_token();
+        Ok(match next_token.token {
+            Token::Number(n, l) => Value::Number(n, l),
+            Token::Minus => match self.next_token().token {
+                Token::Number(n, l) => Value::Number(format!("-{n}"), l),
+                token => return parser_err!("Expected a number after '-', found {token}"),
+            },
+            Token::SingleQuotedString(s) => Value::SingleQuotedString(s),
+            Token::DoubleQuotedString(s) => Value::DoubleQuotedString(s),
+            Token::NationalStringLiteral(s) => Value::NationalStringLiteral(s),
+            Token::HexStringLiteral(s) => Value::HexStringLiteral(s),
+            Token::SingleQuotedByteStringLiteral(s) => Value::SingleQuotedByteStringLiteral(s),
+            Token::Word(w) if w.quote_style.is_none() && w.keyword == Keyword::NULL => Value::Null,
+            Token::Word(w) if w.quote_style.is_none() && w.keyword == Keyword::TRUE => {
+                Value::Boolean(true)
+            }
+            Token::Word(w) if w.quote_style.is_none() && w.keyword == Keyword::FALSE => {
+                Value::Boolean(false)
+            }
+            Token::Word(w) if w.quote_style.is_none() => match self.peek_token().token {
+                Token::SingleQuotedString(s) if w.value.eq_ignore_ascii_case("b") => {
+                    self.next_token();
+                    Value::SingleQuotedByteStringLiteral(s)
+                }
+                Token::SingleQuotedString(s) if w.value.starts_with('_') => {
+                    self.next_token();
+                    Value::SingleQuotedString(s)
+                }
+                _ => {
+                    self.prev_token();
+                    Value::UnQuotedString(self.parse_function_call()?)
+                }
+            },
+            Token::LParen => {
+                self.prev_token();
+                Value::UnQuotedString(format!("({})", self.parse_parenthesized_expr()?))
+            }
+            _ => return parser_err!("Expected a default value, found {next_token}"),
+        })
+    }
+
+    // Parse `name[(args)]` as written, e.g. `CURRENT_TIMESTAMP(3)`.
+    pub fn parse_function_call(&mut self) -> Result<String, ParserError> {
+        let name = self.expect_identifier()?;
+        if self.peek_token() == Token::LParen {
+            Ok(format!("{name}({})", self.parse_parenthesized_expr()?))
+        } else {
+            Ok(name.to_string())
         }
     }
 
@@ -714,40 +1335,96 @@ impl Parser {
     // [CONSTRAINT [symbol]] CHECK (expr) [[NOT] ENFORCED]
     //
     // Returns false if check constraint doesn't exist.
-    pub fn parse_check_constraint_definition(&mut self) -> bool {
+    pub fn parse_check_constraint_definition(&mut self) -> Result<bool, ParserError> {
         if self.parse_keyword(Keyword::CONSTRAINT) {
             self.parse_optional_identifier();
         }
-        if self.parse_keyword(Keyword::CHECK) {
-            return false;
+        if !self.parse_keyword(Keyword::CHECK) {
+            return Ok(false);
         }
-        self.parse_parenthesized_expr();
+        self.parse_parenthesized_expr()?;
         let _ = self.parse_keyword(Keyword::NOT);
         let _ = self.parse_keyword(Keyword::ENFORCED);
-        true
+        Ok(true)
+    }
+
+    // col_name {= | <> | != | < | <= | > | >=} literal
+    // | col_name IS [NOT] NULL
+    // | col_name [NOT] IN (literal, ...)
+    pub fn parse_predicate(&mut self) -> Result<Predicate, ParserError> {
+        let next_token = self.next_token();
+        let column = match next_token.token {
+            Token::Word(w) => w.to_ident(),
+            _ => return parser_err!("Expected a column name, found {next_token}"),
+        };
+        if self.parse_keyword(Keyword::IS) {
+            let negated = self.parse_keyword(Keyword::NOT);
+            self.expect_keyword(Keyword::NULL)?;
+            return Ok(Predicate::IsNull { column, negated });
+        }
+        let negated = self.parse_keyword(Keyword::NOT);
+        if self.parse_keyword(Keyword::IN) {
+            self.expect_token(&Token::LParen)?;
+            let list = self.parse_comma_separated(Parser::parse_literal_value)?;
+            self.expect_token(&Token::RParen)?;
+            return Ok(Predicate::InList {
+                column,
+                list,
+                negated,
+            });
+        }
+        if negated {
+            return parser_err!("Expected IN after NOT, found {}", self.peek_token());
+        }
+        let next_token = self.next_token();
+        let op = match next_token.token {
+            Token::Eq => CompareOperator::Eq,
+            Token::Neq => CompareOperator::NotEq,
+            Token::Lt => CompareOperator::Lt,
+            Token::LtEq => CompareOperator::LtEq,
+            Token::Gt => CompareOperator::Gt,
+            Token::GtEq => CompareOperator::GtEq,
+            _ => return parser_err!("Expected a comparison operator, found {next_token}"),
+        };
+        let value = self.parse_literal_value()?;
+        Ok(Predicate::Compare { column, op, value })
+    }
+
+    /// Parse a number or a string literal.
+    pub fn parse_literal_value(&mut self) -> Result<Value, ParserError> {
+        match self.parse_default_value()? {
+            value @ (Value::Number(..)
+            | Value::SingleQuotedString(_)
+            | Value::DoubleQuotedString(_)
+            | Value::NationalStringLiteral(_)) => Ok(value),
+            value => parser_err!("Expected a literal, found {value}"),
+        }
     }
 
     /// Parse a literal string
-    pub fn parse_literal_string(&mut self) -> String {
+    pub fn parse_literal_string(&mut self) -> Result<String, ParserError> {
         let next_token = self.next_token();
         match next_token.token {
             Token::Word(Word {
                 value,
                 keyword: Keyword::NoKeyword,
                 ..
-            }) => value,
-            Token::SingleQuotedString(s) => s,
-            Token::DoubleQuotedString(s) => s,
-            _ => panic!("Expected a literal string, found {}", next_token),
+            }) => Ok(value),
+            Token::SingleQuotedString(s) => Ok(s),
+            Token::DoubleQuotedString(s) => Ok(s),
+            _ => parser_err!("Expected a literal string, found {}", next_token),
         }
     }
 
-    // Parse `(expr)`.
-    pub fn parse_parenthesized_expr(&mut self) {
-        self.expect_token(&Token::LParen);
+    // Parse `(expr)`. Returns the expression inside the parentheses as written.
+    pub fn parse_parenthesized_expr(&mut self) -> Result<String, ParserError> {
+        self.expect_token(&Token::LParen)?;
+        let mut expr = String::new();
         let mut paren = 1;
         loop {
-            let next_token = self.next_token();
+            let Some(next_token) = self.next_token_no_skip() else {
+                return parser_err!("Expected ')', found EOF");
+            };
             match next_token.token {
                 Token::LParen => paren += 1,
                 Token::RParen => {
@@ -758,7 +1435,9 @@ impl Parser {
                 }
                 _ => (),
             }
+            expr.push_str(&next_token.to_string());
         }
+        Ok(expr)
     }
 
     pub fn parse_literal(&mut self) -> bool {
@@ -779,31 +1458,33 @@ impl Parser {
     }
 
     /// Parse an unsigned literal integer/long
-    pub fn parse_literal_uint(&mut self) -> u64 {
+    pub fn parse_literal_uint(&mut self) -> Result<u64, ParserError> {
         let next_token = self.next_token();
         match next_token.token {
-            Token::Number(s, _) => s.parse::<u64>().unwrap(),
-            _ => panic!("Expected literal int, found {}", next_token),
+            Token::Number(s, _) => s.parse::<u64>().map_err(|e| {
+                ParserError::ParserError(format!("Could not parse '{s}' as u64: {e}"))
+            }),
+            _ => parser_err!("Expected literal int, found {}", next_token),
         }
     }
 
-    pub fn parse_string_values(&mut self) -> Vec<String> {
-        self.expect_token(&Token::LParen);
+    pub fn parse_string_values(&mut self) -> Result<Vec<String>, ParserError> {
+        self.expect_token(&Token::LParen)?;
         let mut values = Vec::new();
         loop {
             let next_token = self.next_token();
             match next_token.token {
                 Token::SingleQuotedString(value) => values.push(value),
-                _ => panic!("Expected a string, found {}", next_token),
+                _ => return parser_err!("Expected a string, found {}", next_token),
             }
             let next_token = self.next_token();
             match next_token.token {
                 Token::Comma => (),
                 Token::RParen => break,
-                _ => panic!("Expected , or }}, found {}", next_token),
+                _ => return parser_err!("Expected , or ), found {}", next_token),
             }
         }
-        values
+        Ok(values)
     }
 
     /// Return the first non-whitespace token that has not yet been processed
@@ -878,12 +1559,11 @@ impl Parser {
         self.tokens.get(self.index - 1)
     }
 
-    /// Push back the last one non-whitespace token. Must be called after
-    /// `next_token()`, otherwise might panic. OK to call after
-    /// `next_token()` indicates an EOF.
+    /// Push back the last one non-whitespace token. Supposed to be called after
+    /// `next_token()`, and does nothing at the beginning of the tokens. OK to
+    /// call after `next_token()` indicates an EOF.
     pub fn prev_token(&mut self) {
-        loop {
-            assert!(self.index > 0);
+        while self.index > 0 {
             self.index -= 1;
             if let Some(TokenWithLocation {
                 token: Token::Whitespace(_),
@@ -947,31 +1627,34 @@ impl Parser {
 
     /// If the current token is one of the expected keywords, consume the token
     /// and return the keyword that matches. Otherwise, return an error.
-    pub fn expect_one_of_keywords(&mut self, keywords: &[Keyword]) -> Keyword {
+    pub fn expect_one_of_keywords(&mut self, keywords: &[Keyword]) -> Result<Keyword, ParserError> {
         if let Some(keyword) = self.parse_one_of_keywords(keywords) {
-            keyword
+            Ok(keyword)
         } else {
             let keywords: Vec<String> = keywords.iter().map(|x| format!("{x:?}")).collect();
-            panic!(
+            parser_err!(
                 "Expected one of {}, found: {}",
                 keywords.join(" or "),
                 self.peek_token()
-            );
+            )
         }
     }
 
     /// If the current token is the `expected` keyword, consume the token.
     /// Otherwise return an error.
-    pub fn expect_keyword(&mut self, expected: Keyword) {
-        if !self.parse_keyword(expected) {
-            panic!("Expected {:?}, found: {}", expected, self.peek_token())
+    pub fn expect_keyword(&mut self, expected: Keyword) -> Result<(), ParserError> {
+        if self.parse_keyword(expected) {
+            Ok(())
+        } else {
+            parser_err!("Expected {:?}, found: {}", expected, self.peek_token())
         }
     }
 
-    pub fn expect_keywords(&mut self, expected: &[Keyword]) {
+    pub fn expect_keywords(&mut self, expected: &[Keyword]) -> Result<(), ParserError> {
         for &kw in expected {
-            self.expect_keyword(kw);
+            self.expect_keyword(kw)?;
         }
+        Ok(())
     }
 
     /// Consume the next token if it matches the expected token, otherwise return false
@@ -986,9 +1669,11 @@ impl Parser {
     }
 
     /// Bail out if the current token is not an expected keyword, or consume it if it is
-    pub fn expect_token(&mut self, expected: &Token) {
-        if !self.consume_token(expected) {
-            panic!(
+    pub fn expect_token(&mut self, expected: &Token) -> Result<(), ParserError> {
+        if self.consume_token(expected) {
+            Ok(())
+        } else {
+            parser_err!(
                 "Expected: {}, found: {}",
                 &expected.to_string(),
                 self.peek_token()
@@ -997,21 +1682,19 @@ impl Parser {
     }
 
     /// Parse a comma-separated list of 1+ items accepted by `F`
-    pub fn parse_comma_separated<T, F>(&mut self, mut f: F) -> Vec<T>
+    pub fn parse_comma_separated<T, F>(&mut self, mut f: F) -> Result<Vec<T>, ParserError>
     where
-        F: FnMut(&mut Parser) -> T,
+        F: FnMut(&mut Parser) -> Result<T, ParserError>,
     {
         let mut values = vec![];
         loop {
-            values.push(f(self));
+            values.push(f(self)?);
             if !self.consume_token(&Token::Comma) {
                 break;
             } else if TRAILING_COMMAS {
                 match self.peek_token().token {
                     Token::Word(kw)
-                        if keywords::RESERVED_FOR_COLUMN_ALIAS
-                            .iter()
-                            .any(|d| kw.keyword == *d) =>
+                        if keywords::RESERVED_FOR_COLUMN_ALIAS.contains(&kw.keyword) =>
                     {
                         break;
                     }
@@ -1024,20 +1707,20 @@ impl Parser {
                 }
             }
         }
-        values
+        Ok(values)
     }
 
     /// Parse a possibly qualified, possibly quoted identifier, e.g.
     /// `foo` or `myschema."table".
-    pub fn parse_object_name(&mut self) -> ObjectName {
+    pub fn parse_object_name(&mut self) -> Result<ObjectName, ParserError> {
         let mut idents = vec![];
         loop {
-            idents.push(self.expect_identifier());
+            idents.push(self.expect_identifier()?);
             if !self.consume_token(&Token::Period) {
                 break;
             }
         }
-        ObjectName(idents)
+        Ok(ObjectName(idents))
     }
 
     /// Parse identifiers
@@ -1069,8 +1752,10 @@ impl Parser {
         Some(ident)
     }
 
-    pub fn expect_identifier(&mut self) -> Ident {
-        self.parse_optional_identifier()
-            .unwrap_or_else(|| panic!("Expected identifier, found: {}", self.peek_token()))
+    pub fn expect_identifier(&mut self) -> Result<Ident, ParserError> {
+        match self.parse_optional_identifier() {
+            Some(ident) => Ok(ident),
+            None => parser_err!("Expected identifier, found: {}", self.peek_token()),
+        }
     }
 }
@@ -432,6 +432,8 @@ struct State<'a> {
     peekable: Peekable<Chars<'a>>,
     pub line: u64,
     pub col: u64,
+    /// The number of open executable comments, i.e. `/*! ... */`.
+    pub executable_comments: usize,
 }
 
 impl<'a> State<'a> {
@@ -476,17 +478,23 @@ impl<'a> Tokenizer<'a> {
     /// Create a new SQL tokenizer for the specified SQL statement
     ///
     /// ```
-    /// use sqlparser::tokenizer::{Token, Whitespace, Tokenizer};
+    /// use chgcap_mysql_ddl_parser::tokenizer::{Token, Whitespace, Tokenizer};
     /// let query = r#"SELECT 'foo'"#;
     ///
     /// // Parsing the query
-    /// let tokens = Tokenizer::new(&query).tokenize().unwrap();
+    /// let tokens: Vec<Token> = Tokenizer::new(&query)
+    ///   .tokenize_with_location()
+    ///   .unwrap()
+    ///   .into_iter()
+    ///   .map(|t| t.token)
+    ///   .collect();
     ///
     /// assert_eq!(tokens, vec![
     ///   Token::make_word("SELECT", None),
     ///   Token::Whitespace(Whitespace::Space),
     ///   Token::SingleQuotedString("foo".to_string()),
     /// ]);
+    /// ```
     pub fn new(query: &'a str) -> Self {
         Self {
             query,
@@ -511,6 +519,7 @@ impl<'a> Tokenizer<'a> {
             peekable: self.query.chars().peekable(),
             line: 1,
             col: 1,
+            executable_comments: 0,
         };
 
         let mut location = state.location();
@@ -538,6 +547,7 @@ impl<'a> Tokenizer<'a> {
                 peekable: word.chars().peekable(),
                 line: 0,
                 col: 0,
+                executable_comments: 0,
             };
             let mut s = peeking_take_while(&mut inner_state, |ch| matches!(ch, '0'..='9' | '.'));
             let s2 = peeking_take_while(chars, |ch| matches!(ch, '0'..='9' | '.'));
@@ -751,6 +761,16 @@ impl<'a> Tokenizer<'a> {
                     match chars.peek() {
                         Some('*') => {
                             chars.next(); // consume the '*', starting a multi-line comment
+                            if chars.peek() == Some(&'!') {
+                                // MySQL executes the content of `/*![version] ... */`, e.g.
+                                // `/*!80023 INVISIBLE */` printed by `SHOW CREATE TABLE`.
+                                chars.next();
+                                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                                    chars.next();
+                                }
+                                chars.executable_comments += 1;
+                                return Ok(Some(Token::Whitespace(Whitespace::Space)));
+                            }
                             self.tokenize_multiline_comment(chars)
                         }
                         // a regular '/' operator
@@ -758,7 +778,16 @@ impl<'a> Tokenizer<'a> {
                     }
                 }
                 '+' => self.consume_and_return(chars, Token::Plus),
-                '*' => self.consume_and_return(chars, Token::Mul),
+                '*' => {
+                    chars.next(); // consume the '*'
+                    if chars.executable_comments > 0 && chars.peek() == Some(&'/') {
+                        // the end of an executable comment
+                        chars.next();
+                        chars.executable_comments -= 1;
+                        return Ok(Some(Token::Whitespace(Whitespace::Space)));
+                    }
+                    Ok(Some(Token::Mul))
+                }
                 '%' => {
                     chars.next(); // advance past '%'
                     match chars.peek() {
@@ -0,0 +1,173 @@
+use chgcap_mysql_ddl_parser::ast::data_type::{CharacterLength, DataType};
+use chgcap_mysql_ddl_parser::ast::ddl::{
+    AlterColumnOperation, AlterTableOperation, ColumnOption, ColumnPosition, TableConstraint,
+    TableOption,
+};
+use chgcap_mysql_ddl_parser::ast::value::Value;
+use chgcap_mysql_ddl_parser::ast::{ColumnDef, Ident, ObjectName, Statement};
+use chgcap_mysql_ddl_parser::parser::Parser;
+
+fn column_def(name: &str, data_type: DataType, options: Vec<ColumnOption>) -> ColumnDef {
+    ColumnDef {
+        name: Ident::new(name),
+        data_type,
+        options,
+    }
+}
+
+#[test]
+fn test_parse_alter_table() {
+    let sql = "ALTER TABLE db.t
+  ADD COLUMN a INT NOT NULL FIRST,
+  ADD b VARCHAR(10) AFTER a,
+  ADD (c INT, d INT),
+  ADD INDEX idx (a) USING BTREE COMMENT 'x, y',
+  ADD PRIMARY KEY (a),
+  DROP COLUMN e,
+  DROP f,
+  DROP PRIMARY KEY,
+  DROP INDEX idx,
+  MODIFY g INT NULL,
+  CHANGE COLUMN h i INT AFTER a,
+  RENAME COLUMN j TO k,
+  RENAME INDEX idx TO idx2,
+  ALTER COLUMN l SET DEFAULT 1,
+  ALTER m DROP DEFAULT,
+  ALTER COLUMN n SET INVISIBLE,
+  ALGORITHM=INPLACE, LOCK=NONE,
+  CONVERT TO CHARACTER SET utf8mb4 COLLATE utf8mb4_bin,
+  DEFAULT CHARSET = latin1,
+  RENAME TO db.u
+  PARTITION BY HASH (a) PARTITIONS 2";
+    let Some(Statement::AlterTable { name, operations }) = Parser::parse_sql(sql).unwrap() else {
+        panic!("Failed to parse {sql}");
+    };
+    assert_eq!(name, ObjectName(vec![Ident::new("db"), Ident::new("t")]));
+    assert_eq!(
+        operations,
+        vec![
+            AlterTableOperation::AddColumn {
+                column_def: column_def("a", DataType::Int(None), vec![ColumnOption::NotNull]),
+                position: Some(ColumnPosition::First),
+            },
+            AlterTableOperation::AddColumn {
+                column_def: column_def(
+                    "b",
+                    DataType::Varchar(Some(CharacterLength::IntegerLength {
+                        length: 10,
+                        unit: None
+                    })),
+                    vec![]
+                ),
+                position: Some(ColumnPosition::After(Ident::new("a"))),
+            },
+            AlterTableOperation::AddColumn {
+                column_def: column_def("c", DataType::Int(None), vec![]),
+                position: None,
+            },
+            AlterTableOperation::AddColumn {
+                column_def: column_def("d", DataType::Int(None), vec![]),
+                position: None,
+            },
+            AlterTableOperation::AddConstraint(TableConstraint::Index {
+                columns: vec![Ident::new("a")]
+            }),
+            AlterTableOperation::AddConstraint(TableConstraint::PrimaryKeys {
+                columns: vec![Ident::new("a")]
+            }),
+            AlterTableOperation::DropColumn {
+                column_name: Ident::new("e")
+            },
+            AlterTableOperation::DropColumn {
+                column_name: Ident::new("f")
+            },
+            AlterTableOperation::DropPrimaryKey,
+            AlterTableOperation::ModifyColumn {
+                column_def: column_def("g", DataType::Int(None), vec![ColumnOption::Null]),
+                position: None,
+            },
+            AlterTableOperation::ChangeColumn {
+                old_name: Ident::new("h"),
+                column_def: column_def("i", DataType::Int(None), vec![]),
+                position: Some(ColumnPosition::After(Ident::new("a"))),
+            },
+            AlterTableOperation::RenameColumn {
+                old_name: Ident::new("j"),
+                new_name: Ident::new("k"),
+            },
+            AlterTableOperation::AlterColumn {
+                column_name: Ident::new("l"),
+                op: AlterColumnOperation::SetDefault(Value::Number("1".to_string(), false)),
+            },
+            AlterTableOperation::AlterColumn {
+                column_name: Ident::new("m"),
+                op: AlterColumnOperation::DropDefault,
+            },
+            AlterTableOperation::AlterColumn {
+                column_name: Ident::new("n"),
+                op: AlterColumnOperation::SetInvisible,
+            },
+            AlterTableOperation::ConvertToCharacterSet {
+                charset: Ident::new("utf8mb4"),
+                collation: Some(Ident::new("utf8mb4_bin")),
+            },
+            AlterTableOperation::TableOption(TableOption::CharacterSet(Ident::new("latin1"))),
+            AlterTableOperation::RenameTable {
+                table_name: ObjectName(vec![Ident::new("db"), Ident::new("u")]),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_drop_and_rename_table() {
+    assert_eq!(
+        Parser::parse_sql("DROP TABLE IF EXISTS `t1`, db.t2 /* generated by server */").unwrap(),
+        Some(Statement::DropTable {
+            temporary: false,
+            if_exists: true,
+            names: vec![
+                ObjectName(vec![Ident::with_quote('`', "t1")]),
+                ObjectName(vec![Ident::new("db"), Ident::new("t2")]),
+            ],
+        })
+    );
+    assert_eq!(
+        Parser::parse_sql("DROP TEMPORARY TABLE t").unwrap(),
+        Some(Statement::DropTable {
+            temporary: true,
+            if_exists: false,
+            names: vec![ObjectName(vec![Ident::new("t")])],
+        })
+    );
+    assert_eq!(
+        Parser::parse_sql("DROP DATABASE IF EXISTS db").unwrap(),
+        Some(Statement::DropDatabase {
+            if_exists: true,
+            name: Ident::new("db"),
+        })
+    );
+    assert_eq!(
+        Parser::parse_sql("RENAME TABLE a TO b, db.c TO db.d").unwrap(),
+        Some(Statement::RenameTable {
+            renames: vec![
+                (
+                    ObjectName(vec![Ident::new("a")]),
+                    ObjectName(vec![Ident::new("b")])
+                ),
+                (
+                    ObjectName(vec![Ident::new("db"), Ident::new("c")]),
+                    ObjectName(vec![Ident::new("db"), Ident::new("d")])
+                ),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_parse_invalid_alter_table() {
+    assert!(Parser::parse_sql("ALTER TABLE t ALTER COLUMN a").is_err());
+    assert!(Parser::parse_sql("ALTER TABLE t ADD COLUMN").is_err());
+    assert!(Parser::parse_sql("ALTER TABLE t RENAME COLUMN a b").is_err());
+    assert!(Parser::parse_sql("ALTER TABLE t ADD INDEX (a))").is_err());
+}
@@ -0,0 +1,305 @@
+use chgcap_mysql_ddl_parser::ast::data_type::{CharacterLength, DataType, ExactNumberInfo};
+use chgcap_mysql_ddl_parser::ast::ddl::{ColumnOption, TableConstraint, TableOption};
+use chgcap_mysql_ddl_parser::ast::value::Value;
+use chgcap_mysql_ddl_parser::ast::{ColumnDef, Ident, ObjectName, Statement};
+use chgcap_mysql_ddl_parser::parser::Parser;
+
+#[test]
+fn test_parse_show_create_table() {
+    let sql = "CREATE TABLE `orders` (
+  `id` bigint unsigned NOT NULL AUTO_INCREMENT,
+  `name` varchar(20) COLLATE utf8mb4_bin DEFAULT NULL COMMENT 'the name',
+  `code` char(4) CHARACTER SET latin1 NOT NULL DEFAULT 'x,y',
+  `price` decimal(10,2) unsigned DEFAULT '0.00',
+  `flag` bit(1) DEFAULT b'1',
+  `status` enum('new','paid') NOT NULL DEFAULT 'new',
+  `total` decimal(12,2) GENERATED ALWAYS AS ((`price` * 2)) STORED,
+  `hidden` int DEFAULT '-1' /*!80023 INVISIBLE */,
+  `updated_at` timestamp(3) NULL DEFAULT CURRENT_TIMESTAMP(3) ON UPDATE CURRENT_TIMESTAMP(3),
+  `doc` json DEFAULT (json_array()),
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `name` (`name`(10),`code` DESC),
+  KEY `idx_status` (`status`) USING BTREE,
+  FULLTEXT KEY `ft` (`name`),
+  CONSTRAINT `fk` FOREIGN KEY (`code`) REFERENCES `codes` (`code`) ON DELETE CASCADE ON UPDATE SET NULL,
+  CONSTRAINT `chk` CHECK ((`price` >= 0))
+) ENGINE=InnoDB AUTO_INCREMENT=3 DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci";
+    let Some(Statement::CreateTable {
+        name,
+        columns,
+        constraints,
+        ..
+    }) = Parser::parse_sql(sql).unwrap()
+    else {
+        panic!("Failed to parse {sql}");
+    };
+    assert_eq!(name.to_string(), "`orders`");
+
+    let names: Vec<&str> = columns.iter().map(|c| c.name.value.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "id",
+            "name",
+            "code",
+            "price",
+            "flag",
+            "status",
+            "total",
+            "hidden",
+            "updated_at",
+            "doc"
+        ]
+    );
+    assert_eq!(columns[0].data_type, DataType::UnsignedBigInt(None));
+    assert_eq!(
+        columns[0].options,
+        vec![ColumnOption::NotNull, ColumnOption::AutoIncrement]
+    );
+    assert_eq!(
+        columns[1].data_type,
+        DataType::Varchar(Some(CharacterLength::IntegerLength {
+            length: 20,
+            unit: None
+        }))
+    );
+    assert_eq!(
+        columns[1].options,
+        vec![
+            ColumnOption::Collate(Ident::new("utf8mb4_bin")),
+            ColumnOption::Default(Value::Null),
+            ColumnOption::Comment("the name".to_string()),
+        ]
+    );
+    assert_eq!(
+        columns[2].options,
+        vec![
+            ColumnOption::CharacterSet(Ident::new("latin1")),
+            ColumnOption::NotNull,
+            ColumnOption::Default(Value::SingleQuotedString("x,y".to_string())),
+        ]
+    );
+    assert_eq!(
+        columns[3].data_type,
+        DataType::Decimal(ExactNumberInfo::PrecisionAndScale(10, 2))
+    );
+    assert_eq!(
+        columns[3].options,
+        vec![ColumnOption::Default(Value::SingleQuotedString(
+            "0.00".to_string()
+        ))]
+    );
+    assert_eq!(
+        columns[4].options,
+        vec![ColumnOption::Default(Value::SingleQuotedByteStringLiteral(
+            "1".to_string()
+        ))]
+    );
+    assert_eq!(
+        columns[5].data_type,
+        DataType::Enum(vec!["new".to_string(), "paid".to_string()])
+    );
+    assert_eq!(
+        columns[6].options,
+        vec![ColumnOption::Generated {
+            expr: "(`price` * 2)".to_string(),
+            stored: true
+        }]
+    );
+    assert_eq!(
+        columns[7].options,
+        vec![
+            ColumnOption::Default(Value::SingleQuotedString("-1".to_string())),
+            ColumnOption::Invisible,
+        ]
+    );
+    assert_eq!(
+        columns[8].options,
+        vec![
+            ColumnOption::Null,
+            ColumnOption::Default(Value::UnQuotedString("CURRENT_TIMESTAMP(3)".to_string())),
+            ColumnOption::OnUpdate("CURRENT_TIMESTAMP(3)".to_string()),
+        ]
+    );
+    assert_eq!(
+        columns[9].options,
+        vec![ColumnOption::Default(Value::UnQuotedString(
+            "(json_array())".to_string()
+        ))]
+    );
+
+    assert_eq!(
+        constraints,
+        vec![
+            TableConstraint::PrimaryKeys {
+                columns: vec![Ident::with_quote('`', "id")]
+            },
+            TableConstraint::Unique {
+                columns: vec![
+                    Ident::with_quote('`', "name"),
+                    Ident::with_quote('`', "code")
+                ]
+            },
+            TableConstraint::Index {
+                columns: vec![Ident::with_quote('`', "status")]
+            },
+            TableConstraint::Index {
+                columns: vec![Ident::with_quote('`', "name")]
+            },
+            TableConstraint::ForeignKey {
+                columns: vec![Ident::with_quote('`', "code")],
+                foreign_table: ObjectName(vec![Ident::with_quote('`', "codes")]),
+                referred_columns: vec![Ident::with_quote('`', "code")],
+            },
+            TableConstraint::Check {
+                expr: "(`price` >= 0)".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_unsupported_statement() {
+    assert_eq!(Parser::parse_sql("ALTER VIEW v AS SELECT 1"), Ok(None));
+    assert_eq!(
+        Parser::parse_sql("CREATE TEMPORARY TABLE t (a INT)").unwrap(),
+        Some(Statement::CreateTable {
+            temporary: true,
+            if_not_exists: false,
+            name: ObjectName(vec![Ident::new("t")]),
+            columns: vec![ColumnDef {
+                name: Ident::new("a"),
+                data_type: DataType::Int(None),
+                options: vec![],
+            }],
+            constraints: vec![],
+            table_options: vec![],
+            with_query: false,
+        })
+    );
+}
+
+#[test]
+fn test_parse_table_options() {
+    let sql = "CREATE TABLE `t` (`a` int) ENGINE=InnoDB DEFAULT CHARSET=latin1 \
+        COMMENT='x (y)' COLLATE latin1_bin PARTITION BY HASH (`a`) PARTITIONS 2";
+    let Some(Statement::CreateTable {
+        table_options,
+        with_query,
+        ..
+    }) = Parser::parse_sql(sql).unwrap()
+    else {
+        panic!("Failed to parse {sql}");
+    };
+    assert_eq!(
+        table_options,
+        vec![
+            TableOption::CharacterSet(Ident::new("latin1")),
+            TableOption::Collate(Ident::new("latin1_bin")),
+        ]
+    );
+    assert!(!with_query);
+
+    for sql in [
+        "CREATE TABLE t (a INT) AS SELECT 1 AS a",
+        "CREATE TABLE t (a INT) SELECT 1 AS a",
+        "CREATE TABLE t (a INT) CHARSET utf8mb4 (SELECT 1 AS a)",
+        "CREATE TABLE t (a INT) IGNORE SELECT 1 AS a",
+    ] {
+        let Some(Statement::CreateTable { with_query, .. }) = Parser::parse_sql(sql).unwrap()
+        else {
+            panic!("Failed to parse {sql}");
+        };
+        assert!(with_query, "{sql}");
+    }
+}
+
+#[test]
+fn test_parse_create_table_like() {
+    for sql in [
+        "CREATE TABLE IF NOT EXISTS db.t2 LIKE db.t1",
+        "CREATE TABLE IF NOT EXISTS db.t2 (LIKE db.t1)",
+    ] {
+        assert_eq!(
+            Parser::parse_sql(sql).unwrap(),
+            Some(Statement::CreateTableLike {
+                temporary: false,
+                if_not_exists: true,
+                name: ObjectName(vec![Ident::new("db"), Ident::new("t2")]),
+                like: ObjectName(vec![Ident::new("db"), Ident::new("t1")]),
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_invalid_statement() {
+    assert!(Parser::parse_sql("CREATE TABLE t (a INT").is_err());
+    assert!(Parser::parse_sql("CREATE TABLE t (a DECIMAL(10,").is_err());
+    assert!(Parser::parse_sql("CREATE TABLE t (a ENUM('x' 'y'))").is_err());
+    assert!(Parser::parse_sql("CREATE TABLE t (a INT DEFAULT -x)").is_err());
+    assert!(Parser::parse_sql("CREATE TABLE t (a INT CHECK (a > 0)").is_err());
+    assert!(Parser::parse_sql("CREATE TABLE t (a VARCHAR(99999999999999999999))").is_err());
+}
+
+#[test]
+fn test_parse_enum_and_set_labels() {
+    let sql = "CREATE TABLE `t` (
+  `e` enum('','a','b''c') DEFAULT NULL,
+  `s` set('x','y,z') DEFAULT NULL
+)";
+    let Some(Statement::CreateTable { columns, .. }) = Parser::parse_sql(sql).unwrap() else {
+        panic!("Failed to parse {sql}");
+    };
+    assert_eq!(
+        columns[0].data_type,
+        DataType::Enum(vec!["".into(), "a".into(), "b'c".into()])
+    );
+    assert_eq!(
+        columns[1].data_type,
+        DataType::Set(vec!["x".into(), "y,z".into()])
+    );
+}
+
+#[test]
+fn test_parse_generated_and_invisible_columns() {
+    let sql = "CREATE TABLE `t` (
+  `a` int NOT NULL,
+  `b` int GENERATED ALWAYS AS ((`a` + 1)) VIRTUAL /*!80023 INVISIBLE */,
+  `c` varchar(10) AS (concat(`a`, _utf8mb4'*/')) STORED NOT NULL,
+  `d` int /*!80023 INVISIBLE */ /*!50100 COMMENT 'x' */,
+  PRIMARY KEY (`a`)
+)";
+    let Some(Statement::CreateTable { columns, .. }) = Parser::parse_sql(sql).unwrap() else {
+        panic!("Failed to parse {sql}");
+    };
+    let names: Vec<&str> = columns.iter().map(|c| c.name.value.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c", "d"]);
+    assert_eq!(
+        columns[1].options,
+        vec![
+            ColumnOption::Generated {
+                expr: "(`a` + 1)".to_string(),
+                stored: false
+            },
+            ColumnOption::Invisible,
+        ]
+    );
+    assert_eq!(
+        columns[2].options,
+        vec![
+            ColumnOption::Generated {
+                expr: "concat(`a`, _utf8mb4'*/')".to_string(),
+                stored: true
+            },
+            ColumnOption::NotNull,
+        ]
+    );
+    assert_eq!(
+        columns[3].options,
+        vec![
+            ColumnOption::Invisible,
+            ColumnOption::Comment("x".to_string())
+        ]
+    );
+}
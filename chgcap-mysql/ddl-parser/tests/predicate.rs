@@ -0,0 +1,65 @@
+use chgcap_mysql_ddl_parser::ast::expr::{CompareOperator, Predicate};
+use chgcap_mysql_ddl_parser::ast::value::Value;
+use chgcap_mysql_ddl_parser::ast::Ident;
+use chgcap_mysql_ddl_parser::parser::Parser;
+
+#[test]
+fn test_parse_predicates() {
+    let sql = "tenant_id >= -42 AND `name` != 'it''s' AND deleted_at IS NULL \
+               AND status NOT IN ('new', \"paid\") AND code IS NOT NULL AND price < 1.5";
+    let predicates = Parser::parse_predicates(sql).unwrap();
+    assert_eq!(
+        predicates,
+        vec![
+            Predicate::Compare {
+                column: Ident::new("tenant_id"),
+                op: CompareOperator::GtEq,
+                value: Value::Number("-42".into(), false),
+            },
+            Predicate::Compare {
+                column: Ident::with_quote('`', "name"),
+                op: CompareOperator::NotEq,
+                value: Value::SingleQuotedString("it's".into()),
+            },
+            Predicate::IsNull {
+                column: Ident::new("deleted_at"),
+                negated: false,
+            },
+            Predicate::InList {
+                column: Ident::new("status"),
+                list: vec![
+                    Value::SingleQuotedString("new".into()),
+                    Value::DoubleQuotedString("paid".into()),
+                ],
+                negated: true,
+            },
+            Predicate::IsNull {
+                column: Ident::new("code"),
+                negated: true,
+            },
+            Predicate::Compare {
+                column: Ident::new("price"),
+                op: CompareOperator::Lt,
+                value: Value::Number("1.5".into(), false),
+            },
+        ]
+    );
+    assert_eq!(predicates[3].to_string(), "status NOT IN ('new', \"paid\")");
+}
+
+#[test]
+fn test_parse_invalid_predicates() {
+    for sql in [
+        "",
+        "a",
+        "a = b",
+        "a = 1 OR b = 2",
+        "a NOT = 1",
+        "a IN ()",
+        "a IS 1",
+        "'a' = 1",
+        "a = 'unterminated",
+    ] {
+        assert!(Parser::parse_predicates(sql).is_err(), "{sql}");
+    }
+}
@@ -0,0 +1,29 @@
+use chgcap_mysql_ddl_parser::parser::Parser;
+
+fn table_names(sql: &str) -> Vec<String> {
+    Parser::parse_table_names(sql)
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn test_parse_table_names() {
+    assert_eq!(
+        table_names("CREATE TABLE IF NOT EXISTS db.t (v UNKNOWN_TYPE)"),
+        ["db.t"]
+    );
+    assert_eq!(table_names("DROP TABLE IF EXISTS t1, `t2`"), ["t1", "`t2`"]);
+    assert_eq!(
+        table_names("ALTER TABLE t RENAME COLUMN a TO b, UNKNOWN OPTION, RENAME TO t2"),
+        ["t", "t2"]
+    );
+    assert_eq!(
+        table_names("RENAME TABLE a TO b, c TO d"),
+        ["a", "b", "c", "d"]
+    );
+    assert!(table_names("CREATE TEMPORARY TABLE t (v INT)").is_empty());
+    assert!(table_names("CREATE DATABASE db").is_empty());
+    assert!(table_names("INSERT INTO t VALUES (1)").is_empty());
+}
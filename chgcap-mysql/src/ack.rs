@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+
+use crate::event::BinlogPosition;
+use crate::state::{commit_offset, SharedOffsetStore, SharedSchemaHistory};
+
+/// A handle to acknowledge that an [`crate::Event`] has been durably processed, see
+/// [`crate::Event::ack_handle`]. It can be sent to the task that processes the event.
+///
+/// The events may be acknowledged in any order. An offset is only committed once all the events
+/// before it are acknowledged, so that a restarted stream receives every unacknowledged event
+/// again.
+#[derive(Clone)]
+pub struct AckHandle {
+    tracker: Arc<Mutex<AckTracker>>,
+    seq: u64,
+}
+
+impl AckHandle {
+    /// Acknowledges the event, which commits the lowest offset before which all events are
+    /// acknowledged if it advances.
+    pub fn ack(&self) -> Result<()> {
+        self.tracker
+            .lock()
+            .map_err(|e| anyhow!("{e}"))?
+            .ack(self.seq)
+    }
+}
+
+impl PartialEq for AckHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tracker, &other.tracker) && self.seq == other.seq
+    }
+}
+
+/// Tracks the acknowledgements of the emitted events, which are numbered in the emission order.
+pub(crate) struct AckTracker {
+    offset_store: SharedOffsetStore,
+    schema_history: Option<SharedSchemaHistory>,
+
+    /// The sequence number of the first event in `pending`.
+    first_seq: u64,
+
+    /// The offsets of the emitted events that are not committed yet, and whether they are
+    /// acknowledged, in the emission order.
+    pending: VecDeque<(BinlogPosition, bool)>,
+
+    /// The offset of the last acknowledged event that is committed, or will be committed once the
+    /// next event is acknowledged.
+    committed: Option<BinlogPosition>,
+}
+
+impl AckTracker {
+    pub fn new(
+        offset_store: SharedOffsetStore,
+        schema_history: Option<SharedSchemaHistory>,
+    ) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            offset_store,
+            schema_history,
+            first_seq: 0,
+            pending: VecDeque::new(),
+            committed: None,
+        }))
+    }
+
+    /// Tracks an emitted event with the offset, returning the handle to acknowledge it.
+    pub fn track(tracker: &Arc<Mutex<Self>>, offset: BinlogPosition) -> Result<AckHandle> {
+        let mut this = tracker.lock().map_err(|e| anyhow!("{e}"))?;
+        let seq = this.first_seq + this.pending.len() as u64;
+        this.pending.push_back((offset, false));
+        Ok(AckHandle {
+            tracker: tracker.clone(),
+            seq,
+        })
+    }
+
+    fn ack(&mut self, seq: u64) -> Result<()> {
+        let Some(index) = seq.checked_sub(self.first_seq) else {
+            // Already committed.
+            return Ok(());
+        };
+        if let Some((_, acked)) = self.pending.get_mut(index as usize) {
+            *acked = true;
+        }
+
+        let mut last_acked = None;
+        while let Some((_, true)) = self.pending.front() {
+            last_acked = self.pending.pop_front().map(|(offset, _)| offset);
+            self.first_seq += 1;
+        }
+        let Some(last_acked) = last_acked else {
+            return Ok(());
+        };
+        // Restarting from the offset of the first unacknowledged event doesn't skip it, nor any
+        // event after the acknowledged ones. If all events are acknowledged, the offset of the
+        // last one is the latest known to be safe, which may receive its transaction again.
+        let offset = match self.pending.front() {
+            Some((offset, _)) => offset.clone(),
+            None => last_acked,
+        };
+        if self.committed.as_ref().is_some_and(|c| c >= &offset) {
+            return Ok(());
+        }
+        commit_offset(&self.offset_store, self.schema_history.as_ref(), &offset)?;
+        self.committed = Some(offset);
+        Ok(())
+    }
+}
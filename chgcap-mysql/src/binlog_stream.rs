@@ -1,32 +1,180 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
 use anyhow::{anyhow, bail, Result};
-use log::{debug, error, info};
+use chgcap_mysql_ddl_parser::ast::data_type::DataType;
+use chgcap_mysql_ddl_parser::ast::Statement;
+use chgcap_mysql_ddl_parser::parser::Parser;
+use futures::future::{self, BoxFuture};
+use futures::FutureExt;
+use itertools::Itertools;
+use log::{debug, error, info, warn};
 use mysql_async::binlog::events::{
-    DeleteRowsEvent, Event, EventData, GtidEvent, IncidentEvent, QueryEvent, RotateEvent,
-    RowsEventData, RowsQueryEvent, StatusVarVal, TableMapEvent, TransactionPayloadEvent,
-    UpdateRowsEvent, WriteRowsEvent,
+    Event, EventData, GtidEvent, IncidentEvent, QueryEvent, RotateEvent, RowsEventData,
+    RowsQueryEvent, StatusVarVal, TransactionPayloadEvent,
 };
+use mysql_async::binlog::row::BinlogRow;
 use mysql_async::prelude::Query;
 use mysql_async::{BinlogStream as MysqlBinlogStream, BinlogStreamRequest, Conn, Pool, Row};
 
-use crate::event::{Event as ChgcapEvent, EventData as ChgcapEventData, RowChange};
+use crate::ack::AckTracker;
+use crate::config::{GeneratedColumnMode, TableFilter};
+use crate::event::{
+    without_noop_updates, BinlogPosition, Event as ChgcapEvent, EventData as ChgcapEventData,
+    Row as ChangeRow, RowChange, SchemaChange,
+};
+use crate::filter::TableSelection;
+use crate::rows::{read_rows, with_column_names, RowImages};
+use crate::schema::{
+    load_table_defs, load_underived_table_defs, qualified_table_name, table_changes, MysqlSchema,
+    MysqlTableDef, SchemaMismatch,
+};
+use crate::signal::Signal;
+use crate::snapshot::{list_tables, IncrementalSnapshot, NewTableSnapshots};
 use crate::source::{Source, SourceContext};
+use crate::state::{commit_offset, SchemaRecord, SharedOffsetStore, SharedSchemaHistory};
+use crate::table_map::TableMapColumns;
+use crate::value::ValueDecoder;
 use crate::SourceConfig;
 
+/// A stream of the changes in the binlog.
+///
+/// Only the changes of the captured tables are emitted. The captured tables are the ones accepted
+/// by [`SourceConfig::table_list`] when the stream starts, plus the ones created or renamed
+/// afterwards if [`SourceConfig::scan_newly_added_table_enabled`] is set.
+///
+/// If [`SourceConfig::signal_table`] is set, the incremental snapshots requested by the signals
+/// are interleaved with the binlog events.
 pub struct BinlogStream {
     binlog_stream: MysqlBinlogStream,
 
     ctx: SourceContext,
     cfg: SourceConfig,
+
+    table_filter: TableFilter,
+
+    /// The `databaseName.tableName` of the captured tables.
+    captured_tables: HashSet<String>,
+
+    /// The tables that are newly captured but whose events are not emitted yet.
+    new_tables: NewTableSnapshots,
+
+    /// The row filters and column projections, keyed by `databaseName.tableName`.
+    selections: HashMap<String, TableSelection>,
+
+    /// The definitions of the captured tables as of the current binlog position. They're rebuilt
+    /// from the schema history when the stream starts, and the DDLs in the binlog are applied to
+    /// them.
+    schema: MysqlSchema,
+
+    decoder: ValueDecoder,
+
+    /// `None` if the signal table is not configured.
+    incremental_snapshot: Option<IncrementalSnapshot>,
+
+    offset_store: Option<SharedOffsetStore>,
+    schema_history: Option<SharedSchemaHistory>,
+
+    /// Tracks the acknowledgements of the events, `None` if there's no offset store.
+    ack_tracker: Option<Arc<Mutex<AckTracker>>>,
+
+    /// The records of the schema history after the current binlog position, which are applied
+    /// when their DDLs are received again.
+    replayed_schema: VecDeque<SchemaRecord>,
+
+    /// The table definitions being derived after a DDL. The binlog is not read until they're
+    /// derived.
+    schema_refresh: Option<SchemaRefresh>,
+
+    pool: Pool,
+}
+
+struct SchemaRefresh {
+    /// The binlog position of the end of the DDL statement.
+    offset: BinlogPosition,
+    pos: u32,
+
+    /// The offset of the [`SchemaChange`] event, i.e. before the DDL statement.
+    event_offset: BinlogPosition,
+    ddl: String,
+    database_name: String,
+    tables: Vec<String>,
+
+    /// The record of the schema history at the DDL statement, if it's received again.
+    replayed: Option<SchemaRecord>,
+
+    /// The definitions of the tables after the DDL statement, which are loaded from the server
+    /// for the tables whose definitions can't be derived from the statement.
+    loading: BoxFuture<'static, Result<BTreeMap<String, Option<MysqlTableDef>>>>,
 }
 
 impl BinlogStream {
     pub async fn new(source: &Source) -> Result<Self> {
+        Self::with_captured_tables(source, None).await
+    }
+
+    /// Creates a stream that resumes capturing `captured_tables`, see
+    /// [`BinlogStream::captured_tables`]. If [`SourceConfig::scan_newly_added_table_enabled`] is
+    /// set, the tables that only match the current configuration are snapshotted before their
+    /// changes are emitted.
+    pub async fn with_captured_tables(
+        source: &Source,
+        captured_tables: Option<HashSet<String>>,
+    ) -> Result<Self> {
         let cfg = source.cfg.clone();
         let pool = &source.pool;
-        let (conn, filename, position) = create_binlog_stream_conn(pool).await?;
+        let table_filter = TableFilter::new(&cfg)?;
+        let selections = TableSelection::from_config(&cfg)?;
+        let mut conn = pool.get_conn().await?;
+        let matched_tables: HashSet<String> = list_tables(&mut conn, &table_filter)
+            .await?
+            .into_iter()
+            .map(|(database_name, table_name)| format!("{database_name}.{table_name}"))
+            .collect();
+        let incremental_snapshot = match cfg.signal_table() {
+            Some(table) => Some(IncrementalSnapshot::new(
+                pool.clone(),
+                &cfg,
+                table.clone(),
+                source.snapshot_progress.clone(),
+            )?),
+            None => None,
+        };
+        let decoder = ValueDecoder::new(&cfg)?.load_charsets(&mut conn).await?;
+        drop(conn);
+        let (conn, mut filename, mut position) = create_binlog_stream_conn(pool).await?;
+        if let Some(store) = &source.offset_store {
+            let offset = store.lock().map_err(|e| anyhow!("{e}"))?.load()?;
+            if let Some(offset) = offset {
+                info!("Resuming from the committed offset {offset}");
+                filename = offset.filename().as_bytes().to_vec();
+                position = offset.position();
+            }
+        }
+        let start = BinlogPosition::new(String::from_utf8(filename.clone())?, position);
+        let mut new_tables = NewTableSnapshots::new(pool.clone(), &cfg)?;
+        let captured_tables = match captured_tables {
+            Some(previous) if cfg.scan_newly_added_table_enabled() => {
+                for table in matched_tables.difference(&previous) {
+                    new_tables.start(table, start.clone(), start.clone(), true)?;
+                }
+                matched_tables
+            }
+            Some(previous) => previous
+                .into_iter()
+                .filter(|t| matched_tables.contains(t))
+                .collect(),
+            None => matched_tables,
+        };
+        let (schema, replayed_schema) = load_schema(
+            pool,
+            source.schema_history.as_ref(),
+            &start,
+            &captured_tables,
+        )
+        .await?;
         let request = BinlogStreamRequest::new(cfg.server_id())
             .with_filename(&filename)
             .with_pos(position);
@@ -35,19 +183,121 @@ impl BinlogStream {
             .await
             .map_err(|e| anyhow!(e))?;
         let ctx = SourceContext {
-            current_binlog_filename: String::from_utf8(filename)?,
+            current_binlog_filename: start.filename().clone(),
+            restart_position: start,
             ..Default::default()
         };
         Ok(Self {
             binlog_stream,
             ctx,
             cfg,
+            table_filter,
+            captured_tables,
+            new_tables,
+            selections,
+            schema,
+            decoder,
+            incremental_snapshot,
+            offset_store: source.offset_store.clone(),
+            schema_history: source.schema_history.clone(),
+            ack_tracker: source
+                .offset_store
+                .clone()
+                .map(|store| AckTracker::new(store, source.schema_history.clone())),
+            replayed_schema,
+            schema_refresh: None,
+            pool: pool.clone(),
         })
     }
 
     pub fn config(&self) -> &SourceConfig {
         &self.cfg
     }
+
+    /// Commits the offset of a processed event, see [`ChgcapEvent::offset`], so that the stream
+    /// resumes from it after a restart. Fails if the source has no offset store, see
+    /// [`Source::with_offset_store`]. Prefer [`ChgcapEvent::ack_handle`] if the events are not
+    /// processed in order.
+    pub fn commit(&self, offset: &BinlogPosition) -> Result<()> {
+        let store = self
+            .offset_store
+            .as_ref()
+            .ok_or_else(|| anyhow!("The source has no offset store"))?;
+        commit_offset(store, self.schema_history.as_ref(), offset)
+    }
+
+    /// The offset of the next emitted event. It doesn't advance while some events are held back.
+    fn offset(&self) -> BinlogPosition {
+        let restart_position = &self.ctx.restart_position;
+        match self.new_tables.earliest_offset() {
+            Some(offset) if offset < restart_position => offset.clone(),
+            _ => restart_position.clone(),
+        }
+    }
+
+    /// The `databaseName.tableName` of the tables whose changes are captured, which can be passed
+    /// to [`BinlogStream::with_captured_tables`] to resume the stream after a configuration change.
+    pub fn captured_tables(&self) -> &HashSet<String> {
+        &self.captured_tables
+    }
+}
+
+/// Loads the definitions of the captured tables as of `start`, from the schema history if it
+/// covers `start`, or from the server otherwise. The records of the history after `start` are
+/// returned as well.
+async fn load_schema(
+    pool: &Pool,
+    history: Option<&SharedSchemaHistory>,
+    start: &BinlogPosition,
+    captured_tables: &HashSet<String>,
+) -> Result<(MysqlSchema, VecDeque<SchemaRecord>)> {
+    let mut rebuilt = None;
+    let mut replayed = VecDeque::new();
+    if let Some(history) = history {
+        let history = history.lock().map_err(|e| anyhow!("{e}"))?;
+        rebuilt = history.schema_at(start)?;
+        if rebuilt.is_some() {
+            info!("Rebuilt the schema at {start} from the schema history");
+            replayed = history
+                .load()?
+                .into_iter()
+                .filter(|r| &r.offset > start)
+                .collect();
+        }
+    }
+    let is_rebuilt = rebuilt.is_some();
+    let mut schema = MysqlSchema::with_tables(pool.clone(), rebuilt.unwrap_or_default());
+    // The tables that are not in the history, e.g. the ones that newly match the configuration,
+    // are loaded from the server.
+    let missing = captured_tables
+        .iter()
+        .filter(|t| schema.get(t).is_none())
+        .cloned()
+        .collect();
+    for (table, table_def) in load_table_defs(pool.clone(), missing).await? {
+        schema.replace(&table, table_def);
+    }
+    if is_rebuilt {
+        return Ok((schema, replayed));
+    }
+
+    if let Some(history) = history {
+        let mut history = history.lock().map_err(|e| anyhow!("{e}"))?;
+        if history.load()?.is_empty() {
+            history.append(SchemaRecord {
+                offset: start.clone(),
+                ddl: None,
+                tables: schema
+                    .tables()
+                    .iter()
+                    .map(|(t, d)| (t.clone(), Some(d.clone())))
+                    .collect(),
+            })?;
+        } else {
+            warn!("The schema history doesn't cover {start}, the current schema is used instead");
+        }
+    }
+    Ok((schema, replayed))
 }
 
 async fn create_binlog_stream_conn(pool: &Pool) -> Result<(Conn, Vec<u8>, u64)> {
@@ -78,18 +328,12 @@ impl BinlogStream {
         self.ctx.server_id = event.header().server_id();
 
         match event_data {
-            EventData::QueryEvent(e) => self.handle_query_event(e),
+            EventData::QueryEvent(e) => self.handle_query_event(e, event.header().log_pos())?,
             EventData::RotateEvent(e) => self.handle_rotate_event(e),
             EventData::HeartbeatEvent => self.handle_heartbeat_event(),
             EventData::RowsQueryEvent(e) => self.handle_rows_query_event(e),
             EventData::GtidEvent(e) => self.handle_gtid_event(e),
-            EventData::RowsEvent(e) => {
-                return Ok(Some(self.handle_rows_event(
-                    &self.binlog_stream,
-                    e,
-                    event.header().log_pos(),
-                )?))
-            }
+            EventData::RowsEvent(e) => return self.handle_rows_event(e, event.header().log_pos()),
             EventData::TableMapEvent(e) => {
                 // An event that contains the schema data for a DML statement, enabled only in row-based mode.
                 // It precedes every DML,
@@ -102,7 +346,10 @@ impl BinlogStream {
                         .collect::<Result<Vec<_>>>()
                 );
             }
-            EventData::XidEvent(e) => debug!("Received XID event: {:?}", e),
+            EventData::XidEvent(e) => {
+                debug!("Received XID event: {:?}", e);
+                self.handle_txn_completion(event.header().log_pos());
+            }
             EventData::TransactionPayloadEvent(e) => self.handle_txn_payload(e),
             _ => {
                 // EventData::UnknownEvent => todo!(),
@@ -140,9 +387,11 @@ impl BinlogStream {
         debug!("Received transaction payload: {:?}", e)
     }
 
-    /// Handle a [mysql_async::binlog::events::XidEvent] or a COMMIT statement.
-    fn handle_txn_completion(&self) {
-        todo!()
+    /// Handle a [mysql_async::binlog::events::XidEvent] or a COMMIT statement, after which the
+    /// stream can restart.
+    fn handle_txn_completion(&mut self, pos: u32) {
+        self.ctx.restart_position =
+            BinlogPosition::new(self.ctx.current_binlog_filename.clone(), pos as u64);
     }
 
     /// Handle the supplied event that signals that mysqld has stopped.
@@ -170,6 +419,7 @@ impl BinlogStream {
     fn handle_rotate_event(&mut self, e: RotateEvent) {
         self.ctx.current_binlog_pos = e.position();
         self.ctx.current_binlog_filename = e.name().to_string();
+        self.ctx.restart_position = BinlogPosition::new(e.name(), e.position());
 
         debug!("Rotated to binlog file: {}", e.name());
     }
@@ -196,7 +446,7 @@ impl BinlogStream {
 
     /// Handle the supplied event with an [QueryEvent] by possibly recording the DDL statements
     /// as changes in the MySQL schemas.
-    fn handle_query_event(&mut self, e: QueryEvent) {
+    fn handle_query_event(&mut self, e: QueryEvent, pos: u32) -> Result<()> {
         // Obtain the database name of a DDL statement.
         let db_name = e.status_vars().iter().find_map(|v| {
             if let Ok(StatusVarVal::UpdatedDbNames(names)) = v.get_value() {
@@ -206,51 +456,568 @@ impl BinlogStream {
             }
         });
         if let Some(name) = db_name {
-            println!("Received DDL for database {}: {}", name.as_str(), e.query());
-            // TODO: parse `create table` and `alter table` statements. https://github.com/neverchanje/chgcap-rs/issues/4
+            debug!("Received DDL for database {}: {}", name.as_str(), e.query());
+        }
+        match Parser::parse_sql(&e.query()) {
+            Ok(Some(statement)) => {
+                let changes = table_changes(&statement, &e.schema());
+                if !changes.is_empty() {
+                    self.refresh_schema(Some(&statement), &changes, &e.query(), &e.schema(), pos);
+                    if self.cfg.scan_newly_added_table_enabled() {
+                        self.handle_table_ddl(changes, pos)?;
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                // The definitions of the tables of a DDL that can't be parsed are reloaded rather
+                // than kept stale.
+                let names = Parser::parse_table_names(&e.query()).unwrap_or_default();
+                warn!(
+                    "Failed to parse the statement {}, so the definitions of its tables [{}] are \
+                     reloaded: {err}",
+                    e.query(),
+                    names.iter().join(", ")
+                );
+                let changes: Vec<_> = names
+                    .iter()
+                    .map(|name| {
+                        let table = qualified_table_name(name, &e.schema());
+                        (Some(table.clone()), Some(table))
+                    })
+                    .collect();
+                if !changes.is_empty() {
+                    self.refresh_schema(None, &changes, &e.query(), &e.schema(), pos);
+                }
+            }
+        }
+        if ends_transaction(&e.query()) {
+            self.handle_txn_completion(pos);
         }
+        Ok(())
     }
 
-    /// Generate source records for the supplied event.
-    fn handle_rows_event(
-        &self,
-        stream: &MysqlBinlogStream,
-        e: RowsEventData,
+    /// Derives the definitions of the tables changed by a DDL statement. They're taken from the
+    /// schema history if the DDL is recorded, or derived from their definitions before the DDL
+    /// otherwise. The definitions that can't be derived are loaded from the server if the stream
+    /// is caught up, i.e. the DDL is the last statement in the binlog, or left unknown otherwise,
+    /// in which case the rows are decoded with the metadata in the binlog. None can be derived if
+    /// the statement couldn't be parsed.
+    fn refresh_schema(
+        &mut self,
+        statement: Option<&Statement>,
+        changes: &[(Option<String>, Option<String>)],
+        query: &str,
+        database_name: &str,
         pos: u32,
-    ) -> Result<ChgcapEvent> {
-        let tme = stream.get_tme(e.table_id()).ok_or_else(|| {
+    ) {
+        let offset = BinlogPosition::new(self.ctx.current_binlog_filename.clone(), pos as u64);
+        let tables: Vec<String> = changes
+            .iter()
+            .flat_map(|(from, to)| [from, to])
+            .flatten()
+            .filter(|t| {
+                t.split_once('.')
+                    .is_some_and(|(d, t)| self.table_filter.matches(d, t))
+            })
+            .unique()
+            .cloned()
+            .collect();
+        if tables.is_empty() {
+            return;
+        }
+        let replayed = self.take_replayed_record(&offset);
+        if replayed.is_some() {
+            debug!("Replayed the schema change at {offset}");
+        }
+        let loading = match &replayed {
+            Some(record) => future::ready(Ok(record.tables.clone())).boxed(),
+            None => {
+                let mut derived = match statement {
+                    Some(statement) => self.schema.apply_ddl(statement, database_name),
+                    None => tables
+                        .iter()
+                        .map(|t| (t.clone(), Err(anyhow!("The statement can't be parsed"))))
+                        .collect(),
+                };
+                derived.retain(|t, _| tables.contains(t));
+                load_underived_table_defs(self.pool.clone(), derived, offset.clone()).boxed()
+            }
+        };
+        self.schema_refresh = Some(SchemaRefresh {
+            offset,
+            pos,
+            event_offset: self.offset(),
+            ddl: query.to_string(),
+            database_name: database_name.to_string(),
+            tables,
+            replayed,
+            loading,
+        });
+    }
+
+    /// Applies the derived table definitions, and records them into the schema history. Returns
+    /// the [`SchemaChange`] event if it's enabled.
+    fn complete_schema_refresh(
+        &mut self,
+        refresh: SchemaRefresh,
+        table_defs: BTreeMap<String, Option<MysqlTableDef>>,
+    ) -> Result<Option<ChgcapEvent>> {
+        let record = match refresh.replayed {
+            Some(record) => record,
+            None => {
+                let record = SchemaRecord {
+                    offset: refresh.offset,
+                    ddl: Some(refresh.ddl.clone()),
+                    tables: table_defs.clone(),
+                };
+                if let Some(history) = &self.schema_history {
+                    history
+                        .lock()
+                        .map_err(|e| anyhow!("{e}"))?
+                        .append(record.clone())?;
+                }
+                record
+            }
+        };
+        let before = record
+            .tables
+            .keys()
+            .map(|t| (t.clone(), self.schema.get(t).cloned()))
+            .collect();
+        self.schema.apply_record(&record);
+        let after = record.tables;
+        if !self.cfg.include_schema_changes() {
+            return Ok(None);
+        }
+        let (database_name, table_name) = refresh
+            .tables
+            .first()
+            .and_then(|t| t.split_once('.'))
+            .unwrap_or_default();
+        Ok(Some(ChgcapEvent {
+            pos: refresh.pos,
+            offset: refresh.event_offset,
+            ack: None,
+            database_name: database_name.to_string(),
+            schema_name: Default::default(),
+            table_id: 0,
+            table_name: table_name.to_string(),
+            data: ChgcapEventData::SchemaChange(SchemaChange {
+                ddl: refresh.ddl,
+                database_name: refresh.database_name,
+                tables: refresh.tables,
+                before,
+                after,
+            }),
+            primary_key_changed: false,
+        }))
+    }
+
+    /// Updates the captured tables according to a DDL statement. A created table is captured
+    /// from its creation, while a table renamed from an uncaptured one may already hold rows, so
+    /// it's snapshotted first.
+    fn handle_table_ddl(
+        &mut self,
+        changes: Vec<(Option<String>, Option<String>)>,
+        pos: u32,
+    ) -> Result<()> {
+        let position = BinlogPosition::new(self.ctx.current_binlog_filename.clone(), pos as u64);
+        let mut dropped = vec![];
+        let mut added = vec![];
+        for (from, to) in changes {
+            if from == to {
+                continue;
+            }
+            let read_rows = from.is_some();
+            dropped.extend(from);
+            added.extend(to.map(|t| (t, read_rows)));
+        }
+        for table in dropped {
+            if self.captured_tables.remove(&table) {
+                self.new_tables.cancel(&table, &self.schema);
+                info!("Stopped capturing table {table}");
+            }
+        }
+        for (table, read_rows) in added {
+            let Some((database_name, table_name)) = table.split_once('.') else {
+                continue;
+            };
+            if !self.table_filter.matches(database_name, table_name)
+                || self.captured_tables.contains(&table)
+            {
+                continue;
+            }
+            let offset = self.ctx.restart_position.clone();
+            self.new_tables
+                .start(&table, position.clone(), offset, read_rows)?;
+            self.captured_tables.insert(table);
+        }
+        Ok(())
+    }
+
+    /// Generate source records for the supplied event. Returns `Ok(None)` if all rows are
+    /// filtered out.
+    fn handle_rows_event(&mut self, e: RowsEventData, pos: u32) -> Result<Option<ChgcapEvent>> {
+        let tme = self.binlog_stream.get_tme(e.table_id()).ok_or_else(|| {
             anyhow!(
                 "Received a rows event for table id {} but no table metadata was found",
                 e.table_id()
             )
         })?;
-        let changes = match e {
-            RowsEventData::WriteRowsEvent(e) => self.handle_write_rows(tme, e),
-            RowsEventData::UpdateRowsEvent(e) => self.handle_update_rows(tme, e),
-            RowsEventData::DeleteRowsEvent(e) => self.handle_delete_rows(tme, e),
-            RowsEventData::PartialUpdateRowsEvent(_) => todo!(),
-
+        let is_update = matches!(
+            e,
+            RowsEventData::UpdateRowsEvent(_) | RowsEventData::PartialUpdateRowsEvent(_)
+        );
+        if matches!(
+            e,
             RowsEventData::DeleteRowsEventV1(_)
-            | RowsEventData::WriteRowsEventV1(_)
-            | RowsEventData::UpdateRowsEventV1(_) => {
-                panic!("Received a V1 rows event. V1 is used in MySQL version 5.1.15-5.6.x, which are unsupported.")
-            }
-        }?;
+                | RowsEventData::WriteRowsEventV1(_)
+                | RowsEventData::UpdateRowsEventV1(_)
+        ) {
+            panic!("Received a V1 rows event. V1 is used in MySQL version 5.1.15-5.6.x, which are unsupported.")
+        }
+        let rows = read_rows(&e, tme)?;
+        let table_name = tme.table_name().to_string();
+        let table_id = tme.table_id();
 
-        Ok(ChgcapEvent {
-            table_name: tme.table_name().to_string(),
-            table_id: tme.table_id(),
+        let table = format!("{}.{}", tme.database_name(), tme.table_name());
+        let columns = TableMapColumns::new(tme);
+        if self
+            .incremental_snapshot
+            .as_ref()
+            .is_some_and(|s| s.is_signal_table(&table))
+        {
+            let offset = self.offset();
+            // The signals are the rows inserted into the signal table.
+            let signals: Vec<Signal> = rows
+                .iter()
+                .filter_map(|(_, after)| after.as_ref())
+                .filter_map(|row| match Signal::from_row(row) {
+                    Ok(signal) => Some(self.without_uncaptured_tables(signal)),
+                    Err(e) => {
+                        warn!("Ignored invalid signal: {e}");
+                        None
+                    }
+                })
+                .collect();
+            let incremental_snapshot = self.incremental_snapshot.as_mut().unwrap();
+            for signal in signals {
+                incremental_snapshot.handle_signal(signal, pos, &offset)?;
+            }
+            return Ok(None);
+        }
+        if !self.is_captured(tme.database_name().as_ref(), tme.table_name().as_ref()) {
+            return Ok(None);
+        }
+        let table_def = self.reconcile_table_def(&table, columns, pos)?;
+        let rows = match &table_def {
+            Some((_, column_names)) => {
+                let name = |row: Option<BinlogRow>| {
+                    row.map(|row| with_column_names(row, column_names))
+                        .transpose()
+                };
+                rows.into_iter()
+                    .map(|(before, after)| Ok((name(before)?, name(after)?)))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            None => rows,
+        };
+        let def = table_def.as_ref().map(|(def, _)| def);
+        let changes = match e {
+            RowsEventData::WriteRowsEvent(_) => self.handle_write_rows(rows, def),
+            RowsEventData::UpdateRowsEvent(_) => self.handle_update_rows(rows, def),
+            RowsEventData::DeleteRowsEvent(_) => self.handle_delete_rows(rows, def),
+            _ => self.handle_partial_update_rows(rows, def),
+        }
+        .map_err(|e| anyhow!("Failed to decode the rows of table {table}: {e}"))?;
+        let labels = def.map(Self::column_labels).unwrap_or_default();
+        let changes = if labels.is_empty() {
+            changes
+        } else {
+            changes
+                .into_iter()
+                .map(|c| c.try_map_row(|row| row.with_labels(&labels)))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| anyhow!("Failed to decode the rows of table {table}: {e}"))?
+        };
+        if let Some(incremental_snapshot) = &mut self.incremental_snapshot {
+            incremental_snapshot.observe_changes(&table, &changes)?;
+        }
+        // The projected columns are kept even if they're virtual.
+        let omit_virtual = self.config().generated_column_mode() == GeneratedColumnMode::Omit
+            && !self
+                .selections
+                .get(&table)
+                .is_some_and(TableSelection::has_projection);
+        let changes = match def {
+            Some(def) if omit_virtual => changes
+                .into_iter()
+                .map(|c| c.try_map_row(|row| Ok(row.without_virtual_columns(def))))
+                .collect::<Result<Vec<_>>>()?,
+            _ => changes,
+        };
+        let primary_keys = def.map_or(&[][..], |def| def.primary_keys.as_slice());
+        let key_columns = match self.selections.get(&table) {
+            Some(selection) => selection.key_columns(primary_keys),
+            None => primary_keys.into(),
+        };
+        let changes = changes
+            .into_iter()
+            .map(|c| c.try_map_row(|row| Ok(row.with_key_columns(key_columns.clone()))))
+            .collect::<Result<Vec<_>>>()?;
+        // An update is a delete of the row before it followed by an insert of the row after it.
+        let key = |c: &RowChange| c.key().map(|k| k.values().to_vec());
+        let primary_key_changed =
+            is_update && changes.chunks(2).any(|pair| key(&pair[0]) != key(&pair[1]));
+        let changes = if is_update {
+            let mut with_changed_columns = Vec::with_capacity(changes.len());
+            for (before, after) in changes.into_iter().tuples() {
+                let changed_columns = ChangeRow::changed_columns_between(before.row(), after.row());
+                for c in [before, after] {
+                    with_changed_columns.push(c.try_map_row(|row| {
+                        Ok(row.with_changed_columns(changed_columns.clone()))
+                    })?);
+                }
+            }
+            with_changed_columns
+        } else {
+            changes
+        };
+        let new_event = |data| ChgcapEvent {
+            table_name: table_name.clone(),
+            table_id,
+            offset: self.offset(),
+            ack: None,
             database_name: self.config().database().clone(),
             schema_name: Default::default(),
             pos,
-            data: ChgcapEventData::DataChange(changes),
-        })
+            data,
+            primary_key_changed,
+        };
+        if self.new_tables.contains(&table) {
+            // The rows are selected once the table is started.
+            let event = new_event(ChgcapEventData::DataChange(changes));
+            self.new_tables.hold_back(&table, event);
+            return Ok(None);
+        }
+        let changes = match self.selections.get(&table) {
+            Some(selection) => changes
+                .into_iter()
+                .filter_map(|c| selection.apply_change(c, def).transpose())
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| anyhow!("Failed to select the rows of table {table}: {e}"))?,
+            None => changes,
+        };
+        // The updates are no-ops if they only change the columns that are projected out.
+        let changes = if self.config().skip_noop_updates() {
+            without_noop_updates(changes)
+        } else {
+            changes
+        };
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(new_event(ChgcapEventData::DataChange(changes))))
+    }
+
+    /// Returns `true` if the rows of the table are captured. The captured tables only change with
+    /// the DDLs if the newly added tables are scanned.
+    fn is_captured(&self, database_name: &str, table_name: &str) -> bool {
+        if self.cfg.scan_newly_added_table_enabled() {
+            self.captured_tables
+                .contains(&format!("{database_name}.{table_name}"))
+        } else {
+            self.table_filter.matches(database_name, table_name)
+        }
     }
 
-    fn handle_write_rows(&self, tme: &TableMapEvent, e: WriteRowsEvent) -> Result<Vec<RowChange>> {
-        e.rows(tme)
-            .map(|r| {
-                let row = r?;
+    /// Drops the tables that are not captured from an `execute-snapshot` signal, whose snapshots
+    /// couldn't be told apart from the changes of the binlog.
+    fn without_uncaptured_tables(&self, signal: Signal) -> Signal {
+        let Signal::ExecuteSnapshot { tables } = signal else {
+            return signal;
+        };
+        let (tables, uncaptured): (Vec<String>, Vec<String>) =
+            tables.into_iter().partition(|table| {
+                table
+                    .split_once('.')
+                    .is_some_and(|(d, t)| self.is_captured(d, t))
+            });
+        if !uncaptured.is_empty() {
+            warn!(
+                "Ignored the incremental snapshot of tables {uncaptured:?}, which are not captured"
+            );
+        }
+        Signal::ExecuteSnapshot { tables }
+    }
+
+    /// Returns the definition of a table as of the rows event ending at `pos`, completed with the
+    /// metadata of its table map event, and the names of the columns in its row images, which may
+    /// lack the virtual generated columns. `None` if the definition is unknown.
+    ///
+    /// The columns may not match if the schema history misses a DDL, e.g. an `ALGORITHM=INSTANT`
+    /// column change before the stream restarts without a history. If the server sets
+    /// `binlog_row_metadata=FULL`, the definition is reconciled with the column names and types in
+    /// the table map event, and the reconciled definition is recorded into the schema history.
+    /// A [`SchemaMismatch`] is returned otherwise.
+    fn reconcile_table_def(
+        &mut self,
+        table: &str,
+        columns: TableMapColumns,
+        pos: u32,
+    ) -> Result<Option<(MysqlTableDef, Vec<String>)>> {
+        let offset = BinlogPosition::new(self.ctx.current_binlog_filename.clone(), pos as u64);
+        if let Some(record) = self.take_replayed_record(&offset) {
+            debug!("Replayed the reconciled schema at {offset}");
+            self.schema.apply_record(&record);
+        }
+        let Some(def) = self.schema.get(table) else {
+            if !columns.has_names() {
+                return Ok(None);
+            }
+            let (database_name, table_name) = table.split_once('.').unwrap_or_default();
+            let def = MysqlTableDef {
+                database_name: database_name.to_string(),
+                table_name: table_name.to_string(),
+                columns: (0..columns.len()).map(|i| columns.column_def(i)).collect(),
+                primary_keys: vec![],
+                charset: None,
+                collation: None,
+            };
+            self.record_reconciled_table_def(table, def.clone(), offset)?;
+            let column_names = def.column_names();
+            return Ok(Some((def, column_names)));
+        };
+
+        // The row images lack the virtual generated columns unless they're logged.
+        let mut def = def.clone();
+        let logs_virtual = def.columns.len() == columns.len();
+        let image_columns: Vec<usize> = (0..def.columns.len())
+            .filter(|i| logs_virtual || !def.columns[*i].is_virtual())
+            .collect();
+        let mismatch = if image_columns.len() == columns.len() {
+            image_columns
+                .iter()
+                .enumerate()
+                .find(|(i, c)| !columns.matches(*i, &def.columns[**c]))
+                .map(|(_, c)| Some(def.columns[*c].name.clone()))
+        } else {
+            Some(None)
+        };
+        let Some(column) = mismatch else {
+            for (i, c) in image_columns.iter().enumerate() {
+                def.columns[*c] = columns.complete(i, def.columns[*c].clone());
+            }
+            let column_names = image_columns
+                .iter()
+                .map(|c| def.columns[*c].name.clone())
+                .collect();
+            return Ok(Some((def, column_names)));
+        };
+        if !columns.has_names() {
+            return Err(SchemaMismatch {
+                table: table.to_string(),
+                offset,
+                binlog_columns: columns.len(),
+                schema_columns: def.columns.len(),
+                column,
+            }
+            .into());
+        }
+
+        // The cached columns are kept if their names and types match, e.g. for their defaults and
+        // character sets, as are the virtual columns missing from the row images.
+        let mut reconciled: Vec<_> = (0..columns.len())
+            .map(|i| match def.column(&columns.names[i]) {
+                Some(c) if columns.matches(i, c) => columns.complete(i, c.clone()),
+                _ => columns.column_def(i),
+            })
+            .collect();
+        reconciled.extend(
+            def.columns
+                .iter()
+                .filter(|c| c.is_virtual() && !columns.names.contains(&c.name))
+                .cloned(),
+        );
+        def.columns = reconciled;
+        def.primary_keys
+            .retain(|k| def.columns.iter().any(|c| &c.name == k));
+        self.record_reconciled_table_def(table, def.clone(), offset)?;
+        Ok(Some((def, columns.names)))
+    }
+
+    /// Replaces the definition of a table with the one reconciled with the binlog at `offset`, and
+    /// records it into the schema history unless the history is being replayed.
+    fn record_reconciled_table_def(
+        &mut self,
+        table: &str,
+        def: MysqlTableDef,
+        offset: BinlogPosition,
+    ) -> Result<()> {
+        warn!("Reconciled the definition of table {table} with the binlog at {offset}");
+        let record = SchemaRecord {
+            offset,
+            ddl: Some(format!("/* {table} reconciled with the binlog */")),
+            tables: BTreeMap::from([(table.to_string(), Some(def))]),
+        };
+        // The records after the replayed ones would be out of order.
+        if self.replayed_schema.is_empty() {
+            if let Some(history) = &self.schema_history {
+                history
+                    .lock()
+                    .map_err(|e| anyhow!("{e}"))?
+                    .append(record.clone())?;
+            }
+        }
+        self.schema.apply_record(&record);
+        Ok(())
+    }
+
+    /// Takes the record of the schema history at `offset` if it's received again, and drops the
+    /// records before it.
+    fn take_replayed_record(&mut self, offset: &BinlogPosition) -> Option<SchemaRecord> {
+        while self
+            .replayed_schema
+            .front()
+            .is_some_and(|r| &r.offset < offset)
+        {
+            self.replayed_schema.pop_front();
+        }
+        if self
+            .replayed_schema
+            .front()
+            .is_some_and(|r| &r.offset == offset)
+        {
+            self.replayed_schema.pop_front()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the labels of the ENUM and SET columns of a table by column name, from its definition
+    /// as of the rows, which has the labels of the table map event if the server sets
+    /// `binlog_row_metadata=FULL`.
+    fn column_labels(def: &MysqlTableDef) -> HashMap<String, Vec<String>> {
+        def.columns
+            .iter()
+            .filter_map(|c| match &c.data_type {
+                DataType::Enum(labels) | DataType::Set(labels) => {
+                    Some((c.name.clone(), labels.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn handle_write_rows(
+        &self,
+        rows: Vec<RowImages>,
+        def: Option<&MysqlTableDef>,
+    ) -> Result<Vec<RowChange>> {
+        rows.into_iter()
+            .map(|row| {
                 if row.0.is_some() {
                     bail!("unexpected 'before' in the UpdateRowsEvent")
                 }
@@ -258,25 +1025,59 @@ impl BinlogStream {
                     .1
                     .ok_or_else(|| anyhow!("'after' is missing in the UpdateRowsEvent"))?;
 
-                Ok(RowChange::Insert(after))
+                Ok(RowChange::Insert(ChangeRow::new(
+                    after,
+                    def,
+                    &self.decoder,
+                )?))
             })
             .collect::<Result<Vec<RowChange>>>()
     }
 
     fn handle_update_rows(
         &self,
-        tme: &TableMapEvent,
-        e: UpdateRowsEvent,
+        rows: Vec<RowImages>,
+        def: Option<&MysqlTableDef>,
     ) -> Result<Vec<RowChange>> {
         let mut changes: Vec<RowChange> = vec![];
-        for r in e.rows(tme) {
-            let row = r?;
+        for row in rows {
             let before = row
                 .0
                 .ok_or_else(|| anyhow!("'before' is missing in the UpdateRowsEvent"))?;
             let after = row
                 .1
                 .ok_or_else(|| anyhow!("'after' is missing in the UpdateRowsEvent"))?;
+            changes.push(RowChange::Delete(ChangeRow::new(
+                before,
+                def,
+                &self.decoder,
+            )?));
+            changes.push(RowChange::Insert(ChangeRow::new(
+                after,
+                def,
+                &self.decoder,
+            )?));
+        }
+        Ok(changes)
+    }
+
+    /// Like [`Self::handle_update_rows`], but the JSON columns of the rows after the updates may
+    /// only have the diffs from the rows before them.
+    fn handle_partial_update_rows(
+        &self,
+        rows: Vec<RowImages>,
+        def: Option<&MysqlTableDef>,
+    ) -> Result<Vec<RowChange>> {
+        let mut changes: Vec<RowChange> = vec![];
+        for row in rows {
+            let before = row
+                .0
+                .ok_or_else(|| anyhow!("'before' is missing in the PartialUpdateRowsEvent"))?;
+            let after = row
+                .1
+                .ok_or_else(|| anyhow!("'after' is missing in the PartialUpdateRowsEvent"))?;
+            let before = ChangeRow::new(before, def, &self.decoder)?;
+            let after = ChangeRow::with_json_diffs(after, &before, def, &self.decoder)?;
             changes.push(RowChange::Delete(before));
             changes.push(RowChange::Insert(after));
         }
@@ -285,19 +1086,22 @@ impl BinlogStream {
 
     fn handle_delete_rows(
         &self,
-        tme: &TableMapEvent,
-        e: DeleteRowsEvent,
+        rows: Vec<RowImages>,
+        def: Option<&MysqlTableDef>,
     ) -> Result<Vec<RowChange>> {
         let mut changes: Vec<RowChange> = vec![];
-        for r in e.rows(tme) {
-            let row = r?;
+        for row in rows {
             let before = row
                 .0
                 .ok_or_else(|| anyhow!("'before' is missing in the UpdateRowsEvent"))?;
             if row.1.is_some() {
                 bail!("unexpected 'after' in the UpdateRowsEvent")
             }
-            changes.push(RowChange::Delete(before));
+            changes.push(RowChange::Delete(ChangeRow::new(
+                before,
+                def,
+                &self.decoder,
+            )?));
         }
         Ok(changes)
     }
@@ -311,13 +1115,59 @@ impl futures_core::stream::Stream for BinlogStream {
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
+        match this.poll_event(cx) {
+            Poll::Ready(Some(Ok(mut event))) => {
+                if let Some(tracker) = &this.ack_tracker {
+                    match AckTracker::track(tracker, event.offset.clone()) {
+                        Ok(handle) => event.ack = Some(handle),
+                        Err(e) => return Poll::Ready(Some(Err(e))),
+                    }
+                }
+                Poll::Ready(Some(Ok(event)))
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl BinlogStream {
+    fn poll_event(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<Result<ChgcapEvent>>> {
         // TODO: Support rate limiting.
         loop {
-            let binlog_stream = Pin::new(&mut this.binlog_stream);
-            return match binlog_stream.poll_next(cx) {
+            if let Some(refresh) = &mut self.schema_refresh {
+                let result = match refresh.loading.poll_unpin(cx) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending => return Poll::Pending,
+                };
+                let refresh = self.schema_refresh.take().unwrap();
+                match result.and_then(|tables| self.complete_schema_refresh(refresh, tables)) {
+                    Ok(Some(event)) => return Poll::Ready(Some(Ok(event))),
+                    Ok(None) => {}
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+            if let Some(event) = self.new_tables.poll_event(cx, &self.schema) {
+                return Poll::Ready(Some(event));
+            }
+            // The offset is only computed when there's an incremental snapshot to poll.
+            if let Some(offset) = self.incremental_snapshot.is_some().then(|| self.offset()) {
+                let incremental_snapshot = self.incremental_snapshot.as_mut().unwrap();
+                if let Some(mut event) = incremental_snapshot.poll_event(cx, &offset) {
+                    if let Ok(event) = &mut event {
+                        event.offset = offset;
+                    }
+                    return Poll::Ready(Some(event));
+                }
+                if incremental_snapshot.is_blocked() {
+                    // Wait for the chunk, which will wake up the task once it's read.
+                    return Poll::Pending;
+                }
+            }
+            let binlog_stream = Pin::new(&mut self.binlog_stream);
+            return match futures_core::Stream::poll_next(binlog_stream, cx) {
                 Poll::Ready(t) => match t {
                     Some(event_result) => match event_result {
-                        Ok(event) => match this.handle_event(event) {
+                        Ok(event) => match self.handle_event(event) {
                             Ok(change) => match change {
                                 Some(c) => Poll::Ready(Some(Ok(c))),
                                 None => continue, // Skip this event.
@@ -333,3 +1183,85 @@ impl futures_core::stream::Stream for BinlogStream {
         }
     }
 }
+
+/// Returns `true` if the statement of a query event ends a transaction, i.e. a `COMMIT` or a
+/// `ROLLBACK` of the non-transactional engines or of an XA transaction, or a DDL, which is
+/// implicitly committed. The other statements, e.g. `BEGIN`, `SAVEPOINT`, `XA START` and the DML
+/// in statement format, are within a transaction.
+fn ends_transaction(query: &str) -> bool {
+    let mut query = query.trim_start();
+    // The statements may be prefixed with comments, e.g. `/* ApplicationName=... */`.
+    while let Some(rest) = query.strip_prefix("/*") {
+        query = rest
+            .split_once("*/")
+            .map_or("", |(_, rest)| rest)
+            .trim_start();
+    }
+    let words: Vec<String> = query
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .take(2)
+        .map(str::to_ascii_uppercase)
+        .collect();
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        ["COMMIT", ..] | ["XA", "COMMIT" | "ROLLBACK"] => true,
+        // `ROLLBACK TO SAVEPOINT` stays within the transaction.
+        ["ROLLBACK", next @ ..] => next != ["TO"],
+        [first, ..] => matches!(
+            *first,
+            "ALTER"
+                | "CREATE"
+                | "DROP"
+                | "RENAME"
+                | "TRUNCATE"
+                | "GRANT"
+                | "REVOKE"
+                | "ANALYZE"
+                | "OPTIMIZE"
+                | "REPAIR"
+                | "FLUSH"
+                | "INSTALL"
+                | "UNINSTALL"
+        ),
+        [] => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ends_transaction() {
+        for query in [
+            "COMMIT",
+            "commit",
+            "ROLLBACK",
+            "XA COMMIT X'31',X'',1",
+            "xa rollback X'31',X'',1",
+            "ALTER TABLE t ADD COLUMN c INT",
+            "/* comment */ CREATE TABLE t (id INT)",
+            "DROP TABLE `t` /* generated by server */",
+            "RENAME TABLE a TO b",
+            "TRUNCATE TABLE t",
+            "GRANT SELECT ON *.* TO 'u'@'%'",
+        ] {
+            assert!(ends_transaction(query), "{query}");
+        }
+        for query in [
+            "BEGIN",
+            "SAVEPOINT sp",
+            "ROLLBACK TO SAVEPOINT sp",
+            "ROLLBACK TO sp",
+            "XA START X'31',X'',1",
+            "XA END X'31',X'',1",
+            "INSERT INTO t VALUES (1)",
+            "/* comment */ UPDATE t SET c = 1",
+            "DELETE FROM t",
+            "",
+        ] {
+            assert!(!ends_transaction(query), "{query}");
+        }
+    }
+}
@@ -1,18 +1,16 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
+use anyhow::Result;
 use getset::{CopyGetters, Getters};
+use regex::Regex;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum SslMode {
+    #[default]
     Disabled,
 }
 
-impl Default for SslMode {
-    fn default() -> Self {
-        Self::Disabled
-    }
-}
-
 /// The set of predefined modes for dealing with failures during event processing.
 #[derive(Debug)]
 pub enum FailureHandlingMode {
@@ -30,6 +28,103 @@ pub enum FailureHandlingMode {
     Ignore,
 }
 
+/// How the DATE, TIME, DATETIME and TIMESTAMP values are represented in [`crate::Value`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemporalMode {
+    /// As [`crate::Value::Date`], [`crate::Value::Time`], [`crate::Value::DateTime`] and
+    /// [`crate::Value::Timestamp`].
+    #[default]
+    Adaptive,
+
+    /// As [`crate::Value::Int`] microseconds: a TIME is the elapsed microseconds, and the others
+    /// are the microseconds since the epoch, where a DATE is at midnight and a DATETIME is taken
+    /// as UTC.
+    Microseconds,
+
+    /// As [`crate::Value::String`] in ISO 8601, e.g. `2024-01-02`, `2024-01-02T03:04:05.000006`
+    /// and `2024-01-02T03:04:05Z` for a TIMESTAMP, which is in UTC. A TIME is
+    /// `[-]HH:MM:SS[.ffffff]`, whose hours may exceed 24.
+    IsoString,
+}
+
+/// How the zero dates, e.g. `0000-00-00`, and the invalid dates, e.g. `2024-02-30`, are
+/// represented. MySQL stores them unless the SQL modes `NO_ZERO_DATE` and `NO_ZERO_IN_DATE`
+/// forbid them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZeroDateMode {
+    /// As they are, e.g. a [`crate::Value::Date`] `0000-00-00` or the ISO string `0000-00-00`,
+    /// except in [`TemporalMode::Microseconds`] and for TIMESTAMP values, which have no instant
+    /// for them and are [`crate::Value::Null`].
+    #[default]
+    Preserve,
+
+    /// As [`crate::Value::Null`].
+    Null,
+
+    /// As the epoch `1970-01-01 00:00:00`.
+    Epoch,
+}
+
+/// How the DECIMAL values are represented in [`crate::Value`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalMode {
+    /// As exact [`crate::Value::Decimal`] values with the declared scale of their columns.
+    #[default]
+    Precise,
+
+    /// As [`crate::Value::String`] values, e.g. `-12.340`.
+    String,
+
+    /// As [`crate::Value::Double`] values, which may lose precision.
+    Double,
+}
+
+/// How the values of the binary strings, i.e. BINARY, VARBINARY and BLOB columns, are
+/// represented in [`crate::Value`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinaryMode {
+    /// As [`crate::Value::Bytes`] values.
+    #[default]
+    Bytes,
+
+    /// As [`crate::Value::String`] values in lowercase hex.
+    Hex,
+
+    /// As [`crate::Value::String`] values in standard base64 with padding.
+    Base64,
+}
+
+/// How the BIT values are represented in [`crate::Value`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitMode {
+    /// As [`crate::Value::Bit`] bitmasks.
+    #[default]
+    Bitmask,
+
+    /// As [`crate::Value::Bool`] values for BIT(1), and as bitmasks otherwise.
+    Boolean,
+
+    /// As binary strings of the big-endian bits, see [`BinaryMode`].
+    Binary,
+}
+
+/// Whether the virtual generated columns are emitted in the rows. The stored generated columns
+/// are always emitted like the other columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeneratedColumnMode {
+    /// The virtual columns are omitted from the snapshot and binlog rows, unless they're in the
+    /// column projection of the table, so that the rows have the same columns whether the row
+    /// images log the virtual columns or not. The row filters can't refer to them.
+    #[default]
+    Omit,
+
+    /// The virtual columns are emitted as the server provides them: the snapshot selects their
+    /// values, while the binlog rows have them only if the row images log them. They're not
+    /// computed by chgcap, so the binlog rows lack them e.g. under `binlog_row_image=MINIMAL`, or
+    /// on the servers that don't log the virtual columns.
+    AsLogged,
+}
+
 pub struct CommonConfig {
     failure_handling_mode: FailureHandlingMode,
 }
@@ -37,6 +132,23 @@ pub struct CommonConfig {
 /// TODO: Allow to load configurations from a YAML file.
 #[derive(Builder, Debug, Clone, Getters, CopyGetters)]
 pub struct SourceConfig {
+    /// How the values of the binary strings are represented, see [`BinaryMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    binary_mode: BinaryMode,
+
+    /// How the BIT values are represented, see [`BitMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    bit_mode: BitMode,
+
+    /// Per-table column projections, keyed by `databaseName.tableName`. Only the listed columns of
+    /// the table are captured, both in the snapshot and in the binlog events, e.g. to skip large
+    /// BLOB columns.
+    #[getset(get = "pub")]
+    #[builder(default)]
+    column_projections: HashMap<String, Vec<String>>,
+
     /// The maximum time that the connector should wait after trying to connect to the MySQL
     /// database server before timing out.
     #[getset(get = "pub")]
@@ -53,6 +165,16 @@ pub struct SourceConfig {
     #[builder(default)]
     database: String,
 
+    /// How the DECIMAL values are represented, see [`DecimalMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    decimal_mode: DecimalMode,
+
+    /// Whether the virtual generated columns are emitted, see [`GeneratedColumnMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    generated_column_mode: GeneratedColumnMode,
+
     /// The interval of heartbeat event.
     #[getset(get = "pub")]
     #[builder(default)]
@@ -66,6 +188,13 @@ pub struct SourceConfig {
     #[builder(default)]
     include_schema_changes: bool,
 
+    /// Per-table message key columns, keyed by `databaseName.tableName`. They're the columns of
+    /// [`crate::RowChange::key`] instead of the primary key, e.g. for tables without a primary
+    /// key.
+    #[getset(get = "pub")]
+    #[builder(default)]
+    message_key_columns: HashMap<String, Vec<String>>,
+
     /// Password to use when connecting to the MySQL database server.
     #[getset(get = "pub")]
     #[builder(default)]
@@ -75,7 +204,24 @@ pub struct SourceConfig {
     #[getset(get_copy = "pub")]
     port: u16,
 
-    /// Whether the [`MySqlSource`] should output the schema changes or not.
+    /// Per-table row filters, keyed by `databaseName.tableName`, e.g. `tenant_id = 42 AND
+    /// created_at > '2024-01-01'`. Only the matching rows are read in the snapshot, and only the
+    /// matching rows of the binlog events are emitted, so that the captured dataset stays
+    /// consistent over time. An update that moves a row out of the filter is emitted as a delete.
+    ///
+    /// The filter is a conjunction (`AND`) of conditions on a single column: `column <op> literal`,
+    /// `column IS [NOT] NULL` and `column [NOT] IN (literal, ...)`. Values of TIMESTAMP columns
+    /// are compared in UTC. Strings can only be compared in the
+    /// columns of binary strings or with `_bin` collations, since the other collations, e.g. the
+    /// default `utf8mb4_0900_ai_ci`, aren't reproduced on the binlog rows.
+    #[getset(get = "pub")]
+    #[builder(default)]
+    row_filters: HashMap<String, String>,
+
+    /// Whether to capture the tables that match [`SourceConfig::table_list`] after the stream
+    /// starts, i.e. the tables created or renamed while streaming, and the tables that newly match
+    /// after a configuration change, see [`crate::BinlogStream::with_captured_tables`]. The rows
+    /// that such a table already holds are snapshotted before its binlog events are emitted.
     #[getset(get_copy = "pub")]
     #[builder(default)]
     scan_newly_added_table_enabled: bool,
@@ -87,13 +233,36 @@ pub struct SourceConfig {
     #[getset(get_copy = "pub")]
     server_id: u32,
 
-    /// The session time zone in database server, e.g. "America/Los_Angeles". It controls how the
-    /// TIMESTAMP type in MYSQL converted to STRING. See more
+    /// The time zone of the server, e.g. "America/Los_Angeles". The TIMESTAMP values are in UTC
+    /// whatever it is: the binlog has them in UTC, and the connections read them in the `+00:00`
+    /// session time zone, where the server converts them with its own time zone rules. See more
     /// https://debezium.io/documentation/reference/1.5/connectors/mysql.html#mysql-temporal-types
     #[getset(get = "pub")]
     #[builder(default)]
     server_timezone: String,
 
+    /// The `databaseName.tableName` of the signal table, see [`crate::BinlogStream`]. The
+    /// inserts into the signal table are treated as commands rather than data changes, e.g. to
+    /// take an incremental snapshot of a table while streaming:
+    /// ```sql
+    /// INSERT INTO db.chgcap_signal VALUES
+    ///     ('ad-hoc-1', 'execute-snapshot', '{"data-collections": ["db.orders"]}');
+    /// ```
+    /// The supported signal types are `execute-snapshot`, `stop-snapshot`, `pause-snapshot` and
+    /// `resume-snapshot`. The snapshots of the tables that are not captured are ignored. The signal
+    /// table is also written by chgcap to delimit the snapshot chunks, so the user must have the
+    /// INSERT privilege on it.
+    #[getset(get = "pub")]
+    #[builder(default)]
+    signal_table: Option<String>,
+
+    /// Whether to drop the updates that change no captured column, e.g. the updates of only the
+    /// columns that are projected out by [`SourceConfig::column_projections`], see
+    /// [`crate::RowChange::changed_columns`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    skip_noop_updates: bool,
+
     /// The group size of split meta, if the meta size exceeds the group size, the meta will be
     /// divided into multiple groups.
     #[getset(get_copy = "pub")]
@@ -101,9 +270,9 @@ pub struct SourceConfig {
     split_meta_group_size: i32,
 
     /// The split size (number of rows) of table snapshot, captured tables are split into multiple
-    /// splits when read the snapshot of table.
+    /// splits when read the snapshot of table. Defaults to 8192.
     #[getset(get_copy = "pub")]
-    #[builder(default)]
+    #[builder(default = "8192")]
     split_size: i32,
 
     #[getset(get = "pub")]
@@ -118,30 +287,98 @@ pub struct SourceConfig {
     #[builder(default)]
     table_list: Vec<String>,
 
+    /// How the temporal values are represented, see [`TemporalMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    temporal_mode: TemporalMode,
+
     /// Name of the MySQL database to use when connecting to the MySQL database server.
     #[getset(get = "pub")]
     username: String,
+
+    /// How the zero and invalid dates are represented, see [`ZeroDateMode`].
+    #[getset(get_copy = "pub")]
+    #[builder(default)]
+    zero_date_mode: ZeroDateMode,
 }
 
 impl Default for SourceConfig {
     fn default() -> Self {
         Self {
+            binary_mode: Default::default(),
+            bit_mode: Default::default(),
+            column_projections: Default::default(),
             connect_timeout: Duration::from_secs(5),
             connection_pool_size: 10,
             database: Default::default(),
+            decimal_mode: Default::default(),
+            generated_column_mode: Default::default(),
             heartbeat_interval: Duration::from_secs(3),
             hostname: Default::default(),
             include_schema_changes: Default::default(),
+            message_key_columns: Default::default(),
+            row_filters: Default::default(),
             scan_newly_added_table_enabled: Default::default(),
             server_id: Default::default(),
             server_timezone: Default::default(),
+            signal_table: Default::default(),
+            skip_noop_updates: Default::default(),
             split_meta_group_size: Default::default(),
-            split_size: Default::default(),
+            split_size: 8192,
             ssl_mode: SslMode::Disabled,
             table_list: Default::default(),
+            temporal_mode: Default::default(),
             username: Default::default(),
+            zero_date_mode: Default::default(),
             password: Default::default(),
             port: Default::default(),
         }
     }
 }
+
+/// Decides which tables are captured according to [`SourceConfig::database`] and
+/// [`SourceConfig::table_list`].
+#[derive(Debug, Clone)]
+pub(crate) struct TableFilter {
+    database: String,
+    table_list: Vec<Regex>,
+}
+
+impl TableFilter {
+    pub fn new(cfg: &SourceConfig) -> Result<Self> {
+        let table_list = cfg
+            .table_list()
+            .iter()
+            .map(|pattern| Regex::new(&format!("^(?:{pattern})$")))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            database: cfg.database().clone(),
+            table_list,
+        })
+    }
+
+    /// Returns `true` if the table `database_name.table_name` should be captured.
+    pub fn matches(&self, database_name: &str, table_name: &str) -> bool {
+        if self.database.is_empty() {
+            if self.table_list.is_empty() && is_system_database(database_name) {
+                return false;
+            }
+        } else if self.database != database_name {
+            return false;
+        }
+        if self.table_list.is_empty() {
+            return true;
+        }
+        let identifier = format!("{database_name}.{table_name}");
+        self.table_list.iter().any(|r| r.is_match(&identifier))
+    }
+}
+
+/// Returns `true` for the built-in databases that are never captured unless they are explicitly
+/// configured.
+fn is_system_database(database_name: &str) -> bool {
+    matches!(
+        database_name,
+        "information_schema" | "mysql" | "performance_schema" | "sys"
+    )
+}
@@ -17,6 +17,7 @@ impl MysqlConn {
     /// Determine if the current user has the named privilege. Note that if the user has the "ALL"
     /// privilege this method returns {@code true}.
     /// - `grantName`: the name of the MySQL privilege; may not be null.
+    ///
     /// Returns `true` if the user has the named privilege, or `false` otherwise.
     pub fn user_has_priviledge(&self, _grant_name: String) -> Result<bool> {
         todo!()
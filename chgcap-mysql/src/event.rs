@@ -1,82 +1,433 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use base64::prelude::*;
 use getset::{CopyGetters, Getters};
 use itertools::Itertools;
+use log::warn;
 use mysql_async::binlog::jsonb::{self, Array, Object, StorageFormat};
 use mysql_async::binlog::row::BinlogRow;
 use mysql_async::binlog::value::BinlogValue;
 use mysql_async::consts::ColumnType;
+use mysql_async::{Column, Value as MysqlValue};
+use mysql_common::binlog::decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Clone, PartialEq)]
+use crate::ack::AckHandle;
+use crate::json_diff::apply_json_diffs;
+use crate::rows::column_name;
+use crate::schema::MysqlTableDef;
+use crate::serialized::column_type_name;
+use crate::value::{civil_from_days, format_micros, Value as ColumnValue, ValueDecoder};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum RowChange {
-    Insert(BinlogRow),
-    Delete(BinlogRow),
-}
-
-fn fmt_column_type(c: &ColumnType) -> String {
-    let s = match c {
-        ColumnType::MYSQL_TYPE_DECIMAL => "DECIMAL",
-        ColumnType::MYSQL_TYPE_TINY => "TINYINT",
-        ColumnType::MYSQL_TYPE_SHORT => "SMALLINT",
-        ColumnType::MYSQL_TYPE_LONG => "INT",
-        ColumnType::MYSQL_TYPE_FLOAT => "FLOAT",
-        ColumnType::MYSQL_TYPE_DOUBLE => "DOUBLE",
-        ColumnType::MYSQL_TYPE_NULL => "NULL",
-        ColumnType::MYSQL_TYPE_TIMESTAMP => "TIMESTAMP",
-        ColumnType::MYSQL_TYPE_LONGLONG => "BIGINT",
-        ColumnType::MYSQL_TYPE_INT24 => "MEDIUMINT",
-        ColumnType::MYSQL_TYPE_DATE => "DATE",
-        ColumnType::MYSQL_TYPE_TIME => "TIME",
-        ColumnType::MYSQL_TYPE_DATETIME => "DATETIME",
-        ColumnType::MYSQL_TYPE_YEAR => "YEAR",
-        ColumnType::MYSQL_TYPE_NEWDATE => "NEWDATE", // Internal to MySql
-        ColumnType::MYSQL_TYPE_VARCHAR => "VARCHAR",
-        ColumnType::MYSQL_TYPE_BIT => "BIT",
-        ColumnType::MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP2",
-        ColumnType::MYSQL_TYPE_DATETIME2 => "DATETIME2",
-        ColumnType::MYSQL_TYPE_TIME2 => "TIME2",
-        ColumnType::MYSQL_TYPE_TYPED_ARRAY => "TYPED ARRAY", // Used for replication only
-        ColumnType::MYSQL_TYPE_UNKNOWN => "UNKNOWN",
-        ColumnType::MYSQL_TYPE_JSON => "JSON",
-        ColumnType::MYSQL_TYPE_NEWDECIMAL => "NEWDECIMAL",
-        ColumnType::MYSQL_TYPE_ENUM => "ENUM",
-        ColumnType::MYSQL_TYPE_SET => "SET",
-        ColumnType::MYSQL_TYPE_TINY_BLOB => "TINYBLOB",
-        ColumnType::MYSQL_TYPE_MEDIUM_BLOB => "MEDIUMBLOB",
-        ColumnType::MYSQL_TYPE_LONG_BLOB => "LONGBLOB",
-        ColumnType::MYSQL_TYPE_BLOB => "BLOB",
-        ColumnType::MYSQL_TYPE_VAR_STRING => "VAR_STRING",
-        ColumnType::MYSQL_TYPE_STRING => "STRING",
-        ColumnType::MYSQL_TYPE_GEOMETRY => "GEOMETRY",
-    };
-    s.to_string()
+    Insert(Row),
+    Delete(Row),
+    /// A row read from the table snapshot rather than the binlog. `last` is set on the last row
+    /// of the table, if the table is not empty. In an incremental snapshot, the rows changed while
+    /// their chunk is read are emitted by the binlog instead, so no row is flagged if all the rows
+    /// of the last chunk are changed, while [`EventData::TableSnapshotCompleted`] always follows
+    /// the last row.
+    Read {
+        row: Row,
+        last: bool,
+    },
+}
+
+/// A row of a table, whose values are decoded into [`ColumnValue`]s. Only the decoded row is kept,
+/// so that a deserialized row is equal to the row and is displayed the same.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Row {
+    pub(crate) columns: Vec<RowColumn>,
+    pub(crate) values: Vec<ColumnValue>,
+
+    /// The names of the key columns, see [`Row::key`].
+    pub(crate) key_columns: Arc<[String]>,
+
+    /// The names of the columns changed by the update of the row, see
+    /// [`RowChange::changed_columns`]. It's shared by both rows of the update and includes the
+    /// columns that are projected out.
+    pub(crate) changed_columns: Option<Arc<[String]>>,
 }
 
-fn fmt_value(val: &BinlogValue, ty: &ColumnType) -> String {
-    match val {
-        BinlogValue::Value(v) => {
-            format!("{}({})", fmt_column_type(ty), v.as_sql(true))
+/// A column of a [`Row`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RowColumn {
+    /// The name of the column, which is `@<i>` after its position `i` in the table if it's
+    /// unknown.
+    pub(crate) name: String,
+
+    /// The type of the column in the binlog, which is serialized by its name, e.g. `"VARCHAR"`.
+    #[serde(with = "crate::serialized")]
+    pub(crate) column_type: ColumnType,
+}
+
+impl Row {
+    /// Decodes the values of a binlog row, or of a snapshot row converted to a binlog row. The
+    /// definition of its table, if it's known, completes what the binlog may lack: the character
+    /// sets of the strings, which the binlog has since MySQL 8.0.1, the precisions and scales of the
+    /// decimals and the widths of the BITs. The definition of a binlog row is the one as of the row,
+    /// completed with the metadata of its table map event.
+    pub(crate) fn new(
+        raw: BinlogRow,
+        def: Option<&MysqlTableDef>,
+        decoder: &ValueDecoder,
+    ) -> Result<Self> {
+        let values = raw
+            .columns_ref()
+            .iter()
+            .enumerate()
+            .map(|(i, column)| decode_column(raw.as_ref(i), column, def, decoder))
+            .collect::<Result<_>>()?;
+        Ok(Self::decoded(&raw, values))
+    }
+
+    /// Decodes the row after a partial update, whose JSON columns may only have the diffs from
+    /// the row `before` it rather than the whole documents. The diffs can't be applied if the
+    /// row before doesn't have the document, e.g. under `binlog_row_image=MINIMAL`, in which
+    /// case the column is omitted from the row, like the other columns missing from the image.
+    pub(crate) fn with_json_diffs(
+        raw: BinlogRow,
+        before: &Row,
+        def: Option<&MysqlTableDef>,
+        decoder: &ValueDecoder,
+    ) -> Result<Self> {
+        let mut missing = vec![];
+        let values = raw
+            .columns_ref()
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                // The columns of the row images are told apart by name, which is `@<i>` if it's
+                // unknown, since the images may have different columns.
+                let name = column.name_str();
+                match (raw.as_ref(i), before.get_by_name(&name)) {
+                    (Some(BinlogValue::JsonDiff(diffs)), Some(ColumnValue::Json(doc))) => {
+                        apply_json_diffs(doc.clone(), diffs)
+                            .map(ColumnValue::Json)
+                            .map_err(|e| anyhow!("Failed to decode column {name}: {e}"))
+                    }
+                    (Some(BinlogValue::JsonDiff(_)), Some(_)) => Err(anyhow!(
+                        "Failed to decode column {name}: The JSON diffs are applied to a value \
+                         that isn't JSON"
+                    )),
+                    (Some(BinlogValue::JsonDiff(_)), None) => {
+                        missing.push(i);
+                        Ok(ColumnValue::Null)
+                    }
+                    (value, _) => decode_column(value, column, def, decoder),
+                }
+            })
+            .collect::<Result<_>>()?;
+        let row = Self::decoded(&raw, values);
+        if missing.is_empty() {
+            return Ok(row);
+        }
+        warn!(
+            "Omitted the partially updated JSON columns {:?} whose documents are not logged \
+             before the update",
+            missing
+                .iter()
+                .map(|&i| row.columns[i].name.as_str())
+                .collect::<Vec<_>>()
+        );
+        let indexes: Vec<usize> = (0..row.values.len())
+            .filter(|i| !missing.contains(i))
+            .collect();
+        Ok(row.project(&indexes))
+    }
+
+    /// Returns the row of the columns of a binlog row, whose values are decoded into `values`.
+    fn decoded(raw: &BinlogRow, values: Vec<ColumnValue>) -> Self {
+        let columns = raw
+            .columns_ref()
+            .iter()
+            .map(|c| RowColumn {
+                name: c.name_str().into_owned(),
+                column_type: c.column_type(),
+            })
+            .collect();
+        Self {
+            columns,
+            values,
+            key_columns: Arc::new([]),
+            changed_columns: None,
         }
-        BinlogValue::Jsonb(v) => format!("JSON({})", fmt_jsonb(v)),
-        BinlogValue::JsonDiff(_) => todo!(),
+    }
+
+    /// Returns the values of the columns, in the order of [`Row::column_names`].
+    pub fn values(&self) -> &[ColumnValue] {
+        &self.values
+    }
+
+    /// Returns the names of the columns. A column is named `@<i>` after its position `i` in the
+    /// table if its name is unknown.
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Returns the index of the column named `name`, which is matched case-insensitively as MySQL
+    /// does.
+    pub(crate) fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of the column named `name`, which is matched case-insensitively as MySQL
+    /// does. `None` if the row has no such column.
+    pub fn get_by_name(&self, name: &str) -> Option<&ColumnValue> {
+        self.values.get(self.column_index(name)?)
+    }
+
+    /// Returns the names of the key columns, which are the primary key of the table, or the
+    /// [`crate::SourceConfig::message_key_columns`] of the table if they're configured. Empty if
+    /// the table has neither.
+    pub fn key_columns(&self) -> &[String] {
+        &self.key_columns
+    }
+
+    /// Returns the key of the row, i.e. the row with only the [`Row::key_columns`] in that order.
+    /// `None` if the row has no key, or if a key column is not captured, e.g. by the
+    /// [`crate::SourceConfig::column_projections`].
+    pub fn key(&self) -> Option<Row> {
+        if self.key_columns.is_empty() {
+            return None;
+        }
+        let indexes = self
+            .key_columns
+            .iter()
+            .map(|k| self.column_index(k))
+            .collect::<Option<Vec<_>>>()?;
+        Some(self.clone().project(&indexes))
+    }
+
+    pub(crate) fn with_key_columns(mut self, key_columns: Arc<[String]>) -> Self {
+        self.key_columns = key_columns;
+        self
+    }
+
+    /// Returns the names of the columns changed from the row `before` an update to the row
+    /// `after` it. A column is unchanged if it's not in the row after the update, e.g. with
+    /// `binlog_row_image=MINIMAL`, and changed if it's only in the row after the update. Two NULLs
+    /// are equal.
+    pub(crate) fn changed_columns_between(before: &Row, after: &Row) -> Arc<[String]> {
+        after
+            .columns
+            .iter()
+            .zip(&after.values)
+            .filter(|(c, value)| before.get_by_name(&c.name) != Some(value))
+            .map(|(c, _)| c.name.clone())
+            .collect()
+    }
+
+    pub(crate) fn with_changed_columns(mut self, changed_columns: Arc<[String]>) -> Self {
+        self.changed_columns = Some(changed_columns);
+        self
+    }
+
+    /// Names the columns of the row that are named `@<i>` after `column_names`, see
+    /// [`crate::rows::with_column_names`].
+    pub(crate) fn with_column_names(mut self, column_names: &[String]) -> Result<Self> {
+        for column in &mut self.columns {
+            if let Some(name) = column_name(&column.name, column_names)? {
+                column.name = name.to_string();
+            }
+        }
+        Ok(self)
+    }
+
+    /// Returns the row with only the columns at `indexes`, in that order.
+    pub(crate) fn project(self, indexes: &[usize]) -> Self {
+        let mut columns: Vec<_> = self.columns.into_iter().map(Some).collect();
+        let mut values: Vec<_> = self.values.into_iter().map(Some).collect();
+        Self {
+            columns: indexes.iter().filter_map(|&i| columns[i].take()).collect(),
+            values: indexes.iter().filter_map(|&i| values[i].take()).collect(),
+            key_columns: self.key_columns,
+            changed_columns: self.changed_columns,
+        }
+    }
+
+    /// Returns the row without the virtual generated columns of its table.
+    pub(crate) fn without_virtual_columns(self, def: &MysqlTableDef) -> Self {
+        let indexes: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !def.column(&c.name).is_some_and(|c| c.is_virtual()))
+            .map(|(i, _)| i)
+            .collect();
+        if indexes.len() == self.values.len() {
+            return self;
+        }
+        self.project(&indexes)
+    }
+
+    /// Replaces the indexes of the ENUM values and the bitmasks of the SET values of a binlog row
+    /// with their labels. `labels` has the labels of the ENUM and SET columns by column name.
+    pub(crate) fn with_labels(mut self, labels: &HashMap<String, Vec<String>>) -> Result<Self> {
+        for (column, value) in self.columns.iter().zip(self.values.iter_mut()) {
+            let name = &column.name;
+            let (Some(labels), ColumnValue::UInt(n)) = (labels.get(name), &value) else {
+                continue;
+            };
+            let n = *n;
+            *value = match column.column_type {
+                // An invalid value is stored as the index 0, i.e. the empty string.
+                ColumnType::MYSQL_TYPE_ENUM if n == 0 => ColumnValue::Enum(String::new()),
+                ColumnType::MYSQL_TYPE_ENUM => {
+                    let label = labels.get(n as usize - 1).ok_or_else(|| {
+                        anyhow!("Invalid index {n} of ENUM column {name} of {labels:?}")
+                    })?;
+                    ColumnValue::Enum(label.clone())
+                }
+                ColumnType::MYSQL_TYPE_SET => {
+                    if labels.len() < 64 && n >> labels.len() != 0 {
+                        bail!("Invalid bitmask {n:#b} of SET column {name} of {labels:?}");
+                    }
+                    let labels = labels
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| n & (1 << i) != 0)
+                        .map(|(_, label)| label.clone())
+                        .collect();
+                    ColumnValue::Set(labels)
+                }
+                _ => continue,
+            };
+        }
+        Ok(self)
     }
 }
 
-fn fmt_row(row: &BinlogRow) -> String {
-    let columns = row.columns_ref();
-    (0..row.len())
-        .map(|i| match row.as_ref(i) {
-            Some(v) => fmt_value(v, &columns[i].column_type()),
-            None => "NULL".to_string(),
-        })
-        .join(",")
+/// Decodes the value of `column` of a binlog row, which is completed with the definition of its
+/// table, see [`Row::new`].
+fn decode_column(
+    value: Option<&BinlogValue>,
+    column: &Column,
+    def: Option<&MysqlTableDef>,
+    decoder: &ValueDecoder,
+) -> Result<ColumnValue> {
+    let name = column.name_str();
+    let decoded = decoder
+        .decode(value, column)
+        .map_err(|e| anyhow!("Failed to decode column {name}: {e}"))?;
+    let Some(column_def) = def.and_then(|def| def.column(&name)) else {
+        return Ok(decoded);
+    };
+    Ok(match (decoded, value) {
+        (
+            decoded @ (ColumnValue::String(_) | ColumnValue::Bytes(_)),
+            Some(BinlogValue::Value(MysqlValue::Bytes(bytes))),
+        ) if column.character_set() == 0 => match &column_def.charset {
+            Some(charset) => decoder.decode_string(bytes, Some(charset)),
+            None => decoded,
+        },
+        (ColumnValue::Decimal(decimal), _) => {
+            match (column_def.decimal_precision(), column_def.decimal_scale()) {
+                (Some(precision), Some(scale)) => {
+                    let decimal = decimal.with_scale(scale as i64);
+                    // More digits than the precision means the value was written under another
+                    // definition of the column, whose scale may differ too.
+                    if decimal.digits() > precision {
+                        bail!(
+                            "The value {decimal} of column {name} doesn't fit in \
+                             DECIMAL({precision},{scale})"
+                        );
+                    }
+                    ColumnValue::Decimal(decimal)
+                }
+                _ => ColumnValue::Decimal(decimal),
+            }
+        }
+        (ColumnValue::Bit(bits), _) => decoder.bits(bits, column_def.bit_width()),
+        (decoded, _) => decoded,
+    })
+}
+
+/// Formats a value of a column of type `ty` as a SQL literal, e.g. `VARCHAR('a')`.
+fn fmt_value(value: &ColumnValue, ty: ColumnType) -> String {
+    // The ENUM and SET columns have the types of strings in the snapshots.
+    let ty = match value {
+        ColumnValue::Enum(_) => "ENUM",
+        ColumnValue::Set(_) => "SET",
+        _ => column_type_name(ty),
+    };
+    let sql = match value {
+        ColumnValue::Null => "NULL".to_string(),
+        ColumnValue::Bool(b) => b.to_string().to_uppercase(),
+        ColumnValue::Int(n) => n.to_string(),
+        ColumnValue::UInt(n) => n.to_string(),
+        ColumnValue::Float(f) => MysqlValue::Float(*f).as_sql(true),
+        ColumnValue::Double(d) => MysqlValue::Double(*d).as_sql(true),
+        ColumnValue::Decimal(decimal) => format!("'{decimal}'"),
+        ColumnValue::Date { year, month, day } => {
+            MysqlValue::Date(*year, *month, *day, 0, 0, 0, 0).as_sql(true)
+        }
+        ColumnValue::Time {
+            negative,
+            hours,
+            minutes,
+            seconds,
+            micros,
+        } => MysqlValue::Time(
+            *negative,
+            hours / 24,
+            (hours % 24) as u8,
+            *minutes,
+            *seconds,
+            *micros,
+        )
+        .as_sql(true),
+        ColumnValue::DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            micros,
+        } => MysqlValue::Date(*year, *month, *day, *hour, *minute, *second, *micros).as_sql(true),
+        // A TIMESTAMP is displayed in UTC.
+        ColumnValue::Timestamp(micros) => {
+            let secs = micros.div_euclid(1_000_000);
+            let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+            let secs_of_day = secs.rem_euclid(86_400);
+            MysqlValue::Date(
+                year as u16,
+                month as u8,
+                day as u8,
+                (secs_of_day / 3_600) as u8,
+                (secs_of_day % 3_600 / 60) as u8,
+                (secs_of_day % 60) as u8,
+                micros.rem_euclid(1_000_000) as u32,
+            )
+            .as_sql(true)
+        }
+        ColumnValue::Bytes(bytes) => MysqlValue::Bytes(bytes.clone()).as_sql(true),
+        ColumnValue::String(s) | ColumnValue::Enum(s) => MysqlValue::from(s.as_str()).as_sql(true),
+        ColumnValue::Set(labels) => MysqlValue::from(labels.join(",")).as_sql(true),
+        ColumnValue::Json(doc) => doc.to_string(),
+        ColumnValue::Bit(bits) => format!("b'{bits:b}'"),
+        ColumnValue::Geometry(geometry) => MysqlValue::from(geometry.to_wkt()).as_sql(true),
+    };
+    format!("{ty}({sql})")
 }
 
-fn fmt_jsonb(v: &jsonb::Value) -> String {
-    to_serde_json(v).to_string()
+fn fmt_row(row: &Row) -> String {
+    row.columns
+        .iter()
+        .zip(&row.values)
+        .map(|(column, value)| fmt_value(value, column.column_type))
+        .join(",")
 }
 
-fn to_serde_json(v: &jsonb::Value) -> serde_json::Value {
-    match v {
+pub(crate) fn to_serde_json(v: &jsonb::Value) -> Result<serde_json::Value> {
+    let v = match v {
         jsonb::Value::Null => Value::Null,
         jsonb::Value::Bool(v) => (*v).into(),
         jsonb::Value::I16(v) => (*v).into(),
@@ -87,25 +438,182 @@ fn to_serde_json(v: &jsonb::Value) -> serde_json::Value {
         jsonb::Value::U64(v) => (*v).into(),
         jsonb::Value::F64(v) => (*v).into(),
         jsonb::Value::String(v) => serde_json::Value::String(v.str().to_string()),
-        jsonb::Value::SmallArray(v) => to_serde_json_array(v),
-        jsonb::Value::LargeArray(v) => to_serde_json_array(v),
-        jsonb::Value::SmallObject(v) => to_serde_json_object(v),
-        jsonb::Value::LargeObject(v) => to_serde_json_object(v),
-        jsonb::Value::Opaque(v) => {
-            serde_json::Value::from_iter([(fmt_column_type(&v.value_type()), v.data().to_string())])
+        jsonb::Value::SmallArray(v) => to_serde_json_array(v)?,
+        jsonb::Value::LargeArray(v) => to_serde_json_array(v)?,
+        jsonb::Value::SmallObject(v) => to_serde_json_object(v)?,
+        jsonb::Value::LargeObject(v) => to_serde_json_object(v)?,
+        jsonb::Value::Opaque(v) => opaque_to_serde_json(v.value_type(), v.data_raw())?,
+    };
+    Ok(v)
+}
+
+/// Converts a MySQL value embedded in a JSON document, e.g. by `CAST(... AS JSON)`, which is
+/// encoded as in the storage of its column type. Decimals become strings of their exact digits,
+/// temporal values become ISO 8601 strings, and the other values, e.g. binary strings, become
+/// base64 strings.
+fn opaque_to_serde_json(value_type: ColumnType, data: &[u8]) -> Result<serde_json::Value> {
+    let v = match value_type {
+        ColumnType::MYSQL_TYPE_NEWDECIMAL | ColumnType::MYSQL_TYPE_DECIMAL => {
+            let [precision, scale, data @ ..] = data else {
+                bail!("Invalid DECIMAL in JSON {data:?}");
+            };
+            let decimal = Decimal::read_bin(data, *precision as usize, *scale as usize, false)?;
+            // A JSON number can't keep the exact digits of a decimal, e.g. `-12.50`.
+            serde_json::Value::String(decimal.to_string())
+        }
+        ColumnType::MYSQL_TYPE_DATE
+        | ColumnType::MYSQL_TYPE_NEWDATE
+        | ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_DATETIME2
+        | ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2
+        | ColumnType::MYSQL_TYPE_TIME
+        | ColumnType::MYSQL_TYPE_TIME2 => {
+            let packed = i64::from_le_bytes(
+                data.try_into()
+                    .map_err(|_| anyhow!("Invalid temporal value in JSON {data:?}"))?,
+            );
+            serde_json::Value::String(format_packed_temporal(value_type, packed))
         }
+        _ => serde_json::Value::String(BASE64_STANDARD.encode(data)),
+    };
+    Ok(v)
+}
+
+/// Formats a temporal value packed in the in-memory format of MySQL as ISO 8601.
+fn format_packed_temporal(value_type: ColumnType, packed: i64) -> String {
+    let negative = packed < 0;
+    let packed = packed.unsigned_abs();
+    let micros = (packed % (1 << 24)) as u32;
+    let int_part = packed >> 24;
+    let hms = int_part % (1 << 17);
+    let (hour, minute, second) = (hms >> 12, (hms >> 6) % (1 << 6), hms % (1 << 6));
+    if matches!(
+        value_type,
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2
+    ) {
+        // The hours of a TIME are not limited to a day.
+        let hour = int_part >> 12;
+        return format!(
+            "{}{hour:02}:{minute:02}:{second:02}{}",
+            if negative { "-" } else { "" },
+            format_micros(micros)
+        );
+    }
+    let ymd = int_part >> 17;
+    let (year, month, day) = ((ymd >> 5) / 13, (ymd >> 5) % 13, ymd % (1 << 5));
+    let date = format!("{year:04}-{month:02}-{day:02}");
+    if matches!(
+        value_type,
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE
+    ) {
+        return date;
     }
+    format!(
+        "{date}T{hour:02}:{minute:02}:{second:02}{}",
+        format_micros(micros)
+    )
 }
 
-fn to_serde_json_array<T: StorageFormat>(v: &jsonb::ComplexValue<T, Array>) -> serde_json::Value {
-    serde_json::Value::from_iter(v.iter().map(|e| to_serde_json(&e.unwrap())))
+fn to_serde_json_array<T: StorageFormat>(
+    v: &jsonb::ComplexValue<T, Array>,
+) -> Result<serde_json::Value> {
+    v.iter().map(|e| to_serde_json(&e?)).collect()
 }
 
-fn to_serde_json_object<T: StorageFormat>(v: &jsonb::ComplexValue<T, Object>) -> serde_json::Value {
-    serde_json::Value::from_iter(v.iter().map(|e| {
-        let (key, val) = e.unwrap();
-        (key.value().to_string(), to_serde_json(&val))
-    }))
+fn to_serde_json_object<T: StorageFormat>(
+    v: &jsonb::ComplexValue<T, Object>,
+) -> Result<serde_json::Value> {
+    let object = v
+        .iter()
+        .map(|e| {
+            let (key, val) = e?;
+            Ok((key.value().to_string(), to_serde_json(&val)?))
+        })
+        .collect::<Result<serde_json::Map<_, _>>>()?;
+    Ok(serde_json::Value::Object(object))
+}
+
+impl RowChange {
+    /// Returns the row of the change.
+    pub fn row(&self) -> &Row {
+        match self {
+            Self::Insert(row) | Self::Delete(row) | Self::Read { row, .. } => row,
+        }
+    }
+
+    /// Returns the names of the columns of the row, see [`Row::column_names`].
+    pub fn column_names(&self) -> Vec<String> {
+        self.row().column_names()
+    }
+
+    /// Returns the key of the row, e.g. the key of a Kafka message, see [`Row::key`].
+    pub fn key(&self) -> Option<Row> {
+        self.row().key()
+    }
+
+    /// Returns the value of the row, e.g. the value of a Kafka message, which is the whole row.
+    pub fn value(&self) -> &Row {
+        self.row()
+    }
+
+    /// Returns the names of the columns changed by the update of the change, which is emitted as
+    /// a [`RowChange::Delete`] of the row before it followed by a [`RowChange::Insert`] of the
+    /// row after it, both of which have the changed columns. Only the columns of the row are
+    /// returned, i.e. not the projected-out ones. `None` if the change is not from an update. See
+    /// [`crate::SourceConfig::skip_noop_updates`] to drop the updates that change no column.
+    pub fn changed_columns(&self) -> Option<Vec<String>> {
+        let row = self.row();
+        let changed_columns = row.changed_columns.as_ref()?;
+        Some(
+            row.columns
+                .iter()
+                .map(|c| &c.name)
+                .filter(|name| changed_columns.contains(name))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Returns the value of the column named `name`, see [`Row::get_by_name`].
+    pub fn get_by_name(&self, name: &str) -> Option<&ColumnValue> {
+        self.row().get_by_name(name)
+    }
+
+    /// Applies `f` to the row of the change.
+    pub(crate) fn try_map_row(self, f: impl FnOnce(Row) -> Result<Row>) -> Result<Self> {
+        Ok(match self {
+            Self::Insert(row) => Self::Insert(f(row)?),
+            Self::Delete(row) => Self::Delete(f(row)?),
+            Self::Read { row, last } => Self::Read { row: f(row)?, last },
+        })
+    }
+}
+
+/// Drops the updates that change no column, i.e. the pairs of a [`RowChange::Delete`] followed by a
+/// [`RowChange::Insert`] of an update whose changed columns are empty.
+pub(crate) fn without_noop_updates(changes: Vec<RowChange>) -> Vec<RowChange> {
+    let is_noop = |c: &RowChange| c.changed_columns().is_some_and(|c| c.is_empty());
+    let mut kept = Vec::with_capacity(changes.len());
+    let mut changes = changes.into_iter().peekable();
+    while let Some(change) = changes.next() {
+        // Both halves of an update share the changed columns, unless the other half is filtered
+        // out because the update moves the row out of or into the row filter.
+        if let (RowChange::Delete(before), Some(RowChange::Insert(after))) =
+            (&change, changes.peek())
+        {
+            let same_update = match (&before.changed_columns, &after.changed_columns) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                _ => false,
+            };
+            if same_update && is_noop(&change) {
+                changes.next();
+                continue;
+            }
+        }
+        kept.push(change);
+    }
+    kept
 }
 
 impl std::fmt::Display for RowChange {
@@ -113,28 +621,125 @@ impl std::fmt::Display for RowChange {
         match self {
             Self::Insert(row) => write!(f, "Insert({})", fmt_row(row)),
             Self::Delete(row) => write!(f, "Delete({})", fmt_row(row)),
+            Self::Read { row, .. } => write!(f, "Read({})", fmt_row(row)),
         }
     }
 }
 
-#[derive(Clone, PartialEq)]
-pub enum SchemaChange {
-    Create,
-    Alter,
-    Drop,
+/// A DDL statement that changed the captured tables. It's emitted if
+/// [`crate::SourceConfig::include_schema_changes`] is set, in an [`Event`] whose `table_id` is 0,
+/// since a DDL statement has no table map, and whose database and table names are the ones of the
+/// first of [`SchemaChange::tables`]. The other tables are only listed in the schema change.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct SchemaChange {
+    /// The DDL statement.
+    pub(crate) ddl: String,
+
+    /// The default database of the DDL statement.
+    pub(crate) database_name: String,
+
+    /// The `databaseName.tableName` of the captured tables changed by the DDL statement, in the
+    /// order of the statement. A renamed table is listed under both its old and new names.
+    pub(crate) tables: Vec<String>,
+
+    /// The definitions of the tables before the change, keyed by `databaseName.tableName`. A
+    /// table that didn't exist, e.g. a created one, is mapped to `None`.
+    pub(crate) before: BTreeMap<String, Option<MysqlTableDef>>,
+
+    /// The definitions of the tables after the change, keyed by `databaseName.tableName`, which
+    /// are derived from the definitions before the change and the DDL statement, or loaded from the
+    /// server if the statement can't be parsed. A table that no longer exists, e.g. a dropped one,
+    /// is mapped to `None`, as well as a table whose definition can't be derived while the stream
+    /// is catching up.
+    pub(crate) after: BTreeMap<String, Option<MysqlTableDef>>,
 }
 
-#[derive(Clone, PartialEq)]
+/// A position in the binlog of the MySQL server.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Getters,
+    CopyGetters,
+    Serialize,
+    Deserialize,
+)]
+pub struct BinlogPosition {
+    #[getset(get = "pub")]
+    pub(crate) filename: String,
+
+    #[getset(get_copy = "pub")]
+    pub(crate) position: u64,
+}
+
+impl BinlogPosition {
+    pub fn new(filename: impl Into<String>, position: u64) -> Self {
+        Self {
+            filename: filename.into(),
+            position,
+        }
+    }
+}
+
+impl Event {
+    /// Returns the handle to acknowledge that the event has been durably processed, which commits
+    /// the offsets of the stream, see [`AckHandle`]. Every event of the stream must be acknowledged
+    /// for the committed offset to advance, including the events that are ignored by the consumer.
+    /// `None` if the source has no offset store, see [`crate::Source::with_offset_store`].
+    pub fn ack_handle(&self) -> Option<AckHandle> {
+        self.ack.clone()
+    }
+}
+
+impl std::fmt::Display for BinlogPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.filename, self.position)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum EventData {
     DataChange(Vec<RowChange>),
     SchemaChange(SchemaChange),
+    /// The snapshot of the captured tables has started. It carries the binlog position at which
+    /// the snapshot is taken.
+    SnapshotStarted(BinlogPosition),
+    /// The snapshot of the table of the event has started. It's followed by the rows of the table.
+    TableSnapshotStarted,
+    /// All rows of the table of the event have been read from the snapshot. It carries the number
+    /// of rows read.
+    TableSnapshotCompleted(u64),
+    /// All captured tables have been read from the snapshot. It carries the binlog position at
+    /// which the snapshot was taken, from which the binlog is streamed.
+    SnapshotCompleted(BinlogPosition),
 }
 
-#[derive(Clone, PartialEq, Getters, CopyGetters)]
+/// An event of a [`crate::SnapshotStream`] or a [`crate::BinlogStream`]. It can be serialized,
+/// e.g. to be persisted or sent to another process, and deserialized to an equal event, since
+/// the [`Event::ack_handle`] is not compared.
+#[derive(Clone, Getters, CopyGetters, Serialize, Deserialize)]
 pub struct Event {
     #[getset(get_copy = "pub")]
     pub(crate) pos: u32,
 
+    /// The binlog position from which a restarted stream receives this event again, i.e. the end
+    /// of a transaction before it. Committing it to an [`crate::OffsetStore`] after the event is
+    /// processed makes a restarted stream resume after the processed events, though the rest of
+    /// the transaction of the event may be received again.
+    #[getset(get = "pub")]
+    pub(crate) offset: BinlogPosition,
+
+    /// Set on the events of a [`crate::BinlogStream`] whose source has an offset store. It's local
+    /// to the stream, so it's neither serialized nor compared.
+    #[serde(skip)]
+    pub(crate) ack: Option<AckHandle>,
+
     #[getset(get = "pub")]
     pub(crate) database_name: String,
 
@@ -149,4 +754,35 @@ pub struct Event {
 
     #[getset(get = "pub")]
     pub(crate) data: EventData,
+
+    /// Whether an update of the event changes the key of its row, see [`RowChange::key`]. The
+    /// update is then emitted as a [`RowChange::Delete`] of the old key and a
+    /// [`RowChange::Insert`] of the new key, which e.g. a Kafka sink sends to different
+    /// partitions.
+    #[getset(get_copy = "pub")]
+    pub(crate) primary_key_changed: bool,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            pos,
+            offset,
+            ack: _,
+            database_name,
+            schema_name,
+            table_id,
+            table_name,
+            data,
+            primary_key_changed,
+        } = self;
+        *pos == other.pos
+            && *offset == other.offset
+            && *database_name == other.database_name
+            && *schema_name == other.schema_name
+            && *table_id == other.table_id
+            && *table_name == other.table_name
+            && *data == other.data
+            && *primary_key_changed == other.primary_key_changed
+    }
 }
@@ -0,0 +1,634 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use bigdecimal::BigDecimal;
+use chgcap_mysql_ddl_parser::ast::expr::{CompareOperator, Predicate};
+use chgcap_mysql_ddl_parser::ast::value::Value as DdlValue;
+use chgcap_mysql_ddl_parser::parser::Parser;
+use mysql_async::consts::ColumnType;
+
+use crate::event::{Row, RowChange};
+use crate::schema::{MysqlColumnDef, MysqlTableDef};
+use crate::snapshot::quote_identifier;
+use crate::value::{datetime_micros, days_from_civil, Value};
+use crate::SourceConfig;
+
+/// The rows and columns to capture from a table, as configured by [`SourceConfig::row_filters`]
+/// and [`SourceConfig::column_projections`], and the key of its rows, as configured by
+/// [`SourceConfig::message_key_columns`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TableSelection {
+    filter: Option<RowFilter>,
+    columns: Option<Vec<String>>,
+    key_columns: Option<Vec<String>>,
+}
+
+impl TableSelection {
+    /// Returns the selections of the configured tables, keyed by `databaseName.tableName`.
+    pub fn from_config(cfg: &SourceConfig) -> Result<HashMap<String, TableSelection>> {
+        let mut selections = HashMap::<String, TableSelection>::new();
+        for (table, predicate) in cfg.row_filters() {
+            let filter = RowFilter::parse(predicate)
+                .map_err(|e| anyhow!("Invalid row filter of table {table}: {e}"))?;
+            selections.entry(table.clone()).or_default().filter = Some(filter);
+        }
+        for (table, columns) in cfg.column_projections() {
+            if columns.is_empty() {
+                bail!("The column projection of table {table} is empty");
+            }
+            selections.entry(table.clone()).or_default().columns = Some(columns.clone());
+        }
+        for (table, columns) in cfg.message_key_columns() {
+            if columns.is_empty() {
+                bail!("The message key columns of table {table} are empty");
+            }
+            selections.entry(table.clone()).or_default().key_columns = Some(columns.clone());
+        }
+        Ok(selections)
+    }
+
+    /// Returns the query that reads the selected rows and columns in the snapshot, where
+    /// `column_names` are the columns to read if the columns are not projected.
+    pub fn select_sql(
+        &self,
+        database_name: &str,
+        table_name: &str,
+        column_names: &[String],
+    ) -> String {
+        let columns: Vec<String> = self
+            .columns
+            .as_deref()
+            .unwrap_or(column_names)
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect();
+        let mut sql = format!(
+            "SELECT {} FROM {}.{}",
+            columns.join(", "),
+            quote_identifier(database_name),
+            quote_identifier(table_name)
+        );
+        if let Some(filter) = &self.filter {
+            sql.push_str(" WHERE ");
+            sql.push_str(&filter.to_sql());
+        }
+        sql
+    }
+
+    /// Returns `true` if the columns are projected.
+    pub fn has_projection(&self) -> bool {
+        self.columns.is_some()
+    }
+
+    /// Returns the columns of the key of the rows, which are the configured message key columns,
+    /// or `primary_keys` otherwise.
+    pub fn key_columns(&self, primary_keys: &[String]) -> Arc<[String]> {
+        self.key_columns.as_deref().unwrap_or(primary_keys).into()
+    }
+
+    /// Returns the SQL predicate of the row filter, if any.
+    pub fn filter_sql(&self) -> Option<String> {
+        self.filter.as_ref().map(RowFilter::to_sql)
+    }
+
+    /// Projects a row that's selected by [`TableSelection::filter_sql`], whose predicate the
+    /// server evaluates with the collations of the columns.
+    pub fn project(&self, row: Row) -> Result<Row> {
+        let indexes = self.projected_columns(&row)?;
+        if indexes.len() == row.values.len() {
+            return Ok(row);
+        }
+        Ok(row.project(&indexes))
+    }
+
+    /// Applies the selection to the row of a binlog change of a table whose definition is `def`.
+    /// Returns `None` if the row is filtered out, or the change with only the projected columns
+    /// otherwise. The columns of the row are named after `def` if the binlog doesn't contain the
+    /// column names.
+    pub fn apply_change(
+        &self,
+        change: RowChange,
+        def: Option<&MysqlTableDef>,
+    ) -> Result<Option<RowChange>> {
+        let change = match def {
+            Some(def) => change.try_map_row(|row| row.with_column_names(&def.column_names()))?,
+            None => change,
+        };
+        let Some(indexes) = self.select(change.row(), def)? else {
+            return Ok(None);
+        };
+        if indexes.len() == change.row().values.len() {
+            return Ok(Some(change));
+        }
+        change
+            .try_map_row(|row| Ok(row.project(&indexes)))
+            .map(Some)
+    }
+
+    /// Returns the indexes of the projected columns of a row, or `None` if the row is filtered
+    /// out.
+    fn select(&self, row: &Row, def: Option<&MysqlTableDef>) -> Result<Option<Vec<usize>>> {
+        if let Some(filter) = &self.filter {
+            if !filter.matches(row, def)? {
+                return Ok(None);
+            }
+        }
+        self.projected_columns(row).map(Some)
+    }
+
+    fn projected_columns(&self, row: &Row) -> Result<Vec<usize>> {
+        match &self.columns {
+            Some(projection) => projection
+                .iter()
+                .map(|name| column_index(row, name))
+                .collect(),
+            None => Ok((0..row.values.len()).collect()),
+        }
+    }
+}
+
+fn column_index(row: &Row, name: &str) -> Result<usize> {
+    row.column_index(name)
+        .ok_or_else(|| anyhow!("Unknown column {name}"))
+}
+
+/// A predicate on the rows of a table, e.g. `tenant_id = 42 AND created_at > '2024-01-01'`, which is
+/// parsed by [`Parser::parse_predicates`].
+///
+/// Since the predicate is evaluated on the binlog rows as well, only conjunctions of simple
+/// conditions on a single column are supported. The strings can only be compared in the columns of
+/// binary strings, or of text strings with `_bin` collations, whose comparisons are reproduced
+/// exactly:
+/// - `column <op> literal` where `<op>` is one of `=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`.
+/// - `column IS [NOT] NULL`.
+/// - `column [NOT] IN (literal, ...)`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RowFilter {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Compare {
+        column: String,
+        op: CompareOperator,
+        literal: Literal,
+    },
+    IsNull {
+        column: String,
+        negated: bool,
+    },
+    In {
+        column: String,
+        literals: Vec<Literal>,
+        negated: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(String),
+    String(String),
+}
+
+impl RowFilter {
+    pub fn parse(predicate: &str) -> Result<Self> {
+        let conditions = Parser::parse_predicates(predicate)?
+            .into_iter()
+            .map(|predicate| {
+                Ok(match predicate {
+                    Predicate::Compare { column, op, value } => Condition::Compare {
+                        column: column.value,
+                        op,
+                        literal: Literal::from_value(value)?,
+                    },
+                    Predicate::IsNull { column, negated } => Condition::IsNull {
+                        column: column.value,
+                        negated,
+                    },
+                    Predicate::InList {
+                        column,
+                        list,
+                        negated,
+                    } => Condition::In {
+                        column: column.value,
+                        literals: list
+                            .into_iter()
+                            .map(Literal::from_value)
+                            .collect::<Result<_>>()?,
+                        negated,
+                    },
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { conditions })
+    }
+
+    pub fn to_sql(&self) -> String {
+        self.conditions
+            .iter()
+            .map(|c| match c {
+                Condition::Compare {
+                    column,
+                    op,
+                    literal,
+                } => format!("{} {} {}", quote_identifier(column), op, literal.to_sql()),
+                Condition::IsNull { column, negated } => format!(
+                    "{} IS {}NULL",
+                    quote_identifier(column),
+                    if *negated { "NOT " } else { "" }
+                ),
+                Condition::In {
+                    column,
+                    literals,
+                    negated,
+                } => format!(
+                    "{} {}IN ({})",
+                    quote_identifier(column),
+                    if *negated { "NOT " } else { "" },
+                    literals
+                        .iter()
+                        .map(Literal::to_sql)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Evaluates the predicate on a row of a table whose definition is `def` with the SQL
+    /// semantics, i.e. any comparison with NULL is false.
+    pub fn matches(&self, row: &Row, def: Option<&MysqlTableDef>) -> Result<bool> {
+        let collation = |column: &str| {
+            def.and_then(|def| def.column(column))
+                .map(Collation::of)
+                .unwrap_or(Collation::Bytes)
+        };
+        for condition in &self.conditions {
+            let matched = match condition {
+                Condition::Compare {
+                    column,
+                    op,
+                    literal,
+                } => {
+                    let i = column_index(row, column)?;
+                    self.compare(row, i, literal, &collation(column))?
+                        .is_some_and(|ord| accepts(*op, ord))
+                }
+                Condition::IsNull { column, negated } => {
+                    let i = column_index(row, column)?;
+                    (row.values[i] == Value::Null) != *negated
+                }
+                Condition::In {
+                    column,
+                    literals,
+                    negated,
+                } => {
+                    let i = column_index(row, column)?;
+                    let collation = collation(column);
+                    let mut found = false;
+                    for literal in literals {
+                        if self.compare(row, i, literal, &collation)? == Some(Ordering::Equal) {
+                            found = true;
+                            break;
+                        }
+                    }
+                    row.values[i] != Value::Null && found != *negated
+                }
+            };
+            if !matched {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Compares the `i`-th value of the row with the literal. The values are compared as numbers
+    /// in the numeric columns, and as dates and times in the temporal columns, whatever the
+    /// [`crate::TemporalMode`] and the [`crate::DecimalMode`] they're decoded in, and with the
+    /// `collation` of the column in the text columns. Returns `None` if the value is NULL or not
+    /// comparable, or an error if the collation can't be reproduced.
+    fn compare(
+        &self,
+        row: &Row,
+        i: usize,
+        literal: &Literal,
+        collation: &Collation,
+    ) -> Result<Option<Ordering>> {
+        let value = &row.values[i];
+        let column_type = row.columns[i].column_type;
+        let text = match literal {
+            Literal::Number(n) => n,
+            Literal::String(s) => s,
+        };
+        if *value == Value::Null {
+            return Ok(None);
+        }
+        if is_temporal(column_type) {
+            // A TIMESTAMP literal is in UTC, which is the session time zone of the snapshots.
+            let Some(literal) = parse_temporal(text, column_type) else {
+                return Ok(None);
+            };
+            return Ok(temporal_micros(value, column_type).map(|micros| micros.cmp(&literal)));
+        }
+        if column_type.is_numeric_type() || column_type == ColumnType::MYSQL_TYPE_BIT {
+            return Ok(compare_number(value, text));
+        }
+        let string = match value {
+            Value::String(s) | Value::Enum(s) => Some(s.clone()),
+            Value::Set(labels) => Some(labels.join(",")),
+            _ => None,
+        };
+        Ok(match (value, string, literal) {
+            (_, Some(s), Literal::String(l)) => Some(
+                collation
+                    .compare(&s, l)
+                    .map_err(|e| anyhow!("Can't compare column {}: {e}", row.columns[i].name))?,
+            ),
+            (Value::Bytes(bytes), _, Literal::String(l)) => {
+                Some(bytes.as_slice().cmp(l.as_bytes()))
+            }
+            (value, ..) => compare_number(value, text),
+        })
+    }
+}
+
+fn accepts(op: CompareOperator, ord: Ordering) -> bool {
+    match op {
+        CompareOperator::Eq => ord.is_eq(),
+        CompareOperator::NotEq => ord.is_ne(),
+        CompareOperator::Lt => ord.is_lt(),
+        CompareOperator::LtEq => ord.is_le(),
+        CompareOperator::Gt => ord.is_gt(),
+        CompareOperator::GtEq => ord.is_ge(),
+    }
+}
+
+impl Literal {
+    fn from_value(value: DdlValue) -> Result<Self> {
+        Ok(match value {
+            DdlValue::Number(n, _) => Literal::Number(n),
+            DdlValue::SingleQuotedString(s)
+            | DdlValue::DoubleQuotedString(s)
+            | DdlValue::NationalStringLiteral(s) => Literal::String(s),
+            value => bail!("Unsupported literal {value}"),
+        })
+    }
+
+    /// Returns the literal in SQL, which means the same whether the `NO_BACKSLASH_ESCAPES` SQL mode
+    /// is enabled or not. The strings with backslashes are written in hex with the `utf8mb4`
+    /// introducer, which converts to the character set of the column.
+    fn to_sql(&self) -> String {
+        match self {
+            Literal::Number(n) => n.clone(),
+            Literal::String(s) if s.contains('\\') => {
+                let hex: String = s.bytes().map(|b| format!("{b:02X}")).collect();
+                format!("_utf8mb4 X'{hex}'")
+            }
+            Literal::String(s) => format!("'{}'", s.replace('\'', "''")),
+        }
+    }
+}
+
+/// How the strings of a column are compared, from the name of its collation. The strings of the
+/// columns whose definitions are unknown are compared as bytes.
+#[derive(Debug, Clone, PartialEq)]
+enum Collation {
+    Bytes,
+
+    /// A `_bin` collation of a character set whose binary order is the order of the code points,
+    /// which ignores the trailing spaces if it's a `PAD SPACE` one, i.e. not a `_0900_` one.
+    CodePoints {
+        pad_space: bool,
+    },
+
+    /// Any other collation, e.g. the accent-insensitive `utf8mb4_0900_ai_ci`, which compares the
+    /// strings with weights that aren't reproduced.
+    Unsupported(String),
+}
+
+impl Collation {
+    fn of(column_def: &MysqlColumnDef) -> Self {
+        let charset = match column_def.charset.as_deref() {
+            None | Some("binary") => return Self::Bytes,
+            Some(charset) => charset,
+        };
+        // The default collation of `utf8mb4` is `utf8mb4_0900_ai_ci` since MySQL 8.0, while the
+        // default collations of the other character sets are case-insensitive ones, e.g.
+        // `latin1_swedish_ci`.
+        let name = match &column_def.collation {
+            Some(collation) => collation.clone(),
+            None if charset == "utf8mb4" => "utf8mb4_0900_ai_ci".to_string(),
+            None => format!("the default collation of {charset}"),
+        };
+        let code_points = matches!(charset, "ascii" | "latin1" | "utf8" | "utf8mb3" | "utf8mb4");
+        if code_points && name.ends_with("_bin") {
+            Self::CodePoints {
+                pad_space: !name.contains("_0900_"),
+            }
+        } else {
+            Self::Unsupported(name)
+        }
+    }
+
+    fn compare(&self, a: &str, b: &str) -> Result<Ordering> {
+        match self {
+            Self::Bytes => Ok(a.as_bytes().cmp(b.as_bytes())),
+            Self::CodePoints { pad_space: true } => {
+                Ok(a.trim_end_matches(' ').cmp(b.trim_end_matches(' ')))
+            }
+            Self::CodePoints { pad_space: false } => Ok(a.cmp(b)),
+            Self::Unsupported(name) => bail!(
+                "The strings of collation {name} can't be compared in the binlog rows, only the \
+                 binary strings and the `_bin` collations are supported"
+            ),
+        }
+    }
+}
+
+/// Compares a numeric value with a number, e.g. the literal `42` or `'42'`. Returns `None` if
+/// either is not a number.
+fn compare_number(value: &Value, number: &str) -> Option<Ordering> {
+    let value = match value {
+        // The floats are compared as they're displayed, e.g. the FLOAT `5.6` equals `5.6`.
+        Value::Float(f) => {
+            return f
+                .to_string()
+                .parse::<f64>()
+                .ok()?
+                .partial_cmp(&number.parse().ok()?)
+        }
+        Value::Double(d) => return d.partial_cmp(&number.parse().ok()?),
+        Value::Bool(b) => BigDecimal::from(*b as u8),
+        Value::Int(n) => BigDecimal::from(*n),
+        Value::UInt(n) | Value::Bit(n) => BigDecimal::from(*n),
+        Value::Decimal(decimal) => decimal.clone(),
+        Value::String(s) => s.parse().ok()?,
+        _ => return None,
+    };
+    Some(value.cmp(&number.parse().ok()?))
+}
+
+fn is_temporal(column_type: ColumnType) -> bool {
+    matches!(
+        column_type,
+        ColumnType::MYSQL_TYPE_DATE
+            | ColumnType::MYSQL_TYPE_NEWDATE
+            | ColumnType::MYSQL_TYPE_DATETIME
+            | ColumnType::MYSQL_TYPE_DATETIME2
+            | ColumnType::MYSQL_TYPE_TIMESTAMP
+            | ColumnType::MYSQL_TYPE_TIMESTAMP2
+            | ColumnType::MYSQL_TYPE_TIME
+            | ColumnType::MYSQL_TYPE_TIME2
+    )
+}
+
+fn is_time(column_type: ColumnType) -> bool {
+    matches!(
+        column_type,
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2
+    )
+}
+
+/// Returns a temporal value as the microseconds since the epoch in UTC, or the microseconds of a
+/// TIME, in any [`crate::TemporalMode`].
+fn temporal_micros(value: &Value, column_type: ColumnType) -> Option<i64> {
+    Some(match value {
+        Value::Date { year, month, day } => {
+            days_from_civil(*year as i64, *month as i64, *day as i64) * 86_400_000_000
+        }
+        Value::DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            micros,
+        } => datetime_micros(*year, *month, *day, *hour, *minute, *second, *micros),
+        Value::Time {
+            negative,
+            hours,
+            minutes,
+            seconds,
+            micros,
+        } => {
+            let secs = *hours as i64 * 3_600 + *minutes as i64 * 60 + *seconds as i64;
+            let micros = secs * 1_000_000 + *micros as i64;
+            if *negative {
+                -micros
+            } else {
+                micros
+            }
+        }
+        Value::Timestamp(micros) | Value::Int(micros) => *micros,
+        Value::String(s) => parse_temporal(s, column_type)?,
+        _ => return None,
+    })
+}
+
+/// Parses a temporal literal, e.g. `2024-01-02 03:04:05.6`, or a temporal value in the
+/// [`crate::TemporalMode::IsoString`], e.g. `2024-01-02T03:04:05.600000Z`, as the microseconds
+/// since the epoch, or the microseconds of a TIME if the column is a TIME. A date is at midnight.
+fn parse_temporal(s: &str, column_type: ColumnType) -> Option<i64> {
+    if is_time(column_type) {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (hms, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let mut parts = hms.split(':').map(|p| p.parse::<i64>().ok());
+        let hours = parts.next()??;
+        let minutes = parts.next().unwrap_or(Some(0))?;
+        let seconds = parts.next().unwrap_or(Some(0))?;
+        if parts.next().is_some() || fraction.len() > 6 {
+            return None;
+        }
+        let micros = match fraction {
+            "" => 0,
+            fraction => format!("{fraction:0<6}").parse::<i64>().ok()?,
+        };
+        let micros = (hours * 3_600 + minutes * 60 + seconds) * 1_000_000 + micros;
+        return Some(if negative { -micros } else { micros });
+    }
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    let (date, time) = s.split_once([' ', 'T']).unwrap_or((s, "0"));
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    let time = parse_temporal(time, ColumnType::MYSQL_TYPE_TIME)?;
+    Some(days_from_civil(year, month, day) * 86_400_000_000 + time)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::event::RowColumn;
+
+    fn row(values: &[&str]) -> Row {
+        Row {
+            columns: ["a", "b", "c"]
+                .into_iter()
+                .map(|name| RowColumn {
+                    name: name.to_string(),
+                    column_type: ColumnType::MYSQL_TYPE_VARCHAR,
+                })
+                .collect(),
+            values: values
+                .iter()
+                .map(|v| Value::String(v.to_string()))
+                .collect(),
+            key_columns: Arc::from([]),
+            changed_columns: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_row_filter() {
+        let filter =
+            RowFilter::parse("a = 'it''s' AND `b` NOT IN (1, -2.5) AND c IS NOT NULL").unwrap();
+        assert_eq!(
+            filter.to_sql(),
+            "`a` = 'it''s' AND `b` NOT IN (1, -2.5) AND `c` IS NOT NULL"
+        );
+        assert_eq!(
+            RowFilter::parse(r"a = 'a\\b'").unwrap().to_sql(),
+            "`a` = _utf8mb4 X'615C62'"
+        );
+        assert!(RowFilter::parse("a = 1 OR b = 2").is_err());
+        assert!(RowFilter::parse("a = X'00'").is_err());
+    }
+
+    #[test]
+    fn test_match_with_collations() {
+        let def = MysqlTableDef::from_create_table(
+            "db",
+            "CREATE TABLE t (a varchar(10), b varchar(10) COLLATE utf8mb4_bin, \
+             c varchar(10) CHARACTER SET latin1) DEFAULT CHARSET=utf8mb4",
+        )
+        .unwrap();
+        let matches = |predicate: &str, values: &[&str], def: Option<&MysqlTableDef>| {
+            RowFilter::parse(predicate)
+                .unwrap()
+                .matches(&row(values), def)
+        };
+        // `utf8mb4_bin` is case-sensitive with padding.
+        assert!(!matches("b = 'abc'", &["", "ABC", ""], Some(&def)).unwrap());
+        assert!(matches("b = 'abc'", &["", "abc  ", ""], Some(&def)).unwrap());
+        assert!(matches("b IN ('x', 'abc')", &["", "abc", ""], Some(&def)).unwrap());
+        assert!(matches("b < 'b'", &["", "abc", ""], Some(&def)).unwrap());
+        // `utf8mb4_0900_ai_ci` and `latin1_swedish_ci` aren't reproduced, e.g. `'a' = 'á'`.
+        assert!(matches("a = 'abc'", &["ABC", "", ""], Some(&def)).is_err());
+        assert!(matches("c IN ('x', 'abc')", &["", "", "ABC "], Some(&def)).is_err());
+        assert!(!matches("a IS NULL", &["ABC", "", ""], Some(&def)).unwrap());
+        // The strings of an unknown table are compared as bytes.
+        assert!(!matches("a = 'abc'", &["ABC", "", ""], None).unwrap());
+    }
+}
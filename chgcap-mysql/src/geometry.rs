@@ -0,0 +1,316 @@
+use std::fmt::Write;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// A GEOMETRY value, as its spatial reference system id and its WKB (Well-Known Binary), which
+/// can be rendered as WKT (Well-Known Text) or GeoJSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SerializedGeometry")]
+pub struct Geometry {
+    srid: u32,
+    wkb: Vec<u8>,
+}
+
+/// The serialized form of a [`Geometry`], whose WKB is validated when it's deserialized.
+#[derive(Deserialize)]
+struct SerializedGeometry {
+    srid: u32,
+    wkb: Vec<u8>,
+}
+
+impl TryFrom<SerializedGeometry> for Geometry {
+    type Error = anyhow::Error;
+
+    fn try_from(geometry: SerializedGeometry) -> Result<Self> {
+        let geometry = Self {
+            srid: geometry.srid,
+            wkb: geometry.wkb,
+        };
+        geometry.shape()?;
+        Ok(geometry)
+    }
+}
+
+impl Geometry {
+    /// Decodes a GEOMETRY value in the internal format of MySQL, i.e. the little-endian SRID
+    /// followed by the WKB.
+    pub(crate) fn from_mysql(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            bail!("Invalid GEOMETRY value {bytes:?}");
+        }
+        let (srid, wkb) = bytes.split_at(4);
+        let geometry = Self {
+            srid: u32::from_le_bytes(srid.try_into()?),
+            wkb: wkb.to_vec(),
+        };
+        geometry.shape()?;
+        Ok(geometry)
+    }
+
+    /// The spatial reference system id, which is 0 if the geometry has none.
+    pub fn srid(&self) -> u32 {
+        self.srid
+    }
+
+    pub fn wkb(&self) -> &[u8] {
+        &self.wkb
+    }
+
+    /// Renders the geometry as WKT in the format of `ST_AsText`, e.g. `POINT(1 2)`.
+    pub fn to_wkt(&self) -> String {
+        // The WKB is validated when the geometry is decoded.
+        let mut wkt = String::new();
+        write_wkt(&mut wkt, &self.shape().unwrap());
+        wkt
+    }
+
+    /// Renders the geometry as a GeoJSON geometry object, e.g.
+    /// `{"type": "Point", "coordinates": [1.0, 2.0]}`.
+    pub fn to_geojson(&self) -> serde_json::Value {
+        to_geojson(&self.shape().unwrap())
+    }
+
+    fn shape(&self) -> Result<Shape> {
+        let mut reader = WkbReader { buf: &self.wkb };
+        let shape = reader.read_shape()?;
+        if !reader.buf.is_empty() {
+            bail!("Invalid WKB: {} trailing bytes", reader.buf.len());
+        }
+        Ok(shape)
+    }
+}
+
+type Coord = (f64, f64);
+
+enum Shape {
+    Point(Coord),
+    LineString(Vec<Coord>),
+    Polygon(Vec<Vec<Coord>>),
+    MultiPoint(Vec<Coord>),
+    MultiLineString(Vec<Vec<Coord>>),
+    MultiPolygon(Vec<Vec<Vec<Coord>>>),
+    GeometryCollection(Vec<Shape>),
+}
+
+struct WkbReader<'a> {
+    buf: &'a [u8],
+}
+
+impl WkbReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.buf.len() < N {
+            bail!("Invalid WKB: unexpected end");
+        }
+        let (bytes, rest) = self.buf.split_at(N);
+        self.buf = rest;
+        Ok(bytes.try_into()?)
+    }
+
+    /// Reads the byte order of a geometry, which is `true` for little-endian.
+    fn read_byte_order(&mut self) -> Result<bool> {
+        match self.take::<1>()?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(anyhow!("Invalid WKB byte order {b}")),
+        }
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32> {
+        let bytes = self.take()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_coord(&mut self, little_endian: bool) -> Result<Coord> {
+        let mut read_f64 = || -> Result<f64> {
+            let bytes = self.take()?;
+            Ok(if little_endian {
+                f64::from_le_bytes(bytes)
+            } else {
+                f64::from_be_bytes(bytes)
+            })
+        };
+        Ok((read_f64()?, read_f64()?))
+    }
+
+    /// Reads a count of items, each of which takes at least `item_size` bytes.
+    fn read_count(&mut self, little_endian: bool, item_size: usize) -> Result<usize> {
+        let n = self.read_u32(little_endian)? as usize;
+        if n.saturating_mul(item_size) > self.buf.len() {
+            bail!("Invalid WKB: {n} items exceed the remaining bytes");
+        }
+        Ok(n)
+    }
+
+    fn read_coords(&mut self, little_endian: bool) -> Result<Vec<Coord>> {
+        let n = self.read_count(little_endian, 16)?;
+        (0..n).map(|_| self.read_coord(little_endian)).collect()
+    }
+
+    fn read_rings(&mut self, little_endian: bool) -> Result<Vec<Vec<Coord>>> {
+        let n = self.read_count(little_endian, 4)?;
+        (0..n).map(|_| self.read_coords(little_endian)).collect()
+    }
+
+    /// Reads the members of a Multi* geometry, which are geometries of `member_type`.
+    fn read_members<T>(
+        &mut self,
+        little_endian: bool,
+        member_type: u32,
+        mut read: impl FnMut(&mut Self, bool) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let n = self.read_count(little_endian, 5)?;
+        (0..n)
+            .map(|_| {
+                let little_endian = self.read_byte_order()?;
+                let geometry_type = self.read_u32(little_endian)?;
+                if geometry_type != member_type {
+                    bail!(
+                        "Invalid WKB: geometry type {geometry_type} in a collection of type \
+                         {member_type}"
+                    );
+                }
+                read(self, little_endian)
+            })
+            .collect()
+    }
+
+    fn read_shape(&mut self) -> Result<Shape> {
+        let little_endian = self.read_byte_order()?;
+        let shape = match self.read_u32(little_endian)? {
+            1 => Shape::Point(self.read_coord(little_endian)?),
+            2 => Shape::LineString(self.read_coords(little_endian)?),
+            3 => Shape::Polygon(self.read_rings(little_endian)?),
+            4 => Shape::MultiPoint(self.read_members(little_endian, 1, Self::read_coord)?),
+            5 => Shape::MultiLineString(self.read_members(little_endian, 2, Self::read_coords)?),
+            6 => Shape::MultiPolygon(self.read_members(little_endian, 3, Self::read_rings)?),
+            7 => {
+                let n = self.read_count(little_endian, 5)?;
+                Shape::GeometryCollection((0..n).map(|_| self.read_shape()).collect::<Result<_>>()?)
+            }
+            t => bail!("Unsupported WKB geometry type {t}"),
+        };
+        Ok(shape)
+    }
+}
+
+fn write_coord(wkt: &mut String, (x, y): Coord) {
+    let _ = write!(wkt, "{x} {y}");
+}
+
+fn write_coords(wkt: &mut String, coords: &[Coord]) {
+    wkt.push('(');
+    for (i, &coord) in coords.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        write_coord(wkt, coord);
+    }
+    wkt.push(')');
+}
+
+fn write_rings(wkt: &mut String, rings: &[Vec<Coord>]) {
+    wkt.push('(');
+    for (i, ring) in rings.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        write_coords(wkt, ring);
+    }
+    wkt.push(')');
+}
+
+fn write_wkt(wkt: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Point(coord) => {
+            wkt.push_str("POINT(");
+            write_coord(wkt, *coord);
+            wkt.push(')');
+        }
+        Shape::LineString(coords) => {
+            wkt.push_str("LINESTRING");
+            write_coords(wkt, coords);
+        }
+        Shape::Polygon(rings) => {
+            wkt.push_str("POLYGON");
+            write_rings(wkt, rings);
+        }
+        Shape::MultiPoint(coords) => {
+            // Each point is parenthesized, as `ST_AsText` does.
+            wkt.push_str("MULTIPOINT(");
+            for (i, &coord) in coords.iter().enumerate() {
+                if i > 0 {
+                    wkt.push(',');
+                }
+                wkt.push('(');
+                write_coord(wkt, coord);
+                wkt.push(')');
+            }
+            wkt.push(')');
+        }
+        Shape::MultiLineString(lines) => {
+            wkt.push_str("MULTILINESTRING");
+            write_rings(wkt, lines);
+        }
+        Shape::MultiPolygon(polygons) => {
+            wkt.push_str("MULTIPOLYGON(");
+            for (i, rings) in polygons.iter().enumerate() {
+                if i > 0 {
+                    wkt.push(',');
+                }
+                write_rings(wkt, rings);
+            }
+            wkt.push(')');
+        }
+        Shape::GeometryCollection(shapes) if shapes.is_empty() => {
+            wkt.push_str("GEOMETRYCOLLECTION EMPTY");
+        }
+        Shape::GeometryCollection(shapes) => {
+            wkt.push_str("GEOMETRYCOLLECTION(");
+            for (i, shape) in shapes.iter().enumerate() {
+                if i > 0 {
+                    wkt.push(',');
+                }
+                write_wkt(wkt, shape);
+            }
+            wkt.push(')');
+        }
+    }
+}
+
+fn coords_json(coords: &[Coord]) -> serde_json::Value {
+    coords.iter().map(|&(x, y)| json!([x, y])).collect()
+}
+
+fn rings_json(rings: &[Vec<Coord>]) -> serde_json::Value {
+    rings.iter().map(|ring| coords_json(ring)).collect()
+}
+
+fn to_geojson(shape: &Shape) -> serde_json::Value {
+    match shape {
+        Shape::Point((x, y)) => json!({"type": "Point", "coordinates": [x, y]}),
+        Shape::LineString(coords) => {
+            json!({"type": "LineString", "coordinates": coords_json(coords)})
+        }
+        Shape::Polygon(rings) => json!({"type": "Polygon", "coordinates": rings_json(rings)}),
+        Shape::MultiPoint(coords) => {
+            json!({"type": "MultiPoint", "coordinates": coords_json(coords)})
+        }
+        Shape::MultiLineString(lines) => {
+            json!({"type": "MultiLineString", "coordinates": rings_json(lines)})
+        }
+        Shape::MultiPolygon(polygons) => json!({
+            "type": "MultiPolygon",
+            "coordinates": polygons.iter().map(|rings| rings_json(rings)).collect::<Vec<_>>(),
+        }),
+        Shape::GeometryCollection(shapes) => json!({
+            "type": "GeometryCollection",
+            "geometries": shapes.iter().map(to_geojson).collect::<Vec<_>>(),
+        }),
+    }
+}
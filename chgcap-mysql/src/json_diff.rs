@@ -0,0 +1,244 @@
+use anyhow::{anyhow, bail, Result};
+use mysql_async::binlog::jsondiff::{JsonDiff, JsonDiffOperation};
+use serde_json::Value;
+
+use crate::event::to_serde_json;
+
+/// Applies the diffs of a partial JSON update, which the server logs instead of the whole document
+/// if it sets `binlog_row_value_options=PARTIAL_JSON`, to the document before the update.
+pub(crate) fn apply_json_diffs(mut doc: Value, diffs: &[JsonDiff]) -> Result<Value> {
+    for diff in diffs {
+        let path = diff.path_str();
+        let value = diff.value().map(to_serde_json).transpose()?;
+        apply_json_diff(&mut doc, &path, diff.operation(), value)
+            .map_err(|e| anyhow!("Failed to apply the JSON diff at {path}: {e}"))?;
+    }
+    Ok(doc)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathLeg {
+    Key(String),
+    Index(usize),
+}
+
+fn apply_json_diff(
+    doc: &mut Value,
+    path: &str,
+    operation: JsonDiffOperation,
+    value: Option<Value>,
+) -> Result<()> {
+    let mut legs = parse_path(path)?;
+    let Some(last) = legs.pop() else {
+        // Only the whole document can be replaced.
+        match (operation, value) {
+            (JsonDiffOperation::REPLACE, Some(value)) => *doc = value,
+            _ => bail!("Invalid {operation:?} of the whole document"),
+        }
+        return Ok(());
+    };
+    let mut parent = doc;
+    for leg in &legs {
+        parent = match (parent, leg) {
+            (Value::Object(object), PathLeg::Key(key)) => object.get_mut(key),
+            (Value::Array(array), PathLeg::Index(i)) => array.get_mut(*i),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("The path doesn't exist in the document"))?;
+    }
+    // The diffs are logged against the exact document before the update, so a path that doesn't
+    // match the document means that the document is not the one the diffs are logged against.
+    match (operation, parent, last, value) {
+        (JsonDiffOperation::REMOVE, Value::Object(object), PathLeg::Key(key), _) => {
+            object
+                .remove(&key)
+                .ok_or_else(|| anyhow!("The object has no key {key}"))?;
+        }
+        (JsonDiffOperation::REMOVE, Value::Array(array), PathLeg::Index(i), _) => {
+            if i >= array.len() {
+                bail!("The array has no element {i}");
+            }
+            array.remove(i);
+        }
+        (JsonDiffOperation::REPLACE, Value::Object(object), PathLeg::Key(key), Some(value)) => {
+            let member = object
+                .get_mut(&key)
+                .ok_or_else(|| anyhow!("The object has no key {key}"))?;
+            *member = value;
+        }
+        (JsonDiffOperation::REPLACE, Value::Array(array), PathLeg::Index(i), Some(value)) => {
+            let element = array
+                .get_mut(i)
+                .ok_or_else(|| anyhow!("The array has no element {i}"))?;
+            *element = value;
+        }
+        (JsonDiffOperation::INSERT, Value::Object(object), PathLeg::Key(key), Some(value)) => {
+            if object.contains_key(&key) {
+                bail!("The object already has key {key}");
+            }
+            object.insert(key, value);
+        }
+        // An element inserted after the end of an array is appended.
+        (JsonDiffOperation::INSERT, Value::Array(array), PathLeg::Index(i), Some(value)) => {
+            array.insert(i.min(array.len()), value);
+        }
+        (operation, ..) => bail!("Invalid {operation:?}"),
+    }
+    Ok(())
+}
+
+/// Parses a JSON path as normalized by MySQL, e.g. `$.a[1]."b c"`.
+fn parse_path(path: &str) -> Result<Vec<PathLeg>> {
+    let mut chars = path.chars().peekable();
+    if chars.next() != Some('$') {
+        bail!("Invalid JSON path {path}");
+    }
+    let mut legs = vec![];
+    while let Some(c) = chars.next() {
+        match c {
+            '.' if chars.peek() == Some(&'"') => {
+                chars.next();
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => key.extend(chars.next()),
+                        Some(c) => key.push(c),
+                        None => bail!("Invalid JSON path {path}"),
+                    }
+                }
+                legs.push(PathLeg::Key(key));
+            }
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                legs.push(PathLeg::Key(key));
+            }
+            '[' => {
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some(c) => index.push(c),
+                        None => bail!("Invalid JSON path {path}"),
+                    }
+                }
+                let index = index
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid JSON path {path}"))?;
+                legs.push(PathLeg::Index(index));
+            }
+            _ => bail!("Invalid JSON path {path}"),
+        }
+    }
+    Ok(legs)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(parse_path("$").unwrap(), vec![]);
+        assert_eq!(
+            parse_path(r#"$.a[1]."b c".d[ 2 ]"#).unwrap(),
+            vec![
+                PathLeg::Key("a".into()),
+                PathLeg::Index(1),
+                PathLeg::Key("b c".into()),
+                PathLeg::Key("d".into()),
+                PathLeg::Index(2),
+            ]
+        );
+        assert_eq!(
+            parse_path(r#"$."a\"b""#).unwrap(),
+            vec![PathLeg::Key("a\"b".into())]
+        );
+        for path in ["", "a", "$[x]", "$[1", r#"$."a"#, "$a"] {
+            assert!(parse_path(path).is_err(), "{path}");
+        }
+    }
+
+    fn apply(
+        mut doc: Value,
+        path: &str,
+        operation: JsonDiffOperation,
+        value: Option<Value>,
+    ) -> Result<Value> {
+        apply_json_diff(&mut doc, path, operation, value)?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn test_apply_json_diff() {
+        use JsonDiffOperation::*;
+
+        let doc = json!({"a": [1, 2, 3], "b": {"c": "d"}});
+        assert_eq!(
+            apply(doc.clone(), "$.a[1]", REPLACE, Some(json!(5))).unwrap(),
+            json!({"a": [1, 5, 3], "b": {"c": "d"}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.b.c", REPLACE, Some(json!(null))).unwrap(),
+            json!({"a": [1, 2, 3], "b": {"c": null}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.b.e", INSERT, Some(json!([]))).unwrap(),
+            json!({"a": [1, 2, 3], "b": {"c": "d", "e": []}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.a[0]", INSERT, Some(json!(0))).unwrap(),
+            json!({"a": [0, 1, 2, 3], "b": {"c": "d"}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.a[9]", INSERT, Some(json!(4))).unwrap(),
+            json!({"a": [1, 2, 3, 4], "b": {"c": "d"}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.a[2]", REMOVE, None).unwrap(),
+            json!({"a": [1, 2], "b": {"c": "d"}})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$.b", REMOVE, None).unwrap(),
+            json!({"a": [1, 2, 3]})
+        );
+        assert_eq!(
+            apply(doc.clone(), "$", REPLACE, Some(json!(1))).unwrap(),
+            json!(1)
+        );
+    }
+
+    #[test]
+    fn test_apply_json_diff_mismatch() {
+        use JsonDiffOperation::*;
+
+        let doc = json!({"a": [1, 2, 3], "b": {"c": "d"}});
+        let invalid = [
+            ("$.a[3]", REMOVE, None),
+            ("$.e", REMOVE, None),
+            ("$.b.e", REPLACE, Some(json!(1))),
+            ("$.a[3]", REPLACE, Some(json!(1))),
+            ("$.b.c", INSERT, Some(json!(1))),
+            ("$.e.f", REPLACE, Some(json!(1))),
+            ("$.a.b", REPLACE, Some(json!(1))),
+            ("$.a[0]", REPLACE, None),
+            ("$", REMOVE, None),
+        ];
+        for (path, operation, value) in invalid {
+            assert!(
+                apply(doc.clone(), path, operation, value).is_err(),
+                "{operation:?} {path}"
+            );
+        }
+    }
+}
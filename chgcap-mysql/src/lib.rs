@@ -1,4 +1,6 @@
 #[allow(dead_code)]
+mod ack;
+#[allow(dead_code)]
 mod binlog_stream;
 #[allow(dead_code)]
 mod config;
@@ -7,20 +9,53 @@ mod connection;
 #[allow(dead_code)]
 mod event;
 #[allow(dead_code)]
+mod filter;
+#[allow(dead_code)]
+mod geometry;
+#[allow(dead_code)]
+mod json_diff;
+#[allow(dead_code)]
 mod metrics;
 #[allow(dead_code)]
+mod rows;
+#[allow(dead_code)]
 mod schema;
 #[allow(dead_code)]
+mod serialized;
+#[allow(dead_code)]
+mod signal;
+#[allow(dead_code)]
 mod snapshot;
 #[allow(dead_code)]
 mod source;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 #[allow(dead_code)]
 mod state;
+#[allow(dead_code)]
+mod table_map;
+#[allow(dead_code)]
+mod value;
 
 #[macro_use]
 extern crate derive_builder;
 
+pub use ack::AckHandle;
+pub use bigdecimal::BigDecimal;
 pub use binlog_stream::BinlogStream;
-pub use config::{SourceConfig, SourceConfigBuilder};
-pub use event::{Event, EventData};
+pub use config::{
+    BinaryMode, BitMode, DecimalMode, GeneratedColumnMode, SourceConfig, SourceConfigBuilder,
+    TemporalMode, ZeroDateMode,
+};
+pub use event::{BinlogPosition, Event, EventData, Row, RowChange, SchemaChange};
+pub use geometry::Geometry;
+pub use schema::{GeneratedColumn, MysqlColumnDef, MysqlSchema, MysqlTableDef, SchemaMismatch};
+pub use snapshot::SnapshotStream;
 pub use source::Source;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStateStore;
+pub use state::{
+    FileOffsetStore, FileSchemaHistory, OffsetStore, SchemaHistory, SchemaRecord, SnapshotProgress,
+    SnapshotProgressStore,
+};
+pub use value::Value;
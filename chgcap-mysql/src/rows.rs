@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use bitvec::slice::BitSlice;
+use mysql_async::binlog::events::{RowsEventData, TableMapEvent};
+use mysql_async::binlog::row::BinlogRow;
+use mysql_async::binlog::value::BinlogValue;
+use mysql_async::consts::ColumnType;
+use mysql_async::Value as MysqlValue;
+use mysql_common::binlog::misc::{
+    my_packed_time_get_frac_part, my_packed_time_get_int_part, my_time_packed_from_binary,
+};
+use mysql_common::binlog::row::BinlogRowValueOptions;
+use mysql_common::io::ParseBuf;
+
+/// The `(before, after)` row images of a rows event.
+pub(crate) type RowImages = (Option<BinlogRow>, Option<BinlogRow>);
+
+/// Reads the row images of a rows event, like [`RowsEventData::rows`], except that the hours of
+/// the TIME values are preserved. mysql_common keeps the hours in a `u8`, so that e.g.
+/// `838:59:59` would be read as `70:59:59`, hence the TIME values are decoded again from the
+/// images.
+pub(crate) fn read_rows(e: &RowsEventData, tme: &TableMapEvent) -> Result<Vec<RowImages>> {
+    let has_time = (0..tme.columns_count() as usize).any(|i| {
+        matches!(
+            tme.get_column_type(i),
+            Ok(Some(
+                ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2
+            ))
+        )
+    });
+    if !has_time {
+        return Ok(e.rows(tme).collect::<Result<_, _>>()?);
+    }
+
+    let num_columns = e.num_columns();
+    let partial = matches!(e, RowsEventData::PartialUpdateRowsEvent(_));
+    let mut buf = ParseBuf(e.rows_data());
+    let mut rows = vec![];
+    while !buf.is_empty() {
+        let before = match e.columns_before_image() {
+            Some(cols) => Some(read_row(&mut buf, num_columns, cols, false, tme)?),
+            None => None,
+        };
+        let after = match e.columns_after_image() {
+            Some(cols) => Some(read_row(&mut buf, num_columns, cols, partial, tme)?),
+            None => None,
+        };
+        rows.push((before, after));
+    }
+    Ok(rows)
+}
+
+/// Names the columns of a row image, which are named `@<i>` unless the server sets
+/// `binlog_row_metadata=FULL`, after `column_names`, i.e. the columns of the table.
+pub(crate) fn with_column_names(row: BinlogRow, column_names: &[String]) -> Result<BinlogRow> {
+    let columns = row
+        .columns_ref()
+        .iter()
+        .map(|c| match column_name(&c.name_str(), column_names)? {
+            Some(name) => Ok(c.clone().with_name(name.as_bytes())),
+            None => Ok(c.clone()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let values = row.unwrap().into_iter().map(Some).collect();
+    Ok(BinlogRow::new(values, columns.into()))
+}
+
+/// Returns the name in `column_names` of a column named `@<i>` after its position `i` in the
+/// table, or `None` if the column is named otherwise.
+pub(crate) fn column_name<'a>(name: &str, column_names: &'a [String]) -> Result<Option<&'a str>> {
+    let Some(i) = name.strip_prefix('@').and_then(|i| i.parse::<usize>().ok()) else {
+        return Ok(None);
+    };
+    let name = column_names.get(i).ok_or_else(|| {
+        anyhow!(
+            "Column {i} is out of the {} columns of the table",
+            column_names.len()
+        )
+    })?;
+    Ok(Some(name))
+}
+
+/// Reads a row image whose columns are `cols`, and replaces its TIME values by the ones decoded
+/// from the image.
+fn read_row<'a>(
+    buf: &mut ParseBuf<'a>,
+    num_columns: u64,
+    cols: &'a BitSlice<u8>,
+    have_shared_image: bool,
+    tme: &'a TableMapEvent<'a>,
+) -> Result<BinlogRow> {
+    let image = ParseBuf(buf.0);
+    let mut row: BinlogRow = buf.parse((num_columns, cols, have_shared_image, tme))?;
+    for (index, value) in read_times(image, cols, have_shared_image, tme)? {
+        row.place(index, BinlogValue::Value(value));
+    }
+    Ok(row)
+}
+
+/// Decodes the TIME values of a row image, by their indices in the image. The image has been read
+/// as a [`BinlogRow`], so it's known to be well-formed.
+fn read_times<'a>(
+    mut buf: ParseBuf<'a>,
+    cols: &BitSlice<u8>,
+    have_shared_image: bool,
+    tme: &'a TableMapEvent<'a>,
+) -> Result<Vec<(usize, MysqlValue)>> {
+    // The partial JSON columns are only in the images after the partial updates, see `BinlogRow`.
+    let mut partial_cols = vec![];
+    if have_shared_image {
+        let value_options = buf.eat_lenenc_int();
+        if value_options & BinlogRowValueOptions::PARTIAL_JSON_UPDATES as u64 > 0 {
+            let json_columns_count = tme.json_column_count();
+            let bits = buf.eat(json_columns_count.div_ceil(8));
+            partial_cols = BitSlice::<u8>::from_slice(bits)
+                .iter()
+                .by_vals()
+                .take(json_columns_count)
+                .collect();
+        }
+    }
+    let mut partial_cols = partial_cols.into_iter();
+
+    let num_bits = cols.count_ones();
+    let null_bitmap = BitSlice::<u8>::from_slice(buf.eat(num_bits.div_ceil(8)));
+
+    let mut times = vec![];
+    for (image_idx, i) in cols.iter_ones().enumerate() {
+        let column_type = tme
+            .get_column_type(i)?
+            .unwrap_or(ColumnType::MYSQL_TYPE_NULL);
+        let column_meta = tme.get_column_metadata(i).unwrap_or(&[]);
+        let is_partial =
+            column_type == ColumnType::MYSQL_TYPE_JSON && partial_cols.next().unwrap_or(false);
+        if null_bitmap
+            .get(image_idx)
+            .as_deref()
+            .copied()
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        match column_type {
+            ColumnType::MYSQL_TYPE_TIME2 => {
+                let dec = column_meta.first().copied().unwrap_or(0);
+                let packed = my_time_packed_from_binary(&mut buf, dec as u32)?;
+                times.push((image_idx, time_from_packed(packed)));
+            }
+            ColumnType::MYSQL_TYPE_TIME => {
+                // `[-]HHMMSS` as a little-endian signed 3-byte integer.
+                let bytes = buf.eat(3);
+                let hms = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) << 8 >> 8;
+                let (negative, hms) = (hms < 0, hms.unsigned_abs());
+                times.push((
+                    image_idx,
+                    time(negative, hms / 10_000, hms % 10_000 / 100, hms % 100, 0),
+                ));
+            }
+            _ => {
+                buf.parse::<BinlogValue>((column_type, column_meta, false, is_partial))?;
+            }
+        }
+    }
+    Ok(times)
+}
+
+/// Decodes a packed TIME2 value, see `mysql_common::binlog::misc::time_from_packed`, which
+/// truncates the 10-bit hours.
+fn time_from_packed(packed: i64) -> MysqlValue {
+    let negative = packed < 0;
+    let packed = packed.abs();
+    let hms = my_packed_time_get_int_part(packed) as u32;
+    let micros = my_packed_time_get_frac_part(packed) as u32;
+    time(
+        negative,
+        (hms >> 12) % (1 << 10),
+        (hms >> 6) % (1 << 6),
+        hms % (1 << 6),
+        micros,
+    )
+}
+
+/// Builds a TIME value whose hours may exceed 24, which are carried into its days.
+fn time(negative: bool, hours: u32, minutes: u32, seconds: u32, micros: u32) -> MysqlValue {
+    MysqlValue::Time(
+        negative,
+        hours / 24,
+        (hours % 24) as u8,
+        minutes as u8,
+        seconds as u8,
+        micros,
+    )
+}
@@ -1,20 +1,1053 @@
-pub struct MysqlSchema {}
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chgcap_mysql_ddl_parser::ast::data_type::{DataType, ExactNumberInfo};
+use chgcap_mysql_ddl_parser::ast::ddl::{
+    AlterColumnOperation, AlterTableOperation, ColumnOption, ColumnPosition, TableConstraint,
+    TableOption,
+};
+use chgcap_mysql_ddl_parser::ast::value::Value as DdlValue;
+use chgcap_mysql_ddl_parser::ast::{ColumnDef, ObjectName, Statement};
+use chgcap_mysql_ddl_parser::parser::Parser;
+use itertools::Itertools;
+use log::warn;
+use mysql_async::consts::ColumnType;
+use mysql_async::prelude::Queryable;
+use mysql_async::{Conn, Pool};
+use serde::{Deserialize, Serialize};
+
+use crate::config::GeneratedColumnMode;
+use crate::event::BinlogPosition;
+use crate::snapshot::{quote_identifier, read_binlog_position};
+use crate::state::{apply_schema_record, Schema, SchemaRecord};
+
+/// A cache of the table definitions, keyed by `databaseName.tableName`.
+pub struct MysqlSchema {
+    pool: Pool,
+    tables: HashMap<String, MysqlTableDef>,
+}
 
 // We can replace this module with https://github.com/SeaQL/sea-schema.
 
 impl MysqlSchema {
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            tables: HashMap::new(),
+        }
+    }
+
     /// Gets table schema for the given table path. It will request to MySQL server
     /// by running `SHOW CREATE TABLE` if cache missed.
-    pub fn get_table_schema() {
-        todo!()
+    pub async fn get_table_schema(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<&MysqlTableDef> {
+        let table = format!("{database_name}.{table_name}");
+        if !self.tables.contains_key(&table) {
+            let mut conn = self.pool.get_conn().await?;
+            let table_def = load_table_def(&mut conn, database_name, table_name)
+                .await?
+                .ok_or_else(|| anyhow!("Table {table} doesn't exist"))?;
+            self.tables.insert(table.clone(), table_def);
+        }
+        Ok(&self.tables[&table])
+    }
+
+    /// Drops the cached definition of `databaseName.tableName`, which is reloaded on the next
+    /// [`MysqlSchema::get_table_schema`].
+    pub fn invalidate(&mut self, table: &str) {
+        self.tables.remove(table);
+    }
+
+    /// Returns the cached definition of `databaseName.tableName`.
+    pub(crate) fn get(&self, table: &str) -> Option<&MysqlTableDef> {
+        self.tables.get(table)
+    }
+
+    /// Replaces the cached definition of `databaseName.tableName`, which is removed if `table_def`
+    /// is `None`. Returns the previous definition.
+    pub(crate) fn replace(
+        &mut self,
+        table: &str,
+        table_def: Option<MysqlTableDef>,
+    ) -> Option<MysqlTableDef> {
+        match table_def {
+            Some(table_def) => self.tables.insert(table.to_string(), table_def),
+            None => self.tables.remove(table),
+        }
+    }
+
+    /// Creates a cache of the definitions of `tables`.
+    pub(crate) fn with_tables(pool: Pool, tables: Schema) -> Self {
+        Self { pool, tables }
+    }
+
+    /// Returns the cached definitions, keyed by `databaseName.tableName`.
+    pub(crate) fn tables(&self) -> &Schema {
+        &self.tables
+    }
+
+    /// Replaces the cached definitions with the ones of a record of the schema history.
+    pub(crate) fn apply_record(&mut self, record: &SchemaRecord) {
+        apply_schema_record(&mut self.tables, record);
+    }
+
+    /// Derives the definitions of the tables changed by a DDL statement executed in
+    /// `default_database` from their cached definitions, keyed by `databaseName.tableName`. A
+    /// dropped table is mapped to `None`. A table whose definition can't be derived is mapped to an
+    /// error, e.g. a table created by `CREATE TABLE ... SELECT` or altered before it's cached.
+    pub(crate) fn apply_ddl(
+        &self,
+        statement: &Statement,
+        default_database: &str,
+    ) -> BTreeMap<String, Result<Option<MysqlTableDef>>> {
+        let qualify = |name: &ObjectName| qualified_table_name(name, default_database);
+        let mut tables = BTreeMap::new();
+        // The definition of a table before the statement, or after the preceding renames of the
+        // same statement.
+        let get = |tables: &BTreeMap<String, Result<Option<MysqlTableDef>>>, table: &str| {
+            match tables.get(table) {
+                Some(Ok(table_def)) => table_def.clone(),
+                Some(Err(_)) => None,
+                None => self.get(table).cloned(),
+            }
+            .ok_or_else(|| anyhow!("The definition of table {table} is unknown"))
+        };
+        match statement {
+            Statement::CreateTable {
+                temporary: false,
+                name,
+                with_query,
+                ..
+            } => {
+                let table_def = if *with_query {
+                    Err(anyhow!("The table is created from a query"))
+                } else {
+                    MysqlTableDef::from_statement(default_database, statement.clone()).map(Some)
+                };
+                tables.insert(qualify(name), table_def);
+            }
+            Statement::CreateTableLike {
+                temporary: false,
+                name,
+                like,
+                ..
+            } => {
+                let table = qualify(name);
+                let table_def = get(&tables, &qualify(like)).map(|d| Some(d.renamed(&table)));
+                tables.insert(table, table_def);
+            }
+            Statement::DropTable {
+                temporary: false,
+                names,
+                ..
+            } => {
+                for name in names {
+                    tables.insert(qualify(name), Ok(None));
+                }
+            }
+            Statement::RenameTable { renames } => {
+                for (from, to) in renames {
+                    let (from, to) = (qualify(from), qualify(to));
+                    let table_def = get(&tables, &from).map(|d| Some(d.renamed(&to)));
+                    tables.insert(from, Ok(None));
+                    tables.insert(to, table_def);
+                }
+            }
+            Statement::AlterTable { name, operations } => {
+                let from = qualify(name);
+                let mut to = from.clone();
+                let table_def = get(&tables, &from).and_then(|mut table_def| {
+                    for operation in operations {
+                        match operation {
+                            AlterTableOperation::RenameTable { table_name } => {
+                                to = qualify(table_name)
+                            }
+                            operation => table_def.alter(operation)?,
+                        }
+                    }
+                    Ok(Some(table_def.renamed(&to)))
+                });
+                if to != from {
+                    tables.insert(from, Ok(None));
+                }
+                tables.insert(to, table_def);
+            }
+            _ => {}
+        }
+        tables
     }
 }
 
-/// The definition of a table, generated by 'DESC <table>'
+/// The definition of a table, generated by `SHOW CREATE TABLE <table>`, or derived from the DDLs
+/// of the table in the binlog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MysqlTableDef {
+    pub database_name: String,
     pub table_name: String,
     pub columns: Vec<MysqlColumnDef>,
     pub primary_keys: Vec<String>,
+
+    /// The default character set of the table, which is the character set of the string columns
+    /// added without one.
+    #[serde(default)]
+    pub charset: Option<String>,
+
+    /// The default collation of the table, or `None` if it's the default collation of `charset`.
+    #[serde(default)]
+    pub collation: Option<String>,
+}
+
+/// The definition of a column, in the [`MysqlTableDef`] of its table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MysqlColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+
+    /// The default value, which is `None` if the column has no default value or defaults to
+    /// `NULL`. A string literal is unquoted, while an expression such as `CURRENT_TIMESTAMP` is
+    /// kept as written.
+    pub default: Option<String>,
+
+    /// The character set of a string column, which is `binary` for the binary strings, or `None`
+    /// if the column is not a string.
+    pub charset: Option<String>,
+
+    /// The collation of a text string column, or `None` if it's the default collation of
+    /// `charset` or if the column is not a text string.
+    #[serde(default)]
+    pub collation: Option<String>,
+
+    /// The generation of a generated column, or `None` if the column is not generated.
+    pub generated: Option<GeneratedColumn>,
+
+    /// Whether the column is `INVISIBLE`, i.e. omitted by `SELECT *`. It's still in the binlog.
+    pub invisible: bool,
+}
+
+/// The error of a binlog rows event whose columns don't match the schema of its table as of the
+/// event, e.g. if the schema history misses an `ALGORITHM=INSTANT` column change. The values can't
+/// be labelled by their columns, so the stream fails rather than mislabelling them. It can be told
+/// apart from the other errors of the stream by `anyhow::Error::downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    /// The `databaseName.tableName` of the table.
+    pub table: String,
+
+    /// The binlog position of the end of the rows event.
+    pub offset: BinlogPosition,
+
+    /// The number of columns of the rows in the binlog.
+    pub binlog_columns: usize,
+
+    /// The number of columns of the table as of the event.
+    pub schema_columns: usize,
+
+    /// The first column of the table whose type doesn't match the binlog, or `None` if the numbers
+    /// of columns don't match.
+    pub column: Option<String>,
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(
+                f,
+                "The binlog has another type of column {column} of table {} at {}",
+                self.table, self.offset
+            ),
+            None => write!(
+                f,
+                "The binlog has {} columns of table {} at {} but its schema has {} columns",
+                self.binlog_columns, self.table, self.offset, self.schema_columns
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+/// How a generated column is computed, from `[GENERATED ALWAYS] AS (expr) [VIRTUAL | STORED]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratedColumn {
+    pub expr: String,
+
+    /// Whether the values are stored, rather than computed when they're read.
+    pub stored: bool,
+}
+
+impl MysqlColumnDef {
+    /// Builds the definition of a column of a table whose default character set and collation are
+    /// `table_charset` and `table_collation`, and whose primary key is `primary_keys`.
+    fn from_column_def(
+        column: ColumnDef,
+        table_charset: Option<&str>,
+        table_collation: Option<&str>,
+        primary_keys: &[String],
+    ) -> Self {
+        let name = column.name.value;
+        // The columns of the primary key are implicitly `NOT NULL`.
+        let mut nullable = !primary_keys.contains(&name);
+        let mut default = None;
+        let mut charset = None;
+        let mut collation = None;
+        let mut generated = None;
+        let mut invisible = false;
+        for option in column.options {
+            match option {
+                ColumnOption::NotNull | ColumnOption::PrimaryKey => nullable = false,
+                ColumnOption::Default(value) => default = default_value(value),
+                ColumnOption::CharacterSet(name) => charset = Some(name.value),
+                ColumnOption::Collate(name) => collation = Some(name.value),
+                ColumnOption::Generated { expr, stored } => {
+                    generated = Some(GeneratedColumn { expr, stored })
+                }
+                ColumnOption::Invisible => invisible = true,
+                _ => {}
+            }
+        }
+        // A column without a character set nor a collation has the ones of its table.
+        if charset.is_none() && collation.is_none() {
+            collation = table_collation.map(str::to_string);
+        }
+        // A collation is named after its character set, e.g. `utf8mb4_bin`.
+        let charset = charset.or_else(|| Some(collation.as_ref()?.split('_').next()?.to_string()));
+        let (charset, collation) = match column_charset_kind(&column.data_type) {
+            Some(CharsetKind::Text) => (
+                charset
+                    .or_else(|| table_charset.map(str::to_string))
+                    .map(|c| c.to_lowercase()),
+                collation.map(|c| c.to_lowercase()),
+            ),
+            Some(CharsetKind::Binary) => (Some("binary".to_string()), None),
+            None => (None, None),
+        };
+        Self {
+            name,
+            data_type: column.data_type,
+            nullable,
+            default,
+            charset,
+            collation,
+            generated,
+            invisible,
+        }
+    }
+
+    /// Returns the declared precision of a DECIMAL column, which defaults to 10, or `None` if the
+    /// column is not a DECIMAL.
+    pub fn decimal_precision(&self) -> Option<u64> {
+        match &self.data_type {
+            DataType::Decimal(info) | DataType::Numeric(info) | DataType::Dec(info) => match info {
+                ExactNumberInfo::PrecisionAndScale(precision, _)
+                | ExactNumberInfo::Precision(precision) => Some(*precision),
+                ExactNumberInfo::None => Some(10),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the declared scale of a DECIMAL column, or `None` if the column is not a DECIMAL.
+    pub fn decimal_scale(&self) -> Option<u64> {
+        match &self.data_type {
+            DataType::Decimal(info) | DataType::Numeric(info) | DataType::Dec(info) => match info {
+                ExactNumberInfo::PrecisionAndScale(_, scale) => Some(*scale),
+                ExactNumberInfo::Precision(_) | ExactNumberInfo::None => Some(0),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the column is a virtual generated column.
+    pub fn is_virtual(&self) -> bool {
+        self.generated.as_ref().is_some_and(|g| !g.stored)
+    }
+
+    /// Returns the number of bits of a BIT column, or `None` if the column is not a BIT.
+    pub fn bit_width(&self) -> Option<u64> {
+        match &self.data_type {
+            DataType::Custom(name, modifiers) if name.to_string().eq_ignore_ascii_case("bit") => {
+                Some(modifiers.first().and_then(|m| m.parse().ok()).unwrap_or(1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if a column of this definition is logged as `column_type` in the table map
+    /// events, which is the real type of the column, e.g. `MYSQL_TYPE_ENUM` for an ENUM. A type
+    /// that's not known is assumed to match.
+    pub(crate) fn matches_binlog_type(&self, column_type: ColumnType) -> bool {
+        use ColumnType::*;
+        let types: &[ColumnType] = match &self.data_type {
+            DataType::Character(_) | DataType::Char(_) | DataType::Binary(_) => {
+                &[MYSQL_TYPE_STRING]
+            }
+            DataType::CharacterVarying(_)
+            | DataType::CharVarying(_)
+            | DataType::Varchar(_)
+            | DataType::Nvarchar(_)
+            | DataType::Varbinary(_) => &[MYSQL_TYPE_VARCHAR],
+            DataType::Text | DataType::Blob(_) | DataType::Clob(_) => &[MYSQL_TYPE_BLOB],
+            DataType::Decimal(_) | DataType::Numeric(_) | DataType::Dec(_) => {
+                &[MYSQL_TYPE_NEWDECIMAL]
+            }
+            DataType::TinyInt(_)
+            | DataType::UnsignedTinyInt(_)
+            | DataType::Bool
+            | DataType::Boolean => &[MYSQL_TYPE_TINY],
+            DataType::SmallInt(_) | DataType::UnsignedSmallInt(_) => &[MYSQL_TYPE_SHORT],
+            DataType::MediumInt(_) | DataType::UnsignedMediumInt(_) => &[MYSQL_TYPE_INT24],
+            DataType::Int(_)
+            | DataType::Integer(_)
+            | DataType::UnsignedInt(_)
+            | DataType::UnsignedInteger(_) => &[MYSQL_TYPE_LONG],
+            DataType::BigInt(_) | DataType::UnsignedBigInt(_) => &[MYSQL_TYPE_LONGLONG],
+            // `FLOAT(p)` is a DOUBLE if `p` is greater than 24, and `REAL` is a DOUBLE unless
+            // `REAL_AS_FLOAT` is set.
+            DataType::Float(_) | DataType::Real => &[MYSQL_TYPE_FLOAT, MYSQL_TYPE_DOUBLE],
+            DataType::Float4 => &[MYSQL_TYPE_FLOAT],
+            DataType::Double | DataType::DoublePrecision | DataType::Float8 => &[MYSQL_TYPE_DOUBLE],
+            DataType::Date => &[MYSQL_TYPE_DATE, MYSQL_TYPE_NEWDATE],
+            DataType::Time(..) => &[MYSQL_TYPE_TIME, MYSQL_TYPE_TIME2],
+            DataType::Datetime(_) => &[MYSQL_TYPE_DATETIME, MYSQL_TYPE_DATETIME2],
+            DataType::Timestamp(..) => &[MYSQL_TYPE_TIMESTAMP, MYSQL_TYPE_TIMESTAMP2],
+            DataType::JSON => &[MYSQL_TYPE_JSON],
+            DataType::Enum(_) => &[MYSQL_TYPE_ENUM],
+            DataType::Set(_) => &[MYSQL_TYPE_SET],
+            DataType::Custom(name, _) => {
+                let name = name.to_string().to_lowercase();
+                match name.as_str() {
+                    "bit" => &[MYSQL_TYPE_BIT],
+                    "year" => &[MYSQL_TYPE_YEAR],
+                    "geometry" | "point" | "linestring" | "polygon" | "multipoint"
+                    | "multilinestring" | "multipolygon" | "geometrycollection"
+                    | "geomcollection" => &[MYSQL_TYPE_GEOMETRY],
+                    _ if name.ends_with("text") || name.ends_with("blob") => &[MYSQL_TYPE_BLOB],
+                    _ => return true,
+                }
+            }
+            _ => return true,
+        };
+        types.contains(&column_type)
+    }
 }
 
-pub struct MysqlColumnDef {}
+impl MysqlTableDef {
+    /// Builds the definition from the `CREATE TABLE` statement of the table, whose name is
+    /// qualified by `database_name` unless the statement qualifies it.
+    pub fn from_create_table(database_name: &str, sql: &str) -> Result<Self> {
+        let statement = Parser::parse_sql(sql)
+            .with_context(|| format!("Failed to parse the table definition {sql}"))?
+            .ok_or_else(|| anyhow!("Failed to parse the table definition {sql}"))?;
+        Self::from_statement(database_name, statement)
+            .with_context(|| format!("Invalid table definition {sql}"))
+    }
+
+    /// Builds the definition from a parsed `CREATE TABLE` statement.
+    fn from_statement(database_name: &str, statement: Statement) -> Result<Self> {
+        let Statement::CreateTable {
+            name,
+            columns,
+            constraints,
+            table_options,
+            ..
+        } = statement
+        else {
+            bail!("Not a CREATE TABLE statement");
+        };
+        let (database_name, table_name) = match &name.0[..] {
+            [table_name] => (database_name.to_string(), table_name.value.clone()),
+            [database_name, table_name] => (database_name.value.clone(), table_name.value.clone()),
+            _ => bail!("Invalid table name {name}"),
+        };
+
+        let mut primary_keys: Vec<String> = constraints
+            .iter()
+            .find_map(|c| match c {
+                TableConstraint::PrimaryKeys { columns } => {
+                    Some(columns.iter().map(|c| c.value.clone()).collect())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        if primary_keys.is_empty() {
+            primary_keys = columns
+                .iter()
+                .filter(|c| c.options.contains(&ColumnOption::PrimaryKey))
+                .map(|c| c.name.value.clone())
+                .collect();
+        }
+
+        let (charset, collation) = table_charset(&table_options, None, None);
+        let columns = columns
+            .into_iter()
+            .map(|column| {
+                MysqlColumnDef::from_column_def(
+                    column,
+                    charset.as_deref(),
+                    collation.as_deref(),
+                    &primary_keys,
+                )
+            })
+            .collect();
+        Ok(Self {
+            database_name,
+            table_name,
+            columns,
+            primary_keys,
+            charset,
+            collation,
+        })
+    }
+
+    /// Returns the definition of the table renamed to `databaseName.tableName`.
+    fn renamed(mut self, table: &str) -> Self {
+        if let Some((database_name, table_name)) = table.split_once('.') {
+            self.database_name = database_name.to_string();
+            self.table_name = table_name.to_string();
+        }
+        self
+    }
+
+    /// Applies an operation of `ALTER TABLE`, except for renaming the table.
+    fn alter(&mut self, operation: &AlterTableOperation) -> Result<()> {
+        match operation {
+            AlterTableOperation::AddColumn {
+                column_def,
+                position,
+            } => {
+                let column = self.column_def(column_def);
+                self.insert_column(column, position.as_ref(), None)?;
+            }
+            AlterTableOperation::AddConstraint(TableConstraint::PrimaryKeys { columns }) => {
+                self.primary_keys = columns.iter().map(|c| c.value.clone()).collect();
+                for column in &mut self.columns {
+                    if self.primary_keys.contains(&column.name) {
+                        column.nullable = false;
+                    }
+                }
+            }
+            AlterTableOperation::AddConstraint(_) => {}
+            AlterTableOperation::DropColumn { column_name } => {
+                let i = self.column_index(&column_name.value)?;
+                self.columns.remove(i);
+                self.primary_keys.retain(|k| k != &column_name.value);
+            }
+            AlterTableOperation::DropPrimaryKey => self.primary_keys.clear(),
+            AlterTableOperation::ModifyColumn {
+                column_def,
+                position,
+            } => {
+                let i = self.column_index(&column_def.name.value)?;
+                let column = self.column_def(column_def);
+                self.insert_column(column, position.as_ref(), Some(i))?;
+            }
+            AlterTableOperation::ChangeColumn {
+                old_name,
+                column_def,
+                position,
+            } => {
+                let i = self.column_index(&old_name.value)?;
+                self.rename_primary_key(&old_name.value, &column_def.name.value);
+                let column = self.column_def(column_def);
+                self.insert_column(column, position.as_ref(), Some(i))?;
+            }
+            AlterTableOperation::RenameColumn { old_name, new_name } => {
+                let i = self.column_index(&old_name.value)?;
+                self.columns[i].name = new_name.value.clone();
+                self.rename_primary_key(&old_name.value, &new_name.value);
+            }
+            AlterTableOperation::AlterColumn { column_name, op } => {
+                let i = self.column_index(&column_name.value)?;
+                let column = &mut self.columns[i];
+                match op {
+                    AlterColumnOperation::SetDefault(value) => {
+                        column.default = default_value(value.clone())
+                    }
+                    AlterColumnOperation::DropDefault => column.default = None,
+                    AlterColumnOperation::SetVisible => column.invisible = false,
+                    AlterColumnOperation::SetInvisible => column.invisible = true,
+                }
+            }
+            AlterTableOperation::ConvertToCharacterSet { charset, collation } => {
+                let charset = charset.value.to_lowercase();
+                let collation = collation.as_ref().map(|c| c.value.to_lowercase());
+                for column in &mut self.columns {
+                    if column.charset.as_deref().is_some_and(|c| c != "binary") {
+                        column.charset = Some(charset.clone());
+                        column.collation = collation.clone();
+                    }
+                }
+                self.charset = Some(charset);
+                self.collation = collation;
+            }
+            AlterTableOperation::TableOption(option) => {
+                (self.charset, self.collation) = table_charset(
+                    std::slice::from_ref(option),
+                    self.charset.take(),
+                    self.collation.take(),
+                );
+            }
+            AlterTableOperation::RenameTable { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Builds the definition of a column added or redefined by `ALTER TABLE`.
+    fn column_def(&mut self, column: &ColumnDef) -> MysqlColumnDef {
+        if column.options.contains(&ColumnOption::PrimaryKey) {
+            self.primary_keys = vec![column.name.value.clone()];
+        }
+        MysqlColumnDef::from_column_def(
+            column.clone(),
+            self.charset.as_deref(),
+            self.collation.as_deref(),
+            &self.primary_keys,
+        )
+    }
+
+    fn column_index(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| anyhow!("Column {name} doesn't exist"))
+    }
+
+    /// Inserts a column at `position`, or replaces the column at `replaced` if no position is
+    /// given. The column is appended if neither is given.
+    fn insert_column(
+        &mut self,
+        column: MysqlColumnDef,
+        position: Option<&ColumnPosition>,
+        replaced: Option<usize>,
+    ) -> Result<()> {
+        if let Some(i) = replaced {
+            if position.is_none() {
+                self.columns[i] = column;
+                return Ok(());
+            }
+            self.columns.remove(i);
+        }
+        let i = match position {
+            Some(ColumnPosition::First) => 0,
+            Some(ColumnPosition::After(name)) => self.column_index(&name.value)? + 1,
+            None => self.columns.len(),
+        };
+        self.columns.insert(i, column);
+        Ok(())
+    }
+
+    fn rename_primary_key(&mut self, old_name: &str, new_name: &str) {
+        for key in &mut self.primary_keys {
+            if key == old_name {
+                *key = new_name.to_string();
+            }
+        }
+    }
+
+    pub fn column(&self, name: &str) -> Option<&MysqlColumnDef> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Returns the names of the columns that are not virtual generated columns, which are the
+    /// columns of a binlog row image without the virtual columns.
+    pub fn stored_column_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|c| !c.is_virtual())
+            .map(|c| c.name.clone())
+            .collect()
+    }
+}
+
+enum CharsetKind {
+    Text,
+    Binary,
+}
+
+/// Returns whether a column of `data_type` is a text string, a binary string or not a string.
+fn column_charset_kind(data_type: &DataType) -> Option<CharsetKind> {
+    match data_type {
+        DataType::Character(_)
+        | DataType::Char(_)
+        | DataType::CharacterVarying(_)
+        | DataType::CharVarying(_)
+        | DataType::Varchar(_)
+        | DataType::Nvarchar(_)
+        | DataType::Text
+        | DataType::String(_)
+        | DataType::Clob(_)
+        | DataType::Enum(_)
+        | DataType::Set(_) => Some(CharsetKind::Text),
+        DataType::Binary(_) | DataType::Varbinary(_) | DataType::Blob(_) => {
+            Some(CharsetKind::Binary)
+        }
+        // TINYTEXT, MEDIUMTEXT, LONGTEXT and the BLOBs of other sizes are parsed as custom types.
+        DataType::Custom(name, _) => {
+            let name = name.to_string().to_lowercase();
+            if name.ends_with("text") {
+                Some(CharsetKind::Text)
+            } else if name.ends_with("blob") {
+                Some(CharsetKind::Binary)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the default character set and collation of a table, which are `charset` and
+/// `collation` before the table options. A character set resets the collation to its default one,
+/// while a collation implies its character set, which prefixes its name.
+fn table_charset(
+    options: &[TableOption],
+    mut charset: Option<String>,
+    mut collation: Option<String>,
+) -> (Option<String>, Option<String>) {
+    for option in options {
+        match option {
+            TableOption::CharacterSet(name) => {
+                charset = Some(name.value.to_lowercase());
+                collation = None;
+            }
+            TableOption::Collate(name) => {
+                let name = name.value.to_lowercase();
+                charset = name.split('_').next().map(Into::into);
+                collation = Some(name);
+            }
+        }
+    }
+    (charset, collation)
+}
+
+fn default_value(value: DdlValue) -> Option<String> {
+    match value {
+        DdlValue::Null => None,
+        DdlValue::Number(n, _) => Some(n),
+        DdlValue::SingleQuotedString(s)
+        | DdlValue::DoubleQuotedString(s)
+        | DdlValue::NationalStringLiteral(s)
+        | DdlValue::UnQuotedString(s) => Some(s),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Reads the definition of a table by `SHOW CREATE TABLE`. Returns `None` if the table doesn't
+/// exist.
+pub(crate) async fn load_table_def(
+    conn: &mut Conn,
+    database_name: &str,
+    table_name: &str,
+) -> Result<Option<MysqlTableDef>> {
+    let sql = format!(
+        "SHOW CREATE TABLE {}.{}",
+        quote_identifier(database_name),
+        quote_identifier(table_name)
+    );
+    let row: Option<(String, String)> = match conn.query_first(sql).await {
+        Ok(row) => row,
+        Err(mysql_async::Error::Server(e)) if e.code == ER_NO_SUCH_TABLE => None,
+        Err(e) => return Err(e.into()),
+    };
+    row.map(|(_, create_table)| MysqlTableDef::from_create_table(database_name, &create_table))
+        .transpose()
+}
+
+/// The error code of a missing table.
+const ER_NO_SUCH_TABLE: u16 = 1146;
+
+/// Reads the definitions of the tables, keyed by `databaseName.tableName`. A table is mapped to
+/// `None` if it doesn't exist.
+pub(crate) async fn load_table_defs(
+    pool: Pool,
+    tables: Vec<String>,
+) -> Result<HashMap<String, Option<MysqlTableDef>>> {
+    let mut conn = pool.get_conn().await?;
+    let mut table_defs = HashMap::new();
+    for table in tables {
+        let (database_name, table_name) = table
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Invalid table identifier {table}"))?;
+        let table_def = load_table_def(&mut conn, database_name, table_name).await?;
+        table_defs.insert(table, table_def);
+    }
+    Ok(table_defs)
+}
+
+/// Completes the definitions derived from a DDL statement ending at `offset`, see
+/// [`MysqlSchema::apply_ddl`]. The definitions that can't be derived are loaded from the server
+/// only if the stream is caught up, i.e. no statement follows the DDL in the binlog, as the later
+/// statements may have changed them otherwise. They're unknown and mapped to `None` if not.
+pub(crate) async fn load_underived_table_defs(
+    pool: Pool,
+    derived: BTreeMap<String, Result<Option<MysqlTableDef>>>,
+    offset: BinlogPosition,
+) -> Result<BTreeMap<String, Option<MysqlTableDef>>> {
+    let mut table_defs = BTreeMap::new();
+    let mut underived = vec![];
+    for (table, table_def) in derived {
+        match table_def {
+            Ok(table_def) => {
+                table_defs.insert(table, table_def);
+            }
+            Err(e) => underived.push((table, e)),
+        }
+    }
+    if underived.is_empty() {
+        return Ok(table_defs);
+    }
+    let mut conn = pool.get_conn().await?;
+    let caught_up = read_binlog_position(&mut conn).await? <= offset;
+    for (table, e) in underived {
+        let table_def = if caught_up {
+            let (database_name, table_name) = table
+                .split_once('.')
+                .ok_or_else(|| anyhow!("Invalid table identifier {table}"))?;
+            load_table_def(&mut conn, database_name, table_name).await?
+        } else {
+            warn!(
+                "The definition of table {table} after the DDL at {offset} is unknown, so its rows \
+                 are decoded with the metadata in the binlog: {e}"
+            );
+            None
+        };
+        table_defs.insert(table, table_def);
+    }
+    Ok(table_defs)
+}
+
+/// Reads the names of the columns of a table to read in a snapshot, in their ordinal positions.
+/// Unlike `SELECT *`, they include the invisible columns, so that the snapshot rows have the same
+/// columns as the binlog rows, while the virtual generated columns are excluded if `mode` omits
+/// them.
+pub(crate) async fn load_selected_column_names(
+    conn: &mut Conn,
+    database_name: &str,
+    table_name: &str,
+    mode: GeneratedColumnMode,
+) -> Result<Vec<String>> {
+    let columns: Vec<(String, String)> = conn
+        .exec(
+            "SELECT COLUMN_NAME, EXTRA FROM information_schema.COLUMNS \
+             WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+            (database_name, table_name),
+        )
+        .await?;
+    Ok(columns
+        .into_iter()
+        .filter(|(_, extra)| {
+            mode == GeneratedColumnMode::AsLogged || !extra.contains("VIRTUAL GENERATED")
+        })
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Reads the names of the primary key columns of a table, in the order of the key.
+pub(crate) async fn load_primary_keys(
+    conn: &mut Conn,
+    database_name: &str,
+    table_name: &str,
+) -> Result<Vec<String>> {
+    let names = conn
+        .exec(
+            "SELECT COLUMN_NAME FROM information_schema.KEY_COLUMN_USAGE \
+             WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY' \
+             ORDER BY ORDINAL_POSITION",
+            (database_name, table_name),
+        )
+        .await?;
+    Ok(names)
+}
+
+/// Formats the name of a table in a statement executed in `default_database` as
+/// `databaseName.tableName`.
+pub(crate) fn qualified_table_name(name: &ObjectName, default_database: &str) -> String {
+    match name.0.as_slice() {
+        [database_name, table_name] => format!("{}.{}", database_name.value, table_name.value),
+        [table_name] => format!("{default_database}.{}", table_name.value),
+        _ => name.0.iter().map(|i| i.value.as_str()).join("."),
+    }
+}
+
+/// The tables changed by a DDL statement executed in `default_database`, as `(from, to)` pairs
+/// of `databaseName.tableName`. A created table has no `from`, a dropped table has no `to`, and
+/// an altered table has the same `from` and `to`. Temporary tables are ignored.
+pub(crate) fn table_changes(
+    statement: &Statement,
+    default_database: &str,
+) -> Vec<(Option<String>, Option<String>)> {
+    let qualify = |name: &ObjectName| qualified_table_name(name, default_database);
+    match statement {
+        Statement::CreateTable {
+            temporary: false,
+            name,
+            ..
+        }
+        | Statement::CreateTableLike {
+            temporary: false,
+            name,
+            ..
+        } => vec![(None, Some(qualify(name)))],
+        Statement::DropTable {
+            temporary: false,
+            names,
+            ..
+        } => names.iter().map(|n| (Some(qualify(n)), None)).collect(),
+        Statement::RenameTable { renames } => renames
+            .iter()
+            .map(|(from, to)| (Some(qualify(from)), Some(qualify(to))))
+            .collect(),
+        Statement::AlterTable { name, operations } => {
+            let to = operations
+                .iter()
+                .rev()
+                .find_map(|op| match op {
+                    AlterTableOperation::RenameTable { table_name } => Some(table_name),
+                    _ => None,
+                })
+                .unwrap_or(name);
+            vec![(Some(qualify(name)), Some(qualify(to)))]
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(tables: &[&str]) -> MysqlSchema {
+        let mut schema = MysqlSchema::new(Pool::new("mysql://localhost"));
+        for sql in tables {
+            let table_def = MysqlTableDef::from_create_table("db", sql).unwrap();
+            let table = format!("db.{}", table_def.table_name);
+            schema.replace(&table, Some(table_def));
+        }
+        schema
+    }
+
+    fn apply_ddl(schema: &MysqlSchema, sql: &str) -> BTreeMap<String, Option<MysqlTableDef>> {
+        let statement = Parser::parse_sql(sql).unwrap().unwrap();
+        schema
+            .apply_ddl(&statement, "db")
+            .into_iter()
+            .map(|(table, table_def)| (table, table_def.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_binlog_type() {
+        use ColumnType::*;
+        let table_def = MysqlTableDef::from_create_table(
+            "db",
+            "CREATE TABLE t (a varchar(10), b char(2), c mediumtext, d decimal(10,2), \
+             e enum('x','y'), f bit(3), g float, h datetime(3), i tinyint(1), j point)",
+        )
+        .unwrap();
+        let types = [
+            MYSQL_TYPE_VARCHAR,
+            MYSQL_TYPE_STRING,
+            MYSQL_TYPE_BLOB,
+            MYSQL_TYPE_NEWDECIMAL,
+            MYSQL_TYPE_ENUM,
+            MYSQL_TYPE_BIT,
+            MYSQL_TYPE_FLOAT,
+            MYSQL_TYPE_DATETIME2,
+            MYSQL_TYPE_TINY,
+            MYSQL_TYPE_GEOMETRY,
+        ];
+        for (column, column_type) in table_def.columns.iter().zip(types) {
+            assert!(column.matches_binlog_type(column_type), "{}", column.name);
+            assert!(
+                !column.matches_binlog_type(MYSQL_TYPE_LONGLONG),
+                "{}",
+                column.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_alter_table() {
+        let schema = schema(&[
+            "CREATE TABLE `t` (`id` int NOT NULL, `a` varchar(10), `b` int DEFAULT '1', \
+             PRIMARY KEY (`id`)) DEFAULT CHARSET=latin1",
+        ]);
+        let tables = apply_ddl(
+            &schema,
+            "ALTER TABLE t ADD c TEXT FIRST, DROP COLUMN b, CHANGE a d VARCHAR(20) \
+             CHARACTER SET utf8mb4 NOT NULL AFTER id, ALTER c SET DEFAULT 'x', \
+             ADD INDEX idx (d), RENAME COLUMN id TO e",
+        );
+        let table_def = tables["db.t"].as_ref().unwrap();
+        assert_eq!(table_def.column_names(), vec!["c", "e", "d"]);
+        assert_eq!(table_def.primary_keys, vec!["e"]);
+        assert_eq!(table_def.columns[0].charset.as_deref(), Some("latin1"));
+        assert_eq!(table_def.columns[0].default.as_deref(), Some("x"));
+        assert_eq!(table_def.columns[2].charset.as_deref(), Some("utf8mb4"));
+        assert!(!table_def.columns[2].nullable);
+
+        let tables = apply_ddl(&schema, "ALTER TABLE t CONVERT TO CHARACTER SET utf8mb4");
+        let table_def = tables["db.t"].as_ref().unwrap();
+        assert_eq!(table_def.charset.as_deref(), Some("utf8mb4"));
+        assert_eq!(table_def.columns[1].charset.as_deref(), Some("utf8mb4"));
+
+        let statement = Parser::parse_sql("ALTER TABLE t DROP COLUMN x")
+            .unwrap()
+            .unwrap();
+        assert!(schema.apply_ddl(&statement, "db")["db.t"].is_err());
+    }
+
+    #[test]
+    fn test_apply_table_ddls() {
+        let schema = schema(&["CREATE TABLE `t` (`id` int)"]);
+        let t = schema.get("db.t").unwrap().clone();
+
+        let tables = apply_ddl(&schema, "RENAME TABLE t TO u, u TO other.v");
+        assert_eq!(
+            tables,
+            BTreeMap::from([
+                ("db.t".to_string(), None),
+                ("db.u".to_string(), None),
+                ("other.v".to_string(), Some(t.clone().renamed("other.v"))),
+            ])
+        );
+        let tables = apply_ddl(&schema, "ALTER TABLE t RENAME TO u");
+        assert_eq!(
+            tables,
+            BTreeMap::from([
+                ("db.t".to_string(), None),
+                ("db.u".to_string(), Some(t.clone().renamed("db.u"))),
+            ])
+        );
+        let tables = apply_ddl(&schema, "CREATE TABLE u LIKE t");
+        assert_eq!(
+            tables,
+            BTreeMap::from([("db.u".to_string(), Some(t.clone().renamed("db.u")))])
+        );
+        let tables = apply_ddl(&schema, "DROP TABLE t, u");
+        assert_eq!(
+            tables,
+            BTreeMap::from([("db.t".to_string(), None), ("db.u".to_string(), None)])
+        );
+        let tables = apply_ddl(&schema, "CREATE TABLE u (`id` int PRIMARY KEY)");
+        assert_eq!(tables["db.u"].as_ref().unwrap().primary_keys, vec!["id"]);
+
+        for sql in [
+            "CREATE TABLE u AS SELECT * FROM t",
+            "CREATE TABLE u LIKE x",
+            "RENAME TABLE x TO u",
+        ] {
+            let statement = Parser::parse_sql(sql).unwrap().unwrap();
+            assert!(schema.apply_ddl(&statement, "db")["db.u"].is_err(), "{sql}");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! The serialized form of the types of the columns of a [`crate::Row`], which are serialized by
+//! their names, e.g. `"VARCHAR"`, rather than by their codes in the MySQL protocol.
+
+use mysql_async::consts::ColumnType;
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+/// Returns the name of a column type, as it's displayed and serialized.
+pub(crate) fn column_type_name(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::MYSQL_TYPE_DECIMAL => "DECIMAL",
+        ColumnType::MYSQL_TYPE_TINY => "TINYINT",
+        ColumnType::MYSQL_TYPE_SHORT => "SMALLINT",
+        ColumnType::MYSQL_TYPE_LONG => "INT",
+        ColumnType::MYSQL_TYPE_FLOAT => "FLOAT",
+        ColumnType::MYSQL_TYPE_DOUBLE => "DOUBLE",
+        ColumnType::MYSQL_TYPE_NULL => "NULL",
+        ColumnType::MYSQL_TYPE_TIMESTAMP => "TIMESTAMP",
+        ColumnType::MYSQL_TYPE_LONGLONG => "BIGINT",
+        ColumnType::MYSQL_TYPE_INT24 => "MEDIUMINT",
+        ColumnType::MYSQL_TYPE_DATE => "DATE",
+        ColumnType::MYSQL_TYPE_TIME => "TIME",
+        ColumnType::MYSQL_TYPE_DATETIME => "DATETIME",
+        ColumnType::MYSQL_TYPE_YEAR => "YEAR",
+        ColumnType::MYSQL_TYPE_NEWDATE => "NEWDATE", // Internal to MySql
+        ColumnType::MYSQL_TYPE_VARCHAR => "VARCHAR",
+        ColumnType::MYSQL_TYPE_BIT => "BIT",
+        ColumnType::MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP2",
+        ColumnType::MYSQL_TYPE_DATETIME2 => "DATETIME2",
+        ColumnType::MYSQL_TYPE_TIME2 => "TIME2",
+        ColumnType::MYSQL_TYPE_TYPED_ARRAY => "TYPED ARRAY", // Used for replication only
+        ColumnType::MYSQL_TYPE_UNKNOWN => "UNKNOWN",
+        ColumnType::MYSQL_TYPE_JSON => "JSON",
+        ColumnType::MYSQL_TYPE_NEWDECIMAL => "NEWDECIMAL",
+        ColumnType::MYSQL_TYPE_ENUM => "ENUM",
+        ColumnType::MYSQL_TYPE_SET => "SET",
+        ColumnType::MYSQL_TYPE_TINY_BLOB => "TINYBLOB",
+        ColumnType::MYSQL_TYPE_MEDIUM_BLOB => "MEDIUMBLOB",
+        ColumnType::MYSQL_TYPE_LONG_BLOB => "LONGBLOB",
+        ColumnType::MYSQL_TYPE_BLOB => "BLOB",
+        ColumnType::MYSQL_TYPE_VAR_STRING => "VAR_STRING",
+        ColumnType::MYSQL_TYPE_STRING => "STRING",
+        ColumnType::MYSQL_TYPE_GEOMETRY => "GEOMETRY",
+    }
+}
+
+/// Returns the column type of a name returned by [`column_type_name`].
+pub(crate) fn column_type_from_name(name: &str) -> Option<ColumnType> {
+    (0..=u8::MAX)
+        .filter_map(|code| ColumnType::try_from(code).ok())
+        .find(|&column_type| column_type_name(column_type) == name)
+}
+
+/// Serializes a column type by its name, see `#[serde(with)]`.
+pub(crate) fn serialize<S: Serializer>(
+    column_type: &ColumnType,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(column_type_name(*column_type))
+}
+
+/// Deserializes a column type from its name, see `#[serde(with)]`.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<ColumnType, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    column_type_from_name(&name)
+        .ok_or_else(|| de::Error::custom(format!("Unknown column type {name}")))
+}
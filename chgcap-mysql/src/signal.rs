@@ -0,0 +1,101 @@
+use anyhow::{anyhow, bail, Result};
+use mysql_async::binlog::row::BinlogRow;
+use mysql_async::binlog::value::BinlogValue;
+use mysql_async::Value;
+use serde_json::Value as JsonValue;
+
+/// A command inserted into the signal table, see [`crate::SourceConfig::signal_table`].
+///
+/// Like Debezium, the signal table has three columns:
+/// ```sql
+/// CREATE TABLE chgcap_signal (
+///     id VARCHAR(64) PRIMARY KEY,
+///     type VARCHAR(32) NOT NULL,
+///     data VARCHAR(2048) NULL
+/// );
+/// ```
+/// where `type` is the kind of the signal and `data` is its JSON payload.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Signal {
+    /// `execute-snapshot` with `{"data-collections": ["db.table", ...]}`. Starts an incremental
+    /// snapshot of the tables.
+    ExecuteSnapshot { tables: Vec<String> },
+
+    /// `stop-snapshot` with an optional `{"data-collections": [...]}`. Stops the incremental
+    /// snapshot of the tables, or of all tables if none is given.
+    StopSnapshot { tables: Vec<String> },
+
+    /// `pause-snapshot`. Stops reading chunks until the snapshot is resumed.
+    PauseSnapshot,
+
+    /// `resume-snapshot`.
+    ResumeSnapshot,
+
+    /// `snapshot-window-open`, the low watermark written before reading a chunk.
+    OpenWindow { chunk_id: String },
+
+    /// `snapshot-window-close`, the high watermark written after reading a chunk.
+    CloseWindow { chunk_id: String },
+}
+
+pub(crate) const OPEN_WINDOW: &str = "snapshot-window-open";
+pub(crate) const CLOSE_WINDOW: &str = "snapshot-window-close";
+
+impl Signal {
+    /// Parses a row inserted into the signal table.
+    pub fn from_row(row: &BinlogRow) -> Result<Self> {
+        if row.len() < 3 {
+            bail!("The signal table must have the columns (id, type, data)");
+        }
+        let ty = column_str(row, 1)?.ok_or_else(|| anyhow!("The signal type is NULL"))?;
+        let data = column_str(row, 2)?;
+        let signal = match ty.as_str() {
+            "execute-snapshot" => Signal::ExecuteSnapshot {
+                tables: data_collections(data.as_deref())?,
+            },
+            "stop-snapshot" => Signal::StopSnapshot {
+                tables: data_collections(data.as_deref())?,
+            },
+            "pause-snapshot" | "pause" => Signal::PauseSnapshot,
+            "resume-snapshot" | "resume" => Signal::ResumeSnapshot,
+            OPEN_WINDOW | CLOSE_WINDOW => {
+                let chunk_id = data.ok_or_else(|| anyhow!("Missing the chunk id of {ty}"))?;
+                if ty == OPEN_WINDOW {
+                    Signal::OpenWindow { chunk_id }
+                } else {
+                    Signal::CloseWindow { chunk_id }
+                }
+            }
+            _ => bail!("Unknown signal type {ty}"),
+        };
+        Ok(signal)
+    }
+}
+
+fn column_str(row: &BinlogRow, i: usize) -> Result<Option<String>> {
+    match row.as_ref(i) {
+        None | Some(BinlogValue::Value(Value::NULL)) => Ok(None),
+        Some(BinlogValue::Value(Value::Bytes(b))) => Ok(Some(String::from_utf8(b.clone())?)),
+        Some(v) => bail!("Unexpected value {v:?} in column {i} of the signal table"),
+    }
+}
+
+/// Parses the `data-collections` of the signal data. Returns an empty list if it's absent.
+fn data_collections(data: Option<&str>) -> Result<Vec<String>> {
+    let Some(data) = data.filter(|d| !d.trim().is_empty()) else {
+        return Ok(vec![]);
+    };
+    let data: JsonValue = serde_json::from_str(data)?;
+    match data.get("data-collections") {
+        None | Some(JsonValue::Null) => Ok(vec![]),
+        Some(JsonValue::Array(tables)) => tables
+            .iter()
+            .map(|t| {
+                t.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Invalid table {t} in data-collections"))
+            })
+            .collect(),
+        Some(v) => bail!("Invalid data-collections {v}"),
+    }
+}
@@ -1 +1,1090 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use anyhow::{anyhow, bail, Context, Result};
+use futures::channel::mpsc::{self, Sender};
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream};
+use futures::{future, FutureExt, SinkExt, StreamExt};
+use log::{info, warn};
+use mysql_async::binlog::row::BinlogRow;
+use mysql_async::binlog::value::BinlogValue;
+use mysql_async::prelude::{Query, Queryable};
+use mysql_async::{Conn, Pool, Row, Value};
+
+use crate::config::{GeneratedColumnMode, TableFilter};
+use crate::event::{
+    without_noop_updates, BinlogPosition, Event, EventData, Row as ChangeRow, RowChange,
+};
+use crate::filter::TableSelection;
+use crate::schema::{load_primary_keys, load_selected_column_names, MysqlSchema};
+use crate::signal::{Signal, CLOSE_WINDOW, OPEN_WINDOW};
+use crate::source::Source;
+use crate::state::{SharedSnapshotProgressStore, SnapshotProgress};
+use crate::value::{Value as ColumnValue, ValueDecoder};
+use crate::SourceConfig;
+
+/// A stream that reads a consistent snapshot of every captured table. It starts with an
+/// [`EventData::SnapshotStarted`] event and ends with an [`EventData::SnapshotCompleted`] event,
+/// and the rows of each table are delimited by [`EventData::TableSnapshotStarted`] and
+/// [`EventData::TableSnapshotCompleted`]. Unlike [`crate::BinlogStream`], it does not tail the
+/// binlog afterwards. The snapshot briefly holds a global read lock while it starts, which requires
+/// the RELOAD privilege.
+pub struct SnapshotStream {
+    inner: BoxStream<'static, Result<Event>>,
+    position: BinlogPosition,
+}
+
+impl SnapshotStream {
+    pub async fn new(source: &Source) -> Result<Self> {
+        let reader = SnapshotReader::new(source).await?;
+        let position = reader.position.clone();
+        Ok(Self {
+            inner: reader_stream(|tx| reader.read(tx)),
+            position,
+        })
+    }
+
+    /// The binlog position at which the snapshot was taken. Changes after this position are not
+    /// included in the snapshot.
+    pub fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+}
+
+impl futures_core::stream::Stream for SnapshotStream {
+    type Item = Result<Event>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_next_unpin(cx)
+    }
+}
+
+/// Reads the captured tables within a single `REPEATABLE READ` transaction.
+struct SnapshotReader {
+    conn: Conn,
+    cfg: SourceConfig,
+    position: BinlogPosition,
+
+    /// The `(database_name, table_name)` of the tables to read.
+    tables: Vec<(String, String)>,
+
+    /// The rows and columns to read of the tables, keyed by `databaseName.tableName`.
+    selections: HashMap<String, TableSelection>,
+
+    decoder: ValueDecoder,
+}
+
+impl SnapshotReader {
+    async fn new(source: &Source) -> Result<Self> {
+        let cfg = source.cfg.clone();
+        let filter = TableFilter::new(&cfg)?;
+        let selections = TableSelection::from_config(&cfg)?;
+        let decoder = ValueDecoder::new(&cfg)?;
+        let mut conn = source.pool.get_conn().await?;
+
+        // The global read lock ensures that no transaction commits between the start of the
+        // snapshot transaction and the read of the binlog position. It requires the RELOAD
+        // privilege, without which the snapshot can't be consistent with its binlog position.
+        "FLUSH TABLES WITH READ LOCK"
+            .ignore(&mut conn)
+            .await
+            .context("Failed to acquire the global read lock for the snapshot")?;
+        "SET SESSION TRANSACTION ISOLATION LEVEL REPEATABLE READ"
+            .ignore(&mut conn)
+            .await?;
+        "START TRANSACTION WITH CONSISTENT SNAPSHOT"
+            .ignore(&mut conn)
+            .await?;
+        let position = read_binlog_position(&mut conn).await?;
+        "UNLOCK TABLES".ignore(&mut conn).await?;
+        let tables = list_tables(&mut conn, &filter).await?;
+        info!(
+            "Started snapshot of {} tables at binlog position {}",
+            tables.len(),
+            position
+        );
+
+        Ok(Self {
+            conn,
+            cfg,
+            position,
+            tables,
+            selections,
+            decoder,
+        })
+    }
+
+    async fn read(mut self, mut tx: Sender<Result<Event>>) {
+        if let Err(e) = self.read_tables(&mut tx).await {
+            // The consumer may have dropped the stream, in which case there is no one to notify.
+            let _ = tx.send(Err(e)).await;
+        }
+    }
+
+    async fn read_tables(&mut self, tx: &mut Sender<Result<Event>>) -> Result<()> {
+        let batch_size = self.cfg.split_size().max(1) as usize;
+        let data = EventData::SnapshotStarted(self.position.clone());
+        tx.send(Ok(new_event(&self.position, "", "", data))).await?;
+        for (database_name, table_name) in std::mem::take(&mut self.tables) {
+            info!("Reading snapshot of table {database_name}.{table_name}");
+            let selection = self
+                .selections
+                .get(&format!("{database_name}.{table_name}"))
+                .cloned()
+                .unwrap_or_default();
+            read_table(
+                &mut self.conn,
+                &database_name,
+                &table_name,
+                &selection,
+                self.cfg.generated_column_mode(),
+                &self.decoder,
+                batch_size,
+                &self.position,
+                tx,
+            )
+            .await?;
+        }
+        "COMMIT".ignore(&mut self.conn).await?;
+
+        info!("Completed snapshot at binlog position {}", self.position);
+        let data = EventData::SnapshotCompleted(self.position.clone());
+        tx.send(Ok(new_event(&self.position, "", "", data))).await?;
+        Ok(())
+    }
+}
+
+/// Runs `read` as part of the returned stream, so that it only makes progress while the consumer
+/// polls the stream. The bounded channel provides the backpressure.
+fn reader_stream<F>(
+    read: impl FnOnce(Sender<Result<Event>>) -> F,
+) -> BoxStream<'static, Result<Event>>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(1);
+    let reader = read(tx).into_stream().filter_map(|_| future::ready(None));
+    stream::select(rx, reader).boxed()
+}
+
+/// Reads the selected rows of a table within the current transaction of `conn`, in events of at
+/// most `batch_size` rows between the table snapshot markers.
+#[allow(clippy::too_many_arguments)]
+async fn read_table(
+    conn: &mut Conn,
+    database_name: &str,
+    table_name: &str,
+    selection: &TableSelection,
+    generated_column_mode: GeneratedColumnMode,
+    decoder: &ValueDecoder,
+    batch_size: usize,
+    position: &BinlogPosition,
+    tx: &mut Sender<Result<Event>>,
+) -> Result<()> {
+    let event = |data| Ok(new_event(position, database_name, table_name, data));
+    tx.send(event(EventData::TableSnapshotStarted)).await?;
+
+    let column_names =
+        load_selected_column_names(conn, database_name, table_name, generated_column_mode).await?;
+    if column_names.is_empty() {
+        bail!("Table {database_name}.{table_name} doesn't exist");
+    }
+    let query = selection.select_sql(database_name, table_name, &column_names);
+    let key_columns =
+        selection.key_columns(&load_primary_keys(conn, database_name, table_name).await?);
+    // The binary protocol is used so that the values are typed as they are in the binlog.
+    let mut result = conn.exec_iter(query, ()).await?;
+    let mut changes = vec![];
+    let mut count = 0;
+    // A row is only known not to be the last one once the next row is read.
+    let mut prev = None;
+    loop {
+        let row = result.next().await?;
+        if let Some(prev) = prev.take() {
+            changes.push(RowChange::Read {
+                row: ChangeRow::new(to_binlog_row(prev), None, decoder)?
+                    .with_key_columns(key_columns.clone()),
+                last: row.is_none(),
+            });
+            count += 1;
+        }
+        if changes.len() >= batch_size || (row.is_none() && !changes.is_empty()) {
+            let data = EventData::DataChange(std::mem::take(&mut changes));
+            tx.send(event(data)).await?;
+        }
+        match row {
+            Some(row) => prev = Some(row),
+            None => break,
+        }
+    }
+    drop(result);
+
+    tx.send(event(EventData::TableSnapshotCompleted(count)))
+        .await?;
+    Ok(())
+}
+
+fn new_event(
+    position: &BinlogPosition,
+    database_name: &str,
+    table_name: &str,
+    data: EventData,
+) -> Event {
+    Event {
+        pos: position.position() as u32,
+        offset: position.clone(),
+        ack: None,
+        database_name: database_name.to_string(),
+        schema_name: Default::default(),
+        // Table ids are only assigned by the binlog.
+        table_id: 0,
+        table_name: table_name.to_string(),
+        data,
+        primary_key_changed: false,
+    }
+}
+
+/// Converts a row read with the binary protocol to the same representation as the binlog rows.
+fn to_binlog_row(row: Row) -> BinlogRow {
+    let columns = row.columns();
+    let values = row
+        .unwrap()
+        .into_iter()
+        .map(|v| Some(BinlogValue::Value(v)))
+        .collect();
+    BinlogRow::new(values, columns)
+}
+
+/// Reads the current binlog position of the server.
+pub(crate) async fn read_binlog_position(conn: &mut Conn) -> Result<BinlogPosition> {
+    // `SHOW MASTER STATUS` is removed since MySQL 8.4.
+    let query = if conn.server_version() >= (8, 4, 0) {
+        "SHOW BINARY LOG STATUS"
+    } else {
+        "SHOW MASTER STATUS"
+    };
+    let row: Row = query
+        .first(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow!("The binlog is not enabled on the MySQL server"))?;
+    let filename: String = row
+        .get(0)
+        .ok_or_else(|| anyhow!("Missing the binlog filename in `{query}`"))?;
+    let position: u64 = row
+        .get(1)
+        .ok_or_else(|| anyhow!("Missing the binlog position in `{query}`"))?;
+    Ok(BinlogPosition::new(filename, position))
+}
+
+/// Lists the `(database_name, table_name)` of the base tables that are accepted by the filter.
+pub(crate) async fn list_tables(
+    conn: &mut Conn,
+    filter: &TableFilter,
+) -> Result<Vec<(String, String)>> {
+    let tables: Vec<(String, String)> =
+        "SELECT TABLE_SCHEMA, TABLE_NAME FROM information_schema.TABLES \
+        WHERE TABLE_TYPE = 'BASE TABLE' ORDER BY TABLE_SCHEMA, TABLE_NAME"
+            .fetch(&mut *conn)
+            .await?;
+    Ok(tables
+        .into_iter()
+        .filter(|(database_name, table_name)| filter.matches(database_name, table_name))
+        .collect())
+}
+
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("`{}`", identifier.replace('`', "``"))
+}
+
+/// Reads the snapshot of tables on demand while the binlog is being streamed, as requested by the
+/// signals of [`SourceConfig::signal_table`].
+///
+/// It follows the watermark-based algorithm of DBLog (<https://arxiv.org/pdf/2010.12597v1.pdf>):
+/// each chunk of a table is read by primary key order between a low and a high watermark that are
+/// written into the signal table. The binlog events of the table that are received between the
+/// watermarks are newer than the chunk, so the chunk rows with the same keys are dropped, and the
+/// rest of the chunk is emitted at the high watermark.
+pub(crate) struct IncrementalSnapshot {
+    pool: Pool,
+    signal_table: String,
+    chunk_size: usize,
+    selections: HashMap<String, TableSelection>,
+    generated_column_mode: GeneratedColumnMode,
+    decoder: ValueDecoder,
+
+    /// The `databaseName.tableName` of the tables to snapshot after the current one.
+    queue: VecDeque<String>,
+    paused: bool,
+
+    /// The table being read.
+    current: Option<TableProgress>,
+    state: ChunkState,
+
+    /// The events of the completed chunks that are not emitted yet.
+    ready: VecDeque<Event>,
+
+    /// Distinguishes the chunks of this stream from the ones of other streams that share the
+    /// signal table.
+    chunk_id_prefix: String,
+    next_chunk_id: u64,
+
+    progress_store: Option<SharedSnapshotProgressStore>,
+
+    /// The progress of the queued tables whose snapshots were started before a restart.
+    resumed: HashMap<String, SnapshotProgress>,
+}
+
+/// The progress of reading the snapshot of a table.
+struct TableProgress {
+    database_name: String,
+    table_name: String,
+    column_names: Vec<String>,
+    key_columns: Vec<String>,
+
+    /// The indexes of the primary key columns in `column_names`, i.e. in the chunk rows.
+    key_indexes: Vec<usize>,
+
+    /// The primary key of the last row read, `None` if no chunk has been read.
+    last_key: Option<Vec<Value>>,
+
+    /// The number of rows emitted.
+    rows: u64,
+}
+
+enum ChunkState {
+    Idle,
+    LoadingTable(BoxFuture<'static, Result<TableProgress>>),
+    Reading(ChunkWindow),
+}
+
+struct ChunkWindow {
+    chunk_id: String,
+    read: Option<BoxFuture<'static, Result<Vec<Row>>>>,
+    rows: Option<Vec<Row>>,
+    open: bool,
+    closed: bool,
+
+    /// The binlog position of the high watermark.
+    close_pos: u32,
+
+    /// The primary keys of the rows changed in the binlog within the window, which are compared
+    /// with the decoded keys of the chunk rows.
+    changed_keys: Vec<Vec<ColumnValue>>,
+}
+
+impl IncrementalSnapshot {
+    pub fn new(
+        pool: Pool,
+        cfg: &SourceConfig,
+        signal_table: String,
+        progress_store: Option<SharedSnapshotProgressStore>,
+    ) -> Result<Self> {
+        let resumed = match &progress_store {
+            Some(store) => store.lock().map_err(|e| anyhow!("{e}"))?.load()?,
+            None => BTreeMap::new(),
+        };
+        if !resumed.is_empty() {
+            info!(
+                "Resuming incremental snapshot of tables {:?}",
+                resumed.keys().collect::<Vec<_>>()
+            );
+        }
+        Ok(Self {
+            pool,
+            signal_table,
+            chunk_size: cfg.split_size().max(1) as usize,
+            selections: TableSelection::from_config(cfg)?,
+            generated_column_mode: cfg.generated_column_mode(),
+            decoder: ValueDecoder::new(cfg)?,
+            queue: resumed.keys().cloned().collect(),
+            paused: false,
+            current: None,
+            state: ChunkState::Idle,
+            ready: VecDeque::new(),
+            chunk_id_prefix: format!(
+                "chgcap-{}",
+                SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+            ),
+            next_chunk_id: 0,
+            progress_store,
+            resumed: resumed.into_iter().collect(),
+        })
+    }
+
+    /// Returns `true` if `table` is the signal table, whose changes are signals.
+    pub fn is_signal_table(&self, table: &str) -> bool {
+        self.signal_table == table
+    }
+
+    /// Handles a signal received at `pos`, whose transaction starts at `offset`.
+    pub fn handle_signal(
+        &mut self,
+        signal: Signal,
+        pos: u32,
+        offset: &BinlogPosition,
+    ) -> Result<()> {
+        match signal {
+            Signal::ExecuteSnapshot { tables } => {
+                info!("Incremental snapshot requested for tables {tables:?}");
+                for table in tables {
+                    if !self.queue.contains(&table) {
+                        self.queue.push_back(table);
+                    }
+                }
+            }
+            Signal::StopSnapshot { tables } => {
+                info!("Incremental snapshot stopped for tables {tables:?}");
+                let stops_current = self
+                    .current
+                    .as_ref()
+                    .is_some_and(|t| tables.is_empty() || tables.contains(&t.identifier()));
+                if tables.is_empty() {
+                    self.queue.clear();
+                } else {
+                    self.queue.retain(|t| !tables.contains(t));
+                }
+                let mut stopped: Vec<String> = self
+                    .resumed
+                    .keys()
+                    .filter(|t| tables.is_empty() || tables.contains(t))
+                    .cloned()
+                    .collect();
+                self.resumed.retain(|t, _| !stopped.contains(t));
+                if stops_current {
+                    stopped.extend(self.current.take().map(|t| t.identifier()));
+                    self.state = ChunkState::Idle;
+                }
+                for table in stopped {
+                    self.save_progress(offset, &table, None)?;
+                }
+            }
+            Signal::PauseSnapshot => {
+                info!("Incremental snapshot paused");
+                self.paused = true;
+            }
+            Signal::ResumeSnapshot => {
+                info!("Incremental snapshot resumed");
+                self.paused = false;
+            }
+            Signal::OpenWindow { chunk_id } => {
+                if let ChunkState::Reading(window) = &mut self.state {
+                    if window.chunk_id == chunk_id {
+                        window.open = true;
+                    }
+                }
+            }
+            Signal::CloseWindow { chunk_id } => {
+                if let ChunkState::Reading(window) = &mut self.state {
+                    if window.chunk_id == chunk_id {
+                        window.closed = true;
+                        window.close_pos = pos;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the rows of `table` changed in the binlog, which must be the full rows named after
+    /// the columns of the table.
+    pub fn observe_changes(&mut self, table: &str, changes: &[RowChange]) -> Result<()> {
+        let (Some(current), ChunkState::Reading(window)) = (&self.current, &mut self.state) else {
+            return Ok(());
+        };
+        if !window.open || window.closed || current.identifier() != table {
+            return Ok(());
+        }
+        for change in changes {
+            let key = current.key(change.row()).ok_or_else(|| {
+                anyhow!("The primary key of table {table} is unknown in its binlog rows")
+            })?;
+            window.changed_keys.push(key);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the high watermark of the chunk has been received but the chunk is not
+    /// read yet. The binlog events after the watermark must wait for the chunk to be emitted.
+    pub fn is_blocked(&self) -> bool {
+        matches!(&self.state, ChunkState::Reading(w) if w.closed && w.rows.is_none())
+    }
+
+    /// Makes progress on the snapshot. Returns the next chunk event if one is ready, which is
+    /// emitted at `offset`.
+    pub fn poll_event(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        offset: &BinlogPosition,
+    ) -> Option<Result<Event>> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Some(Ok(event));
+            }
+            match &mut self.state {
+                ChunkState::Idle => {
+                    if self.paused {
+                        return None;
+                    }
+                    match &self.current {
+                        Some(current) => {
+                            let chunk_id =
+                                format!("{}-{}", self.chunk_id_prefix, self.next_chunk_id);
+                            let window = self.start_chunk(current, chunk_id);
+                            self.next_chunk_id += 1;
+                            self.state = ChunkState::Reading(window);
+                        }
+                        None => {
+                            let table = self.queue.pop_front()?;
+                            let future =
+                                load_table(self.pool.clone(), table, self.generated_column_mode)
+                                    .boxed();
+                            self.state = ChunkState::LoadingTable(future);
+                        }
+                    }
+                }
+                ChunkState::LoadingTable(future) => {
+                    let result = match future.poll_unpin(cx) {
+                        Poll::Ready(result) => result,
+                        Poll::Pending => return None,
+                    };
+                    self.state = ChunkState::Idle;
+                    let mut table = match result {
+                        Ok(table) => table,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let identifier = table.identifier();
+                    if let Some(progress) = self.resumed.remove(&identifier) {
+                        info!(
+                            "Resumed incremental snapshot of table {identifier} after {} rows",
+                            progress.rows
+                        );
+                        table.last_key = progress.last_key;
+                        table.rows = progress.rows;
+                    }
+                    let progress = table.progress();
+                    self.current = Some(table);
+                    if let Err(e) = self.save_progress(offset, &identifier, Some(progress)) {
+                        return Some(Err(e));
+                    }
+                }
+                ChunkState::Reading(window) => {
+                    if let Some(read) = &mut window.read {
+                        match read.poll_unpin(cx) {
+                            Poll::Ready(Ok(rows)) => {
+                                window.rows = Some(rows);
+                                window.read = None;
+                            }
+                            Poll::Ready(Err(e)) => {
+                                warn!("Failed to read the chunk of the incremental snapshot: {e}");
+                                self.current = None;
+                                self.state = ChunkState::Idle;
+                                return Some(Err(e));
+                            }
+                            Poll::Pending => return None,
+                        }
+                    }
+                    if !window.closed {
+                        return None;
+                    }
+                    let ChunkState::Reading(window) =
+                        std::mem::replace(&mut self.state, ChunkState::Idle)
+                    else {
+                        unreachable!()
+                    };
+                    if let Err(e) = self.complete_chunk(window, offset) {
+                        self.current = None;
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_chunk(&self, table: &TableProgress, chunk_id: String) -> ChunkWindow {
+        let key_columns = table
+            .key_columns
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut conditions = vec![];
+        if let Some(filter) = self
+            .selections
+            .get(&table.identifier())
+            .and_then(TableSelection::filter_sql)
+        {
+            conditions.push(format!("({filter})"));
+        }
+        let mut params = vec![];
+        if let Some(last_key) = &table.last_key {
+            let placeholders = vec!["?"; last_key.len()].join(", ");
+            conditions.push(format!("({key_columns}) > ({placeholders})"));
+            params = last_key.clone();
+        }
+        let columns = table
+            .column_names
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut query = format!(
+            "SELECT {columns} FROM {}.{}",
+            quote_identifier(&table.database_name),
+            quote_identifier(&table.table_name)
+        );
+        if !conditions.is_empty() {
+            query = format!("{query} WHERE {}", conditions.join(" AND "));
+        }
+        // One more row is read to tell whether the chunk is the last one of the table. It's read
+        // again by the next chunk.
+        let query = format!(
+            "{query} ORDER BY {key_columns} LIMIT {}",
+            self.chunk_size + 1
+        );
+
+        let read = read_chunk(
+            self.pool.clone(),
+            self.signal_table.clone(),
+            chunk_id.clone(),
+            query,
+            params,
+        )
+        .boxed();
+        ChunkWindow {
+            chunk_id,
+            read: Some(read),
+            rows: None,
+            open: false,
+            closed: false,
+            close_pos: 0,
+            changed_keys: vec![],
+        }
+    }
+
+    /// Emits the rows of a chunk that have not been changed within its window, between the table
+    /// snapshot markers if it's the first or the last chunk of the table.
+    fn complete_chunk(&mut self, window: ChunkWindow, offset: &BinlogPosition) -> Result<()> {
+        let table = self
+            .current
+            .as_mut()
+            .ok_or_else(|| anyhow!("No table is being read"))?;
+        let new_event = |data| Event {
+            pos: window.close_pos,
+            // Assigned by the binlog stream.
+            offset: Default::default(),
+            ack: None,
+            database_name: table.database_name.clone(),
+            schema_name: Default::default(),
+            table_id: 0,
+            table_name: table.table_name.clone(),
+            data,
+            primary_key_changed: false,
+        };
+        if table.last_key.is_none() {
+            self.ready
+                .push_back(new_event(EventData::TableSnapshotStarted));
+        }
+
+        let mut rows = window.rows.unwrap_or_default();
+        let completed = rows.len() <= self.chunk_size;
+        rows.truncate(self.chunk_size);
+        let last_key = rows.last().map(|last| {
+            table
+                .key_indexes
+                .iter()
+                .map(|&i| last.as_ref(i).cloned().unwrap_or(Value::NULL))
+                .collect()
+        });
+        let identifier = table.identifier();
+        let selection = self
+            .selections
+            .get(&identifier)
+            .cloned()
+            .unwrap_or_default();
+        let key_columns = selection.key_columns(&table.key_columns);
+
+        let mut changes = vec![];
+        for row in rows {
+            let row = ChangeRow::new(to_binlog_row(row), None, &self.decoder)?;
+            if table
+                .key(&row)
+                .is_some_and(|key| window.changed_keys.contains(&key))
+            {
+                continue;
+            }
+            let row = selection
+                .project(row)?
+                .with_key_columns(key_columns.clone());
+            changes.push(RowChange::Read { row, last: false });
+        }
+        if completed {
+            if let Some(RowChange::Read { last, .. }) = changes.last_mut() {
+                *last = true;
+            }
+        }
+        let count = changes.len() as u64;
+        if !changes.is_empty() {
+            self.ready
+                .push_back(new_event(EventData::DataChange(changes)));
+        }
+        if completed {
+            let rows = table.rows + count;
+            self.ready
+                .push_back(new_event(EventData::TableSnapshotCompleted(rows)));
+            info!("Completed incremental snapshot of table {identifier} with {rows} rows");
+            self.current = None;
+            self.save_progress(offset, &identifier, None)
+        } else {
+            table.rows += count;
+            table.last_key = last_key;
+            let progress = table.progress();
+            self.save_progress(offset, &identifier, Some(progress))
+        }
+    }
+
+    fn save_progress(
+        &self,
+        offset: &BinlogPosition,
+        table: &str,
+        progress: Option<SnapshotProgress>,
+    ) -> Result<()> {
+        if let Some(store) = &self.progress_store {
+            store
+                .lock()
+                .map_err(|e| anyhow!("{e}"))?
+                .save(offset, table, progress)?;
+        }
+        Ok(())
+    }
+}
+
+impl TableProgress {
+    fn identifier(&self) -> String {
+        format!("{}.{}", self.database_name, self.table_name)
+    }
+
+    /// Returns the values of the primary key columns of `row`, `None` if a column is missing.
+    fn key(&self, row: &ChangeRow) -> Option<Vec<ColumnValue>> {
+        self.key_columns
+            .iter()
+            .map(|c| row.get_by_name(c).cloned())
+            .collect()
+    }
+
+    fn progress(&self) -> SnapshotProgress {
+        SnapshotProgress {
+            last_key: self.last_key.clone(),
+            rows: self.rows,
+        }
+    }
+}
+
+async fn load_table(
+    pool: Pool,
+    table: String,
+    generated_column_mode: GeneratedColumnMode,
+) -> Result<TableProgress> {
+    let (database_name, table_name) = table
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid table identifier {table}"))?;
+    let mut conn = pool.get_conn().await?;
+    let column_names =
+        load_selected_column_names(&mut conn, database_name, table_name, generated_column_mode)
+            .await?;
+    if column_names.is_empty() {
+        bail!("Table {table} doesn't exist");
+    }
+    let key_columns = load_primary_keys(&mut conn, database_name, table_name).await?;
+    if key_columns.is_empty() {
+        bail!("Table {table} has no primary key, which is required by the incremental snapshot");
+    }
+    let key_indexes = key_columns
+        .iter()
+        .map(|k| {
+            column_names
+                .iter()
+                .position(|c| c == k)
+                .ok_or_else(|| anyhow!("Unknown primary key column {k} of table {table}"))
+        })
+        .collect::<Result<_>>()?;
+    info!("Started incremental snapshot of table {table}");
+    Ok(TableProgress {
+        database_name: database_name.to_string(),
+        table_name: table_name.to_string(),
+        column_names,
+        key_columns,
+        key_indexes,
+        last_key: None,
+        rows: 0,
+    })
+}
+
+/// Reads a chunk between the low and high watermarks.
+async fn read_chunk(
+    pool: Pool,
+    signal_table: String,
+    chunk_id: String,
+    query: String,
+    params: Vec<Value>,
+) -> Result<Vec<Row>> {
+    let mut conn = pool.get_conn().await?;
+    let (database_name, table_name) = signal_table
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid signal table {signal_table}"))?;
+    let insert = format!(
+        "INSERT INTO {}.{} VALUES (?, ?, ?)",
+        quote_identifier(database_name),
+        quote_identifier(table_name)
+    );
+    conn.exec_drop(
+        &insert,
+        (format!("{chunk_id}-open"), OPEN_WINDOW, &chunk_id),
+    )
+    .await?;
+    let rows = conn.exec(query, params).await?;
+    conn.exec_drop(
+        &insert,
+        (format!("{chunk_id}-close"), CLOSE_WINDOW, &chunk_id),
+    )
+    .await?;
+    Ok(rows)
+}
+
+/// Starts capturing the tables that newly match the configuration while the binlog is being
+/// streamed, see [`SourceConfig::scan_newly_added_table_enabled`].
+///
+/// The binlog events of such a table are held back until its snapshot is read if the table may
+/// already hold rows, while a created table is captured right away. The snapshot is taken after the table
+/// started to be captured, so it may already contain the effects of some of the held-back events,
+/// which are then emitted after the snapshot. Since all the events are emitted in order, the
+/// captured table still converges to the latest state. The other tables are not held back.
+pub(crate) struct NewTableSnapshots {
+    pool: Pool,
+    batch_size: usize,
+    selections: HashMap<String, TableSelection>,
+    generated_column_mode: GeneratedColumnMode,
+    skip_noop_updates: bool,
+    decoder: ValueDecoder,
+
+    /// The tables that are being started, keyed by `databaseName.tableName`.
+    tables: HashMap<String, NewTable>,
+
+    /// The events that are ready to be emitted.
+    ready: VecDeque<Result<Event>>,
+}
+
+struct NewTable {
+    database_name: String,
+    table_name: String,
+    position: BinlogPosition,
+
+    /// The offset of the stream when the table started to be captured. The stream must restart
+    /// from it until the held-back events are emitted.
+    offset: BinlogPosition,
+    rows: BoxStream<'static, Result<Event>>,
+
+    /// The binlog events of the table received since it started to be captured, whose rows are
+    /// not selected yet.
+    held_back: Vec<Event>,
+}
+
+impl NewTableSnapshots {
+    pub fn new(pool: Pool, cfg: &SourceConfig) -> Result<Self> {
+        Ok(Self {
+            pool,
+            batch_size: cfg.split_size().max(1) as usize,
+            selections: TableSelection::from_config(cfg)?,
+            generated_column_mode: cfg.generated_column_mode(),
+            skip_noop_updates: cfg.skip_noop_updates(),
+            decoder: ValueDecoder::new(cfg)?,
+            tables: HashMap::new(),
+            ready: VecDeque::new(),
+        })
+    }
+
+    /// Starts capturing `databaseName.tableName` at `position`, while the stream is at `offset`.
+    /// The rows already in the table are snapshotted if `read_rows` is set, which is not needed for
+    /// a table created at `position`.
+    pub fn start(
+        &mut self,
+        table: &str,
+        position: BinlogPosition,
+        offset: BinlogPosition,
+        read_rows: bool,
+    ) -> Result<()> {
+        let (database_name, table_name) = table
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Invalid table identifier {table}"))?;
+        info!("Started capturing new table {table} at binlog position {position}");
+        if !read_rows {
+            return Ok(());
+        }
+        let rows = read_new_table(
+            self.pool.clone(),
+            database_name.to_string(),
+            table_name.to_string(),
+            self.selections.get(table).cloned().unwrap_or_default(),
+            self.generated_column_mode,
+            self.decoder.clone(),
+            self.batch_size,
+            position.clone(),
+        );
+        self.tables.insert(
+            table.to_string(),
+            NewTable {
+                database_name: database_name.to_string(),
+                table_name: table_name.to_string(),
+                position,
+                offset,
+                rows,
+                held_back: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    /// Stops starting the table, e.g. if it's dropped. The held-back events are still emitted.
+    pub fn cancel(&mut self, table: &str, schema: &MysqlSchema) {
+        if let Some(new_table) = self.tables.remove(table) {
+            info!("Stopped capturing new table {table}");
+            self.release(table, new_table.held_back, schema);
+        }
+    }
+
+    /// Returns the earliest offset of the tables being started, from which the stream must restart
+    /// so that their held-back events are not lost.
+    pub fn earliest_offset(&self) -> Option<&BinlogPosition> {
+        self.tables.values().map(|t| &t.offset).min()
+    }
+
+    /// Returns `true` if the table is being started, whose events must be held back.
+    pub fn contains(&self, table: &str) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    pub fn hold_back(&mut self, table: &str, event: Event) {
+        if let Some(new_table) = self.tables.get_mut(table) {
+            new_table.held_back.push(event);
+        }
+    }
+
+    /// Makes progress on the new tables. Returns the next event if one is ready. The held-back
+    /// events are named after the columns in `schema` when they're released.
+    pub fn poll_event(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        schema: &MysqlSchema,
+    ) -> Option<Result<Event>> {
+        if let Some(event) = self.ready.pop_front() {
+            return Some(event);
+        }
+        let mut completed = vec![];
+        for (table, new_table) in self.tables.iter_mut() {
+            match new_table.rows.poll_next_unpin(cx) {
+                Poll::Ready(Some(mut event)) => {
+                    if let Ok(event) = &mut event {
+                        event.offset = new_table.offset.clone();
+                    }
+                    self.ready.push_back(event);
+                }
+                Poll::Ready(None) => {
+                    info!(
+                        "Completed snapshot of new table {}.{} read at {}",
+                        new_table.database_name, new_table.table_name, new_table.position
+                    );
+                    completed.push(table.clone());
+                }
+                Poll::Pending => {}
+            }
+        }
+        for table in completed {
+            if let Some(new_table) = self.tables.remove(&table) {
+                self.release(&table, new_table.held_back, schema);
+            }
+        }
+        self.ready.pop_front()
+    }
+
+    /// Selects the rows of the held-back events and makes them ready to be emitted.
+    fn release(&mut self, table: &str, events: Vec<Event>, schema: &MysqlSchema) {
+        let selection = self.selections.get(table);
+        let def = schema.get(table);
+        for mut event in events {
+            if let EventData::DataChange(changes) = event.data {
+                let changes = match selection {
+                    Some(selection) => changes
+                        .into_iter()
+                        .filter_map(|c| selection.apply_change(c, def).transpose())
+                        .collect::<Result<Vec<_>>>(),
+                    None => Ok(changes),
+                };
+                let changes = match changes {
+                    Ok(changes) if self.skip_noop_updates => Ok(without_noop_updates(changes)),
+                    changes => changes,
+                };
+                match changes {
+                    Ok(changes) if changes.is_empty() => continue,
+                    Ok(changes) => event.data = EventData::DataChange(changes),
+                    Err(e) => {
+                        self.ready.push_back(Err(anyhow!(
+                            "Failed to select the rows of table {table}: {e}"
+                        )));
+                        continue;
+                    }
+                }
+            }
+            self.ready.push_back(Ok(event));
+        }
+    }
+}
+
+/// Reads the rows of a new table within a transaction of its own.
+#[allow(clippy::too_many_arguments)]
+fn read_new_table(
+    pool: Pool,
+    database_name: String,
+    table_name: String,
+    selection: TableSelection,
+    generated_column_mode: GeneratedColumnMode,
+    decoder: ValueDecoder,
+    batch_size: usize,
+    position: BinlogPosition,
+) -> BoxStream<'static, Result<Event>> {
+    reader_stream(move |mut tx| async move {
+        let result = async {
+            let mut conn = pool.get_conn().await?;
+            "SET SESSION TRANSACTION ISOLATION LEVEL REPEATABLE READ"
+                .ignore(&mut conn)
+                .await?;
+            "START TRANSACTION WITH CONSISTENT SNAPSHOT"
+                .ignore(&mut conn)
+                .await?;
+            info!("Reading snapshot of new table {database_name}.{table_name}");
+            read_table(
+                &mut conn,
+                &database_name,
+                &table_name,
+                &selection,
+                generated_column_mode,
+                &decoder,
+                batch_size,
+                &position,
+                &mut tx,
+            )
+            .await?;
+            "COMMIT".ignore(&mut conn).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = result {
+            let _ = tx.send(Err(e)).await;
+        }
+    })
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{anyhow, Result};
+use mysql_async::Value;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value as JsonValue};
+
+use crate::event::BinlogPosition;
+use crate::state::{
+    compact_schema_records, decode_schema_record, encode_schema_record, OffsetStore, SchemaHistory,
+    SchemaRecord, SnapshotProgress, SnapshotProgressStore,
+};
+
+/// An [`OffsetStore`], [`SchemaHistory`] and [`SnapshotProgressStore`] backed by a single SQLite
+/// database file. The clones of a store share its database, so a stream keeps all its state in one
+/// file by passing clones of the same store to [`crate::Source::with_offset_store`],
+/// [`crate::Source::with_schema_history`] and [`crate::Source::with_snapshot_progress`].
+///
+/// The snapshot progress is kept in memory until an offset later than the one it was saved at is
+/// committed, and is then written in the same transaction as that offset, so a crash never leaves
+/// a progress that is ahead of the committed offset.
+#[derive(Clone)]
+pub struct SqliteStateStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    conn: Connection,
+    compaction_interval: usize,
+
+    /// The snapshot progress that is saved but not committed yet, in the order of the saves.
+    staged: Vec<(BinlogPosition, String, Option<SnapshotProgress>)>,
+}
+
+impl SqliteStateStore {
+    /// Opens the database at `path`, which is created if it doesn't exist. The schema history is
+    /// compacted once `compaction_interval` DDL records precede the compaction offset, in the same
+    /// way as [`crate::FileSchemaHistory`].
+    pub fn open(path: impl AsRef<Path>, compaction_interval: usize) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS offsets (
+                id INTEGER PRIMARY KEY CHECK (id = 0),
+                filename TEXT NOT NULL,
+                position INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS schema_history (
+                id INTEGER PRIMARY KEY,
+                record TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS snapshot_progress (
+                table_name TEXT PRIMARY KEY,
+                last_key TEXT,
+                rows INTEGER NOT NULL
+            );",
+        )?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                conn,
+                compaction_interval: compaction_interval.max(1),
+                staged: vec![],
+            })),
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Inner>> {
+        self.inner.lock().map_err(|e| anyhow!("{e}"))
+    }
+}
+
+impl OffsetStore for SqliteStateStore {
+    fn load(&self) -> Result<Option<BinlogPosition>> {
+        let inner = self.lock()?;
+        let offset = inner
+            .conn
+            .query_row(
+                "SELECT filename, position FROM offsets WHERE id = 0",
+                [],
+                |row| Ok(BinlogPosition::new(row.get::<_, String>(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(offset)
+    }
+
+    fn commit(&mut self, offset: &BinlogPosition) -> Result<()> {
+        let mut inner = self.lock()?;
+        let inner = &mut *inner;
+        let tx = inner.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO offsets (id, filename, position) VALUES (0, ?1, ?2)
+            ON CONFLICT (id) DO UPDATE SET filename = excluded.filename, position = excluded.position",
+            params![offset.filename(), offset.position()],
+        )?;
+        for (_, table, progress) in inner.staged.iter().filter(|(o, ..)| o < offset) {
+            match progress {
+                Some(progress) => tx.execute(
+                    "INSERT OR REPLACE INTO snapshot_progress (table_name, last_key, rows)
+                    VALUES (?1, ?2, ?3)",
+                    params![
+                        table,
+                        progress.last_key.as_deref().map(encode_key),
+                        progress.rows
+                    ],
+                )?,
+                None => tx.execute(
+                    "DELETE FROM snapshot_progress WHERE table_name = ?1",
+                    params![table],
+                )?,
+            };
+        }
+        tx.commit()?;
+        inner.staged.retain(|(o, ..)| o >= offset);
+        Ok(())
+    }
+}
+
+impl SchemaHistory for SqliteStateStore {
+    fn load(&self) -> Result<Vec<SchemaRecord>> {
+        Ok(load_schema_records(&self.lock()?.conn)?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
+    fn append(&mut self, record: SchemaRecord) -> Result<()> {
+        let inner = self.lock()?;
+        let last = inner
+            .conn
+            .query_row(
+                "SELECT record FROM schema_history ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        if let Some(last) = last {
+            let last = decode_schema_record(&last)
+                .ok_or_else(|| anyhow!("Invalid schema record {last}"))?;
+            if record.offset < last.offset {
+                return Err(anyhow!(
+                    "The schema record at {} precedes the last record at {}",
+                    record.offset,
+                    last.offset
+                ));
+            }
+        }
+        inner.conn.execute(
+            "INSERT INTO schema_history (record) VALUES (?1)",
+            params![encode_schema_record(&record)],
+        )?;
+        Ok(())
+    }
+
+    fn compact(&mut self, offset: &BinlogPosition) -> Result<()> {
+        let mut inner = self.lock()?;
+        let inner = &mut *inner;
+        let records = load_schema_records(&inner.conn)?;
+        let (ids, records): (Vec<i64>, Vec<SchemaRecord>) = records.into_iter().unzip();
+        let Some((compacted, snapshot)) =
+            compact_schema_records(&records, offset, inner.compaction_interval)
+        else {
+            return Ok(());
+        };
+        // The snapshot takes the id of the last folded record to stay before the rest.
+        let last_id = ids[compacted - 1];
+        let tx = inner.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM schema_history WHERE id <= ?1",
+            params![last_id],
+        )?;
+        tx.execute(
+            "INSERT INTO schema_history (id, record) VALUES (?1, ?2)",
+            params![last_id, encode_schema_record(&snapshot)],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl SnapshotProgressStore for SqliteStateStore {
+    fn load(&self) -> Result<BTreeMap<String, SnapshotProgress>> {
+        let inner = self.lock()?;
+        let mut stmt = inner
+            .conn
+            .prepare("SELECT table_name, last_key, rows FROM snapshot_progress")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, u64>(2)?,
+            ))
+        })?;
+        let mut progress = BTreeMap::new();
+        for row in rows {
+            let (table, last_key, rows) = row?;
+            let last_key = match last_key {
+                Some(key) => Some(
+                    decode_key(&key)
+                        .ok_or_else(|| anyhow!("Invalid snapshot key {key} of table {table}"))?,
+                ),
+                None => None,
+            };
+            progress.insert(table, SnapshotProgress { last_key, rows });
+        }
+        Ok(progress)
+    }
+
+    fn save(
+        &mut self,
+        offset: &BinlogPosition,
+        table: &str,
+        progress: Option<SnapshotProgress>,
+    ) -> Result<()> {
+        self.lock()?
+            .staged
+            .push((offset.clone(), table.to_string(), progress));
+        Ok(())
+    }
+}
+
+/// Returns the schema records with their ids, ordered by id.
+fn load_schema_records(conn: &Connection) -> Result<Vec<(i64, SchemaRecord)>> {
+    let mut stmt = conn.prepare("SELECT id, record FROM schema_history ORDER BY id")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?;
+    let mut records = vec![];
+    for row in rows {
+        let (id, record) = row?;
+        let record = decode_schema_record(&record)
+            .ok_or_else(|| anyhow!("Invalid schema record {record}"))?;
+        records.push((id, record));
+    }
+    Ok(records)
+}
+
+/// Encodes a primary key as a JSON array, in which each value is tagged by its type so that it's
+/// decoded to the same [`Value`].
+fn encode_key(key: &[Value]) -> String {
+    let values = key
+        .iter()
+        .map(|value| match value {
+            Value::NULL => JsonValue::Null,
+            Value::Bytes(bytes) => json!({ "bytes": bytes }),
+            Value::Int(i) => json!({ "int": i }),
+            Value::UInt(u) => json!({ "uint": u }),
+            Value::Float(f) => json!({ "float": f }),
+            Value::Double(d) => json!({ "double": d }),
+            Value::Date(year, month, day, hour, minute, second, micros) => {
+                json!({ "date": [year, month, day, hour, minute, second, micros] })
+            }
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                json!({ "time": [negative, days, hours, minutes, seconds, micros] })
+            }
+        })
+        .collect();
+    JsonValue::Array(values).to_string()
+}
+
+fn decode_key(s: &str) -> Option<Vec<Value>> {
+    let key: JsonValue = serde_json::from_str(s).ok()?;
+    key.as_array()?.iter().map(decode_value).collect()
+}
+
+fn decode_value(value: &JsonValue) -> Option<Value> {
+    if value.is_null() {
+        return Some(Value::NULL);
+    }
+    let (kind, value) = value.as_object()?.iter().next()?;
+    let parts = |len: usize| -> Option<Vec<u64>> {
+        let parts = value.as_array()?;
+        if parts.len() != len {
+            return None;
+        }
+        parts.iter().map(|p| p.as_u64()).collect()
+    };
+    Some(match kind.as_str() {
+        "bytes" => Value::Bytes(
+            value
+                .as_array()?
+                .iter()
+                .map(|b| b.as_u64()?.try_into().ok())
+                .collect::<Option<_>>()?,
+        ),
+        "int" => Value::Int(value.as_i64()?),
+        "uint" => Value::UInt(value.as_u64()?),
+        "float" => Value::Float(value.as_f64()? as f32),
+        "double" => Value::Double(value.as_f64()?),
+        "date" => {
+            let p = parts(7)?;
+            Value::Date(
+                p[0].try_into().ok()?,
+                p[1].try_into().ok()?,
+                p[2].try_into().ok()?,
+                p[3].try_into().ok()?,
+                p[4].try_into().ok()?,
+                p[5].try_into().ok()?,
+                p[6].try_into().ok()?,
+            )
+        }
+        "time" => {
+            let negative = value.as_array()?.first()?.as_bool()?;
+            let p = value.as_array()?[1..]
+                .iter()
+                .map(|p| p.as_u64())
+                .collect::<Option<Vec<_>>>()?;
+            if p.len() != 5 {
+                return None;
+            }
+            Value::Time(
+                negative,
+                p[0].try_into().ok()?,
+                p[1].try_into().ok()?,
+                p[2].try_into().ok()?,
+                p[3].try_into().ok()?,
+                p[4].try_into().ok()?,
+            )
+        }
+        _ => return None,
+    })
+}
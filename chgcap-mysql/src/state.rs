@@ -1 +1,420 @@
-pub struct CdcState {}
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use mysql_async::Value;
+use serde_json::{json, Value as JsonValue};
+
+use crate::event::BinlogPosition;
+use crate::schema::MysqlTableDef;
+
+/// Persists the offsets of a [`crate::BinlogStream`], so that it resumes from the last committed
+/// offset after a restart, see [`crate::Event::offset`].
+pub trait OffsetStore: Send {
+    /// Returns the last committed offset, or `None` if no offset has been committed.
+    fn load(&self) -> Result<Option<BinlogPosition>>;
+
+    /// Durably records the offset. It's returned by [`OffsetStore::load`] once this returns.
+    fn commit(&mut self, offset: &BinlogPosition) -> Result<()>;
+}
+
+pub(crate) type SharedOffsetStore = Arc<Mutex<Box<dyn OffsetStore>>>;
+
+/// Commits the offset, and compacts the schema history up to it.
+pub(crate) fn commit_offset(
+    store: &SharedOffsetStore,
+    schema_history: Option<&SharedSchemaHistory>,
+    offset: &BinlogPosition,
+) -> Result<()> {
+    store.lock().map_err(|e| anyhow!("{e}"))?.commit(offset)?;
+    if let Some(history) = schema_history {
+        history
+            .lock()
+            .map_err(|e| anyhow!("{e}"))?
+            .compact(offset)?;
+    }
+    Ok(())
+}
+
+/// An [`OffsetStore`] backed by a file, which holds the last committed offsets, one
+/// `filename:position` per line from the oldest to the newest.
+///
+/// The file is replaced atomically on every commit: the offsets are written and synced to a
+/// temporary file, which is then renamed over the file. A crash leaves either the previous or the
+/// new offsets, never a partially written file.
+pub struct FileOffsetStore {
+    path: PathBuf,
+
+    /// The number of committed offsets to keep, including the last one.
+    history_size: usize,
+
+    /// The committed offsets, from the oldest to the newest.
+    offsets: VecDeque<BinlogPosition>,
+}
+
+impl FileOffsetStore {
+    /// Opens the store at `path`, which is created on the first commit if it doesn't exist. The
+    /// last `history_size` committed offsets are kept for [`FileOffsetStore::rollback`].
+    pub fn open(path: impl Into<PathBuf>, history_size: usize) -> Result<Self> {
+        let path = path.into();
+        let mut offsets = VecDeque::new();
+        if path.exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                offsets.push_back(
+                    parse_offset(line).ok_or_else(|| {
+                        anyhow!("Invalid offset {line} in file {}", path.display())
+                    })?,
+                );
+            }
+        }
+        let history_size = history_size.max(1);
+        while offsets.len() > history_size {
+            offsets.pop_front();
+        }
+        Ok(Self {
+            path,
+            history_size,
+            offsets,
+        })
+    }
+
+    /// The kept offsets, from the newest to the oldest.
+    pub fn history(&self) -> impl Iterator<Item = &BinlogPosition> {
+        self.offsets.iter().rev()
+    }
+
+    /// Discards the newest `steps` offsets, so that the stream resumes from an earlier offset.
+    /// Returns the offset to resume from, which is `None` if no offset is left.
+    pub fn rollback(&mut self, steps: usize) -> Result<Option<BinlogPosition>> {
+        if steps > self.offsets.len() {
+            return Err(anyhow!(
+                "Cannot roll back {steps} offsets, only {} are kept",
+                self.offsets.len()
+            ));
+        }
+        self.offsets.truncate(self.offsets.len() - steps);
+        self.persist()?;
+        Ok(self.offsets.back().cloned())
+    }
+
+    fn persist(&self) -> Result<()> {
+        let contents: String = self.offsets.iter().map(|o| format!("{o}\n")).collect();
+        write_atomically(&self.path, contents.as_bytes())
+    }
+}
+
+impl OffsetStore for FileOffsetStore {
+    fn load(&self) -> Result<Option<BinlogPosition>> {
+        Ok(self.offsets.back().cloned())
+    }
+
+    fn commit(&mut self, offset: &BinlogPosition) -> Result<()> {
+        self.offsets.push_back(offset.clone());
+        while self.offsets.len() > self.history_size {
+            self.offsets.pop_front();
+        }
+        self.persist()
+    }
+}
+
+/// Replaces the file at `path` with `contents`, which is written and synced to a temporary file
+/// that is then renamed over the file.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_path = path.to_path_buf().into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    sync_parent_dir(path)
+}
+
+/// Syncs the directory of `path`, without which a created or renamed file may be lost on a crash.
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            File::open(".")?
+        } else {
+            File::open(dir)?
+        };
+        dir.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Parses a `filename:position` offset.
+pub(crate) fn parse_offset(s: &str) -> Option<BinlogPosition> {
+    let (filename, position) = s.rsplit_once(':')?;
+    Some(BinlogPosition::new(filename, position.parse().ok()?))
+}
+
+/// A change of the captured schema, see [`SchemaHistory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaRecord {
+    /// The binlog position of the end of the DDL statement.
+    pub offset: BinlogPosition,
+
+    /// The DDL statement, or `None` for a snapshot of the whole schema. A table whose columns are
+    /// reconciled with the table map event of its rows is recorded with a comment instead, e.g.
+    /// `/* db.t reconciled with the binlog */`.
+    pub ddl: Option<String>,
+
+    /// The definitions of the tables after the record, keyed by `databaseName.tableName`. A
+    /// dropped table is mapped to `None`. A snapshot holds all tables, while a DDL only holds the
+    /// tables it changes.
+    pub tables: BTreeMap<String, Option<MysqlTableDef>>,
+}
+
+/// The definitions of the captured tables, keyed by `databaseName.tableName`.
+pub(crate) type Schema = HashMap<String, MysqlTableDef>;
+
+/// Records the DDLs of the captured tables along the binlog, so that the rows of a stream that
+/// resumes from an old offset are decoded against the schema at that offset rather than the
+/// current one.
+pub trait SchemaHistory: Send {
+    /// Returns all records, ordered by offset.
+    fn load(&self) -> Result<Vec<SchemaRecord>>;
+
+    /// Durably appends a record, whose offset must not precede the existing ones.
+    fn append(&mut self, record: SchemaRecord) -> Result<()>;
+
+    /// Folds the records up to `offset` into a snapshot at `offset`. The stream can't resume
+    /// before `offset` afterwards, so it must not be later than the committed offset. The
+    /// implementation may defer the compaction until enough records are accumulated.
+    fn compact(&mut self, offset: &BinlogPosition) -> Result<()>;
+
+    /// Rebuilds the schema as of `offset`, i.e. after the records up to `offset`. Returns `None`
+    /// if there's no record up to `offset`.
+    fn schema_at(&self, offset: &BinlogPosition) -> Result<Option<Schema>> {
+        let records = self.load()?;
+        let mut records = records.iter().filter(|r| &r.offset <= offset).peekable();
+        if records.peek().is_none() {
+            return Ok(None);
+        }
+        let mut schema = Schema::new();
+        for record in records {
+            apply_schema_record(&mut schema, record);
+        }
+        Ok(Some(schema))
+    }
+}
+
+pub(crate) type SharedSchemaHistory = Arc<Mutex<Box<dyn SchemaHistory>>>;
+
+pub(crate) fn apply_schema_record(schema: &mut Schema, record: &SchemaRecord) {
+    if record.ddl.is_none() {
+        schema.clear();
+    }
+    for (table, table_def) in &record.tables {
+        match table_def {
+            Some(table_def) => schema.insert(table.clone(), table_def.clone()),
+            None => schema.remove(table),
+        };
+    }
+}
+
+/// A [`SchemaHistory`] backed by an append-only file, which holds a JSON record per line.
+///
+/// Once `compaction_interval` DDL records precede the compaction offset, they are folded into a
+/// snapshot record, and the file is replaced atomically in the same way as [`FileOffsetStore`].
+pub struct FileSchemaHistory {
+    path: PathBuf,
+    compaction_interval: usize,
+    records: Vec<SchemaRecord>,
+}
+
+impl FileSchemaHistory {
+    /// Opens the history at `path`, which is created on the first append if it doesn't exist.
+    pub fn open(path: impl Into<PathBuf>, compaction_interval: usize) -> Result<Self> {
+        let path = path.into();
+        let mut records = vec![];
+        if path.exists() {
+            // A partial line may end in the middle of a character.
+            let contents = fs::read(&path)?;
+            let lines: Vec<&[u8]> = contents.split_inclusive(|b| *b == b'\n').collect();
+            // The length of the complete records.
+            let mut len = 0;
+            for (i, line) in lines.iter().enumerate() {
+                let record = line
+                    .strip_suffix(b"\n")
+                    .and_then(|l| std::str::from_utf8(l).ok())
+                    .and_then(|l| match l.trim() {
+                        "" => Some(None),
+                        l => decode_schema_record(l).map(Some),
+                    });
+                match record {
+                    Some(record) => {
+                        records.extend(record);
+                        len += line.len();
+                    }
+                    // A crash during an append may leave a partial last line.
+                    None if i + 1 == lines.len() => {
+                        log::warn!("Ignored the partial last record of {}", path.display());
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "Invalid schema record at line {} of {}",
+                            i + 1,
+                            path.display()
+                        ))
+                    }
+                }
+            }
+            // The next record is appended after the complete records rather than after the
+            // partial one.
+            if len < contents.len() {
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(len as u64)?;
+                file.sync_data()?;
+            }
+        }
+        Ok(Self {
+            path,
+            compaction_interval: compaction_interval.max(1),
+            records,
+        })
+    }
+
+    fn persist(&self) -> Result<()> {
+        let contents: String = self
+            .records
+            .iter()
+            .map(|r| format!("{}\n", encode_schema_record(r)))
+            .collect();
+        write_atomically(&self.path, contents.as_bytes())
+    }
+}
+
+impl SchemaHistory for FileSchemaHistory {
+    fn load(&self) -> Result<Vec<SchemaRecord>> {
+        Ok(self.records.clone())
+    }
+
+    fn append(&mut self, record: SchemaRecord) -> Result<()> {
+        if let Some(last) = self.records.last() {
+            if record.offset < last.offset {
+                return Err(anyhow!(
+                    "The schema record at {} precedes the last record at {}",
+                    record.offset,
+                    last.offset
+                ));
+            }
+        }
+        let created = !self.path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", encode_schema_record(&record))?;
+        file.sync_data()?;
+        if created {
+            sync_parent_dir(&self.path)?;
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    fn compact(&mut self, offset: &BinlogPosition) -> Result<()> {
+        let Some((compacted, snapshot)) =
+            compact_schema_records(&self.records, offset, self.compaction_interval)
+        else {
+            return Ok(());
+        };
+        self.records.splice(..compacted, [snapshot]);
+        self.persist()
+    }
+}
+
+/// Folds the records up to `offset` into a snapshot record if at least `compaction_interval` DDL
+/// records are among them. Returns the number of folded records and the snapshot.
+pub(crate) fn compact_schema_records(
+    records: &[SchemaRecord],
+    offset: &BinlogPosition,
+    compaction_interval: usize,
+) -> Option<(usize, SchemaRecord)> {
+    let compacted = records.partition_point(|r| &r.offset <= offset);
+    let ddls = records[..compacted]
+        .iter()
+        .filter(|r| r.ddl.is_some())
+        .count();
+    if ddls < compaction_interval {
+        return None;
+    }
+    let mut schema = Schema::new();
+    for record in &records[..compacted] {
+        apply_schema_record(&mut schema, record);
+    }
+    let snapshot = SchemaRecord {
+        offset: offset.clone(),
+        ddl: None,
+        tables: schema.into_iter().map(|(t, d)| (t, Some(d))).collect(),
+    };
+    Some((compacted, snapshot))
+}
+
+pub(crate) fn encode_schema_record(record: &SchemaRecord) -> String {
+    json!({
+        "offset": record.offset.to_string(),
+        "ddl": record.ddl,
+        "tables": record.tables,
+    })
+    .to_string()
+}
+
+pub(crate) fn decode_schema_record(line: &str) -> Option<SchemaRecord> {
+    let value: JsonValue = serde_json::from_str(line).ok()?;
+    let offset = parse_offset(value.get("offset")?.as_str()?)?;
+    let ddl = match value.get("ddl")? {
+        JsonValue::Null => None,
+        ddl => Some(ddl.as_str()?.to_string()),
+    };
+    let tables = serde_json::from_value(value.get("tables")?.clone()).ok()?;
+    Some(SchemaRecord {
+        offset,
+        ddl,
+        tables,
+    })
+}
+
+/// The progress of the incremental snapshot of a table, see [`SnapshotProgressStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotProgress {
+    /// The primary key of the last row read, `None` if no chunk has been read.
+    pub last_key: Option<Vec<Value>>,
+
+    /// The number of rows emitted.
+    pub rows: u64,
+}
+
+/// Persists the progress of the incremental snapshots, so that a restarted
+/// [`crate::BinlogStream`] resumes them from the last chunk rather than from the first one.
+pub trait SnapshotProgressStore: Send {
+    /// Returns the progress of the unfinished snapshots, keyed by `databaseName.tableName`.
+    fn load(&self) -> Result<BTreeMap<String, SnapshotProgress>>;
+
+    /// Records the progress of `table`, or that its snapshot is finished or stopped if `progress`
+    /// is `None`. The rows read up to the progress are emitted at `offset`, so the progress must
+    /// only become durable once an offset later than `offset` is committed, otherwise the rows
+    /// that are not acknowledged yet are skipped after a restart.
+    fn save(
+        &mut self,
+        offset: &BinlogPosition,
+        table: &str,
+        progress: Option<SnapshotProgress>,
+    ) -> Result<()>;
+}
+
+pub(crate) type SharedSnapshotProgressStore = Arc<Mutex<Box<dyn SnapshotProgressStore>>>;
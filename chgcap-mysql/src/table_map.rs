@@ -0,0 +1,182 @@
+//! The columns of a table as described by its table map event, which are compared with the schema
+//! of the table, and complete it when the schema lacks them.
+
+use chgcap_mysql_ddl_parser::ast::data_type::{DataType, ExactNumberInfo};
+use chgcap_mysql_ddl_parser::ast::{Ident, ObjectName};
+use mysql_async::binlog::events::{OptionalMetadataField, TableMapEvent};
+use mysql_async::consts::ColumnType;
+
+use crate::schema::MysqlColumnDef;
+use crate::serialized::column_type_name;
+
+/// The columns of a table map event. The names and the labels of the ENUM and SET columns are
+/// only present if the server sets `binlog_row_metadata=FULL`.
+pub(crate) struct TableMapColumns {
+    /// The real types of the columns, e.g. `MYSQL_TYPE_ENUM` rather than `MYSQL_TYPE_STRING`.
+    /// `None` if a type is unknown.
+    pub types: Vec<Option<ColumnType>>,
+
+    /// The names of the columns, which are empty if they're not logged.
+    pub names: Vec<String>,
+
+    /// The labels of the ENUM and SET columns, by column index.
+    pub labels: Vec<Option<Vec<String>>>,
+
+    /// The `(precision, scale)` of the DECIMAL columns, by column index.
+    pub decimals: Vec<Option<(u64, u64)>>,
+
+    /// The number of bits of the BIT columns, by column index.
+    pub bit_widths: Vec<Option<u64>>,
+
+    pub nullable: Vec<bool>,
+}
+
+impl TableMapColumns {
+    pub fn new(tme: &TableMapEvent) -> Self {
+        let count = tme.columns_count() as usize;
+        let types: Vec<Option<ColumnType>> = (0..count)
+            .map(|i| tme.get_column_type(i).ok().flatten())
+            .collect();
+        let mut names = vec![];
+        let mut enum_labels = vec![];
+        let mut set_labels = vec![];
+        for field in tme.iter_optional_meta().flatten() {
+            match field {
+                OptionalMetadataField::ColumnName(n) => {
+                    names = n.iter_names().flatten().map(|n| n.name().into()).collect();
+                }
+                OptionalMetadataField::EnumStrValue(v) => {
+                    enum_labels = v
+                        .iter_values()
+                        .flatten()
+                        .map(|v| v.values().iter().map(|l| l.value().into()).collect())
+                        .collect();
+                }
+                OptionalMetadataField::SetStrValue(v) => {
+                    set_labels = v
+                        .iter_values()
+                        .flatten()
+                        .map(|v| v.values().iter().map(|l| l.value().into()).collect())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        if names.len() != count {
+            names.clear();
+        }
+
+        let (mut enum_labels, mut set_labels) = (enum_labels.into_iter(), set_labels.into_iter());
+        let labels = types
+            .iter()
+            .map(|ty| match ty {
+                Some(ColumnType::MYSQL_TYPE_ENUM) => enum_labels.next(),
+                Some(ColumnType::MYSQL_TYPE_SET) => set_labels.next(),
+                _ => None,
+            })
+            .collect();
+        let metadata = |i: usize| tme.get_column_metadata(i).unwrap_or_default();
+        let decimals = types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| match (ty, metadata(i)) {
+                (Some(ColumnType::MYSQL_TYPE_NEWDECIMAL), [precision, scale, ..]) => {
+                    Some((*precision as u64, *scale as u64))
+                }
+                _ => None,
+            })
+            .collect();
+        // The metadata of a BIT column is the number of bits in its last byte, followed by the
+        // number of full bytes.
+        let bit_widths = types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| match (ty, metadata(i)) {
+                (Some(ColumnType::MYSQL_TYPE_BIT), [bits, bytes, ..]) => {
+                    Some(*bytes as u64 * 8 + *bits as u64)
+                }
+                _ => None,
+            })
+            .collect();
+        let nullable = (0..count)
+            .map(|i| tme.null_bitmask().get(i).is_some_and(|b| *b))
+            .collect();
+        Self {
+            types,
+            names,
+            labels,
+            decimals,
+            bit_widths,
+            nullable,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn has_names(&self) -> bool {
+        !self.names.is_empty()
+    }
+
+    /// Returns `true` if the `i`-th column may be `column_def`, by its type and by its name if
+    /// the names are logged.
+    pub fn matches(&self, i: usize, column_def: &MysqlColumnDef) -> bool {
+        let name_matches = !self.has_names() || self.names[i] == column_def.name;
+        let type_matches = self.types[i].is_none_or(|ty| column_def.matches_binlog_type(ty));
+        name_matches && type_matches
+    }
+
+    /// Returns the `i`-th column completed with the metadata of the table map event, i.e. the
+    /// precision and scale of a DECIMAL, the width of a BIT and the labels of an ENUM or a SET.
+    pub fn complete(&self, i: usize, mut column_def: MysqlColumnDef) -> MysqlColumnDef {
+        if let Some(data_type) = self.data_type(i) {
+            column_def.data_type = data_type;
+        }
+        column_def
+    }
+
+    /// Builds the definition of the `i`-th column, which is named after its position `i` if the
+    /// names are not logged. The character set of a string is left to the metadata of the
+    /// column in the binlog rows.
+    pub fn column_def(&self, i: usize) -> MysqlColumnDef {
+        let data_type = self.data_type(i).unwrap_or_else(|| {
+            let name = self.types[i].map_or("UNKNOWN", column_type_name);
+            DataType::Custom(ObjectName(vec![Ident::new(name.to_lowercase())]), vec![])
+        });
+        MysqlColumnDef {
+            name: self
+                .names
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| format!("@{i}")),
+            data_type,
+            nullable: self.nullable[i],
+            default: None,
+            charset: None,
+            collation: None,
+            generated: None,
+            invisible: false,
+        }
+    }
+
+    /// Returns the data type of the `i`-th column if the table map event fully describes it.
+    fn data_type(&self, i: usize) -> Option<DataType> {
+        if let Some((precision, scale)) = self.decimals[i] {
+            return Some(DataType::Decimal(ExactNumberInfo::PrecisionAndScale(
+                precision, scale,
+            )));
+        }
+        if let Some(width) = self.bit_widths[i] {
+            return Some(DataType::Custom(
+                ObjectName(vec![Ident::new("bit")]),
+                vec![width.to_string()],
+            ));
+        }
+        match (self.types[i], self.labels[i].clone()) {
+            (Some(ColumnType::MYSQL_TYPE_ENUM), Some(labels)) => Some(DataType::Enum(labels)),
+            (Some(ColumnType::MYSQL_TYPE_SET), Some(labels)) => Some(DataType::Set(labels)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use base64::prelude::*;
+use bigdecimal::BigDecimal;
+use encoding_rs::Encoding;
+use mysql_async::binlog::value::BinlogValue;
+use mysql_async::consts::{ColumnFlags, ColumnType};
+use mysql_async::prelude::Queryable;
+use mysql_async::{Column, Conn, Value as MysqlValue};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BinaryMode, BitMode, DecimalMode, TemporalMode, ZeroDateMode};
+use crate::event::to_serde_json;
+use crate::geometry::Geometry;
+use crate::SourceConfig;
+
+/// The collation of binary strings, e.g. BINARY, VARBINARY and BLOB columns.
+const BINARY_COLLATION: u16 = 63;
+
+/// The value of a column, decoded from a binlog row or a snapshot row. Both decode to the same
+/// value for the same column.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    /// An exact decimal with the scale of its column, e.g. `-12.340` of a `DECIMAL(5, 3)`.
+    Decimal(BigDecimal),
+    Date {
+        year: u16,
+        month: u8,
+        day: u8,
+    },
+    /// A time of day or an elapsed time, which may be negative and exceed 24 hours.
+    Time {
+        negative: bool,
+        hours: u32,
+        minutes: u8,
+        seconds: u8,
+        micros: u32,
+    },
+    /// A DATETIME, which has no time zone.
+    DateTime {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        micros: u32,
+    },
+    /// A TIMESTAMP, as microseconds since the Unix epoch in UTC.
+    Timestamp(i64),
+    Bytes(Vec<u8>),
+    String(String),
+    Json(serde_json::Value),
+    /// The label of an ENUM value.
+    Enum(String),
+    /// The labels of a SET value.
+    Set(Vec<String>),
+    /// A BIT value, whose least significant bit is the last bit of the column.
+    Bit(u64),
+    Geometry(Geometry),
+}
+
+/// Decodes the values of the binlog rows and the snapshot rows.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ValueDecoder {
+    /// The character sets of the collations of the server by collation id. The strings of the
+    /// binlog are encoded in the character sets of their columns, while the strings of a snapshot
+    /// are converted to the character set of the connection by the server.
+    charsets: HashMap<u16, String>,
+
+    binary_mode: BinaryMode,
+    bit_mode: BitMode,
+    decimal_mode: DecimalMode,
+    temporal_mode: TemporalMode,
+    zero_date_mode: ZeroDateMode,
+}
+
+impl ValueDecoder {
+    pub(crate) fn new(cfg: &SourceConfig) -> Result<Self> {
+        Ok(Self {
+            charsets: HashMap::new(),
+            binary_mode: cfg.binary_mode(),
+            bit_mode: cfg.bit_mode(),
+            decimal_mode: cfg.decimal_mode(),
+            temporal_mode: cfg.temporal_mode(),
+            zero_date_mode: cfg.zero_date_mode(),
+        })
+    }
+
+    /// Loads the character sets of the collations of the server, which are needed to decode the
+    /// strings of the binlog.
+    pub(crate) async fn load_charsets(mut self, conn: &mut Conn) -> Result<Self> {
+        self.charsets = conn
+            .query("SELECT ID, CHARACTER_SET_NAME FROM information_schema.COLLATIONS")
+            .await?
+            .into_iter()
+            .collect();
+        Ok(self)
+    }
+
+    /// Decodes the value of `column`. The binlog and the snapshot represent some types differently,
+    /// e.g. the binlog has the epoch seconds of a TIMESTAMP, which are told apart by `column`.
+    pub(crate) fn decode(&self, value: Option<&BinlogValue>, column: &Column) -> Result<Value> {
+        let value = match value {
+            None => return Ok(Value::Null),
+            Some(BinlogValue::Value(value)) => value,
+            Some(BinlogValue::Jsonb(value)) => return Ok(Value::Json(to_serde_json(value)?)),
+            Some(BinlogValue::JsonDiff(_)) => {
+                bail!(
+                    "Partial JSON updates of column {} are not supported",
+                    column.name_str()
+                )
+            }
+        };
+        let column_type = column.column_type();
+        let flags = column.flags();
+        let value = match (column_type, value) {
+            (_, MysqlValue::NULL) => Value::Null,
+            (ColumnType::MYSQL_TYPE_TIMESTAMP | ColumnType::MYSQL_TYPE_TIMESTAMP2, value) => {
+                match timestamp_micros(value)? {
+                    Some(micros) => Value::Timestamp(micros),
+                    None => self.zero_date(Value::Null, Value::Timestamp(0)),
+                }
+            }
+            (ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL, value) => {
+                let decimal = match value {
+                    MysqlValue::Bytes(bytes) => String::from_utf8(bytes.clone())?,
+                    value => value.as_sql(true),
+                };
+                match self.decimal_mode {
+                    DecimalMode::Precise => Value::Decimal(decimal.parse()?),
+                    DecimalMode::String => Value::String(decimal),
+                    DecimalMode::Double => Value::Double(decimal.parse()?),
+                }
+            }
+            (ColumnType::MYSQL_TYPE_YEAR, MysqlValue::Bytes(bytes)) => {
+                Value::Int(std::str::from_utf8(bytes)?.parse()?)
+            }
+            (ColumnType::MYSQL_TYPE_BIT, MysqlValue::Bytes(bytes)) => {
+                if bytes.len() > 8 {
+                    bail!("Invalid BIT value {bytes:?}");
+                }
+                match self.bit_mode {
+                    BitMode::Binary => self.binary(bytes),
+                    // The binlog doesn't have the widths of the BIT columns.
+                    _ => self.bits(
+                        bytes.iter().fold(0, |bits, &b| bits << 8 | b as u64),
+                        Some(column.column_length() as u64).filter(|&width| width > 0),
+                    ),
+                }
+            }
+            // The binlog has the index of an ENUM value and the bitmask of a SET value, whereas
+            // the snapshot has their labels.
+            (ColumnType::MYSQL_TYPE_ENUM, MysqlValue::Int(index)) => Value::UInt(*index as u64),
+            (ColumnType::MYSQL_TYPE_SET, MysqlValue::Bytes(bytes)) => {
+                Value::UInt(bytes.iter().rev().fold(0, |mask, &b| mask << 8 | b as u64))
+            }
+            (_, MysqlValue::Bytes(bytes)) if flags.contains(ColumnFlags::ENUM_FLAG) => {
+                Value::Enum(String::from_utf8(bytes.clone())?)
+            }
+            (_, MysqlValue::Bytes(bytes)) if flags.contains(ColumnFlags::SET_FLAG) => {
+                let labels = std::str::from_utf8(bytes)?;
+                Value::Set(
+                    labels
+                        .split(',')
+                        .filter(|l| !l.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                )
+            }
+            (ColumnType::MYSQL_TYPE_JSON, MysqlValue::Bytes(bytes)) => {
+                Value::Json(serde_json::from_slice(bytes)?)
+            }
+            (ColumnType::MYSQL_TYPE_GEOMETRY, MysqlValue::Bytes(bytes)) => {
+                Value::Geometry(Geometry::from_mysql(bytes)?)
+            }
+            (_, MysqlValue::Bytes(bytes)) => match column.character_set() {
+                BINARY_COLLATION => self.binary(bytes),
+                collation => {
+                    self.decode_string(bytes, self.charsets.get(&collation).map(String::as_str))
+                }
+            },
+            (_, MysqlValue::Int(i)) => Value::Int(*i),
+            (_, MysqlValue::UInt(u)) => Value::UInt(*u),
+            (_, MysqlValue::Float(f)) => Value::Float(*f),
+            (_, MysqlValue::Double(d)) => Value::Double(*d),
+            (
+                ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE,
+                MysqlValue::Date(year, month, day, ..),
+            ) => Value::Date {
+                year: *year,
+                month: *month,
+                day: *day,
+            },
+            (_, MysqlValue::Date(year, month, day, hour, minute, second, micros)) => {
+                Value::DateTime {
+                    year: *year,
+                    month: *month,
+                    day: *day,
+                    hour: *hour,
+                    minute: *minute,
+                    second: *second,
+                    micros: *micros,
+                }
+            }
+            (_, MysqlValue::Time(negative, days, hours, minutes, seconds, micros)) => Value::Time {
+                negative: *negative,
+                hours: days * 24 + *hours as u32,
+                minutes: *minutes,
+                seconds: *seconds,
+                micros: *micros,
+            },
+        };
+        Ok(self.convert_temporal(value))
+    }
+
+    /// Represents a binary string in the [`BinaryMode`].
+    fn binary(&self, bytes: &[u8]) -> Value {
+        match self.binary_mode {
+            BinaryMode::Bytes => Value::Bytes(bytes.to_vec()),
+            BinaryMode::Hex => Value::String(bytes.iter().map(|b| format!("{b:02x}")).collect()),
+            BinaryMode::Base64 => Value::String(BASE64_STANDARD.encode(bytes)),
+        }
+    }
+
+    /// Represents the bits of a BIT column of `width` bits in the [`BitMode`], where the width is
+    /// `None` if it's unknown.
+    pub(crate) fn bits(&self, bits: u64, width: Option<u64>) -> Value {
+        match (self.bit_mode, width) {
+            (BitMode::Boolean, Some(1)) => Value::Bool(bits != 0),
+            _ => Value::Bit(bits),
+        }
+    }
+
+    /// Represents a temporal value in the [`TemporalMode`], after replacing an invalid date by
+    /// the [`ZeroDateMode`].
+    fn convert_temporal(&self, value: Value) -> Value {
+        let value = match value {
+            Value::Date { year, month, day } if !is_valid_date(year, month, day) => self.zero_date(
+                value,
+                Value::Date {
+                    year: 1970,
+                    month: 1,
+                    day: 1,
+                },
+            ),
+            Value::DateTime {
+                year, month, day, ..
+            } if !is_valid_date(year, month, day) => self.zero_date(
+                value,
+                Value::DateTime {
+                    year: 1970,
+                    month: 1,
+                    day: 1,
+                    hour: 0,
+                    minute: 0,
+                    second: 0,
+                    micros: 0,
+                },
+            ),
+            value => value,
+        };
+        match self.temporal_mode {
+            TemporalMode::Adaptive => value,
+            TemporalMode::Microseconds => match value {
+                Value::Date { year, month, day } => Value::Int(
+                    days_from_civil(year as i64, month as i64, day as i64) * 86_400_000_000,
+                ),
+                Value::Time {
+                    negative,
+                    hours,
+                    minutes,
+                    seconds,
+                    micros,
+                } => {
+                    let secs = hours as i64 * 3_600 + minutes as i64 * 60 + seconds as i64;
+                    let micros = secs * 1_000_000 + micros as i64;
+                    Value::Int(if negative { -micros } else { micros })
+                }
+                Value::DateTime {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                    second,
+                    micros,
+                } => Value::Int(datetime_micros(
+                    year, month, day, hour, minute, second, micros,
+                )),
+                Value::Timestamp(micros) => Value::Int(micros),
+                value => value,
+            },
+            TemporalMode::IsoString => match value {
+                Value::Date { year, month, day } => {
+                    Value::String(format!("{year:04}-{month:02}-{day:02}"))
+                }
+                Value::Time {
+                    negative,
+                    hours,
+                    minutes,
+                    seconds,
+                    micros,
+                } => Value::String(format!(
+                    "{}{hours:02}:{minutes:02}:{seconds:02}{}",
+                    if negative { "-" } else { "" },
+                    format_micros(micros)
+                )),
+                Value::DateTime {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                    second,
+                    micros,
+                } => Value::String(format!(
+                    "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}{}",
+                    format_micros(micros)
+                )),
+                Value::Timestamp(micros) => {
+                    let secs = micros.div_euclid(1_000_000);
+                    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+                    let secs_of_day = secs.rem_euclid(86_400);
+                    Value::String(format!(
+                        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{}Z",
+                        secs_of_day / 3_600,
+                        secs_of_day % 3_600 / 60,
+                        secs_of_day % 60,
+                        format_micros(micros.rem_euclid(1_000_000) as u32)
+                    ))
+                }
+                value => value,
+            },
+        }
+    }
+
+    /// Returns the value of a zero or invalid date `value`, where `epoch` is the epoch of its type.
+    fn zero_date(&self, value: Value, epoch: Value) -> Value {
+        match self.zero_date_mode {
+            ZeroDateMode::Preserve if self.temporal_mode != TemporalMode::Microseconds => value,
+            ZeroDateMode::Preserve | ZeroDateMode::Null => Value::Null,
+            ZeroDateMode::Epoch => epoch,
+        }
+    }
+
+    /// Decodes a string encoded in `charset`. The string is kept as bytes if it's a binary string,
+    /// or if it's not valid in its character set. A string of an unknown character set is decoded
+    /// as UTF-8.
+    pub(crate) fn decode_string(&self, bytes: &[u8], charset: Option<&str>) -> Value {
+        if charset == Some("binary") {
+            return self.binary(bytes);
+        }
+        let decoded = match charset {
+            Some(charset) => decode_charset(bytes, charset),
+            None => None,
+        };
+        match decoded {
+            Some(s) => Value::String(s),
+            None => match String::from_utf8(bytes.to_vec()) {
+                Ok(s) => Value::String(s),
+                Err(e) => Value::Bytes(e.into_bytes()),
+            },
+        }
+    }
+}
+
+/// Decodes a string encoded in the MySQL character set `charset`. Returns `None` if the character
+/// set is unknown or the string is not valid in it.
+fn decode_charset(bytes: &[u8], charset: &str) -> Option<String> {
+    let encoding: &Encoding = match charset {
+        "utf8mb4" | "utf8mb3" | "utf8" | "ascii" => {
+            return String::from_utf8(bytes.to_vec()).ok();
+        }
+        "utf32" => {
+            return bytes
+                .chunks(4)
+                .map(|c| char::from_u32(u32::from_be_bytes(c.try_into().ok()?)))
+                .collect();
+        }
+        // MySQL's latin1 is cp1252, and so is its latin5 cp1254.
+        "latin1" => encoding_rs::WINDOWS_1252,
+        "latin2" => encoding_rs::ISO_8859_2,
+        "latin5" => encoding_rs::WINDOWS_1254,
+        "latin7" => encoding_rs::ISO_8859_13,
+        "greek" => encoding_rs::ISO_8859_7,
+        "hebrew" => encoding_rs::ISO_8859_8,
+        "cp1250" => encoding_rs::WINDOWS_1250,
+        "cp1251" => encoding_rs::WINDOWS_1251,
+        "cp1256" => encoding_rs::WINDOWS_1256,
+        "cp1257" => encoding_rs::WINDOWS_1257,
+        "cp866" => encoding_rs::IBM866,
+        "koi8r" => encoding_rs::KOI8_R,
+        "koi8u" => encoding_rs::KOI8_U,
+        "macroman" => encoding_rs::MACINTOSH,
+        "gbk" | "gb2312" => encoding_rs::GBK,
+        "gb18030" => encoding_rs::GB18030,
+        "big5" => encoding_rs::BIG5,
+        "sjis" | "cp932" => encoding_rs::SHIFT_JIS,
+        "ujis" | "eucjpms" => encoding_rs::EUC_JP,
+        "euckr" => encoding_rs::EUC_KR,
+        "ucs2" | "utf16" => encoding_rs::UTF_16BE,
+        "utf16le" => encoding_rs::UTF_16LE,
+        _ => return None,
+    };
+    let (s, had_errors) = encoding.decode_without_bom_handling(bytes);
+    (!had_errors).then(|| s.into_owned())
+}
+
+/// Returns the microseconds since the epoch of a TIMESTAMP, which the binlog has as the epoch
+/// seconds, and the snapshot as the date and time in the `+00:00` session time zone. Returns
+/// `None` for the zero TIMESTAMP, which the binlog has as 0.
+fn timestamp_micros(value: &MysqlValue) -> Result<Option<i64>> {
+    let micros = match value {
+        MysqlValue::Int(secs) => secs * 1_000_000,
+        MysqlValue::Bytes(bytes) => {
+            let s = std::str::from_utf8(bytes)?;
+            let (secs, micros) = s.split_once('.').unwrap_or((s, "0"));
+            secs.parse::<i64>()? * 1_000_000 + micros.parse::<i64>()?
+        }
+        MysqlValue::Date(year, month, day, ..) if !is_valid_date(*year, *month, *day) => {
+            return Ok(None)
+        }
+        MysqlValue::Date(year, month, day, hour, minute, second, micros) => {
+            datetime_micros(*year, *month, *day, *hour, *minute, *second, *micros)
+        }
+        value => bail!("Invalid TIMESTAMP value {}", value.as_sql(true)),
+    };
+    // The earliest TIMESTAMP is `1970-01-01 00:00:01` UTC, so 0 is the zero TIMESTAMP.
+    Ok((micros != 0).then_some(micros))
+}
+
+/// Returns the microseconds since the epoch of a date and time in UTC.
+pub(crate) fn datetime_micros(
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    micros: u32,
+) -> i64 {
+    let days = days_from_civil(year as i64, month as i64, day as i64);
+    let secs = days * 86_400 + hour as i64 * 3_600 + minute as i64 * 60 + second as i64;
+    secs * 1_000_000 + micros as i64
+}
+
+/// Returns `false` for the zero date and the dates that don't exist, e.g. `2024-02-30`.
+fn is_valid_date(year: u16, month: u8, day: u8) -> bool {
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+/// Formats the fraction of a second, which is omitted if it's 0.
+pub(crate) fn format_micros(micros: u32) -> String {
+    if micros == 0 {
+        String::new()
+    } else {
+        format!(".{micros:06}")
+    }
+}
+
+/// Returns the date of the proleptic Gregorian calendar that is a number of days from 1970-01-01,
+/// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Returns the number of days from 1970-01-01 to a date of the proleptic Gregorian calendar.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -1,18 +1,24 @@
-use env_logger::Target;
-use indexmap::IndexMap;
-use log::info;
+use std::pin::Pin;
 use std::time::Duration;
 
 use anyhow::{bail, Result};
-use chgcap_mysql::{Event, EventData, Source, SourceConfigBuilder};
+use chgcap_mysql::{
+    BinaryMode, BinlogStream, BitMode, DecimalMode, Event, EventData, FileOffsetStore,
+    GeneratedColumnMode, Geometry, MysqlSchema, OffsetStore, RowChange, SchemaMismatch, Source,
+    SourceConfigBuilder, TemporalMode, Value, ZeroDateMode,
+};
+use chgcap_mysql_ddl_parser::ast::data_type::{CharacterLength, DataType};
 use chgcap_mysql_test_utils::mysql_container::Mysql;
+use env_logger::Target;
+use indexmap::IndexMap;
+use log::info;
 use mysql_async::prelude::Query;
 use mysql_async::{Conn, Pool};
 use serde::Deserialize;
 use testcontainers::clients::Cli;
 use testcontainers::Container;
 use tokio::time::sleep;
-use tokio_stream::StreamExt;
+use tokio_stream::{StreamExt, Timeout};
 
 lazy_static::lazy_static! {
     pub static ref DOCKER: Cli = Cli::default();
@@ -26,27 +32,70 @@ struct TableData {
     prepare: String,
     rows: String,
     ddls: Option<String>,
+
+    /// `precise`, `string` or `double`. A table with a decimal mode is captured by a source of its
+    /// own, and the rows of its snapshot follow the rows of the binlog.
+    decimal_mode: Option<String>,
 }
 
-/// This function behaves as a user of the chgcap. It consumes and collects all CDC events into a list.
-async fn consume_cdc_events() -> Result<Vec<Event>> {
-    let cfg = SourceConfigBuilder::default()
-        .hostname("0.0.0.0".into())
+fn parse_decimal_mode(mode: &str) -> Result<DecimalMode> {
+    Ok(match mode {
+        "precise" => DecimalMode::Precise,
+        "string" => DecimalMode::String,
+        "double" => DecimalMode::Double,
+        _ => bail!("Unknown decimal mode: {mode}"),
+    })
+}
+
+fn new_pool() -> Pool {
+    Pool::new(
+        format!(
+            "mysql://root@0.0.0.0:{}/mysql",
+            MYSQL_CONTAINER.get_host_port_ipv4(3306)
+        )
+        .as_str(),
+    )
+}
+
+async fn connect() -> Conn {
+    new_pool().get_conn().await.unwrap()
+}
+
+async fn drop_tables(conn: &mut Conn, tables: &[&str]) {
+    for table in tables {
+        format!("DROP TABLE {table}")
+            .ignore(&mut *conn)
+            .await
+            .unwrap();
+    }
+}
+
+/// The settings of a source of the test container, which replicates as the server 1.
+fn source_config() -> SourceConfigBuilder {
+    let mut cfg = SourceConfigBuilder::default();
+    cfg.hostname("0.0.0.0".into())
         .port(MYSQL_CONTAINER.get_host_port_ipv4(3306))
         .username("root".into())
         .database("mysql".into())
-        .server_id(1)
-        .build()
-        .unwrap();
+        .server_id(1);
+    cfg
+}
 
-    let source = Source::new(cfg).await.unwrap();
-    let cdc_stream = source
-        .cdc_stream()
-        .await
-        .unwrap()
-        .timeout(Duration::from_secs(1));
-    tokio::pin!(cdc_stream);
+/// A binlog stream which is idle after a second without events.
+type CdcStream = Pin<Box<Timeout<BinlogStream>>>;
+
+async fn start_cdc_stream(source: &Source) -> CdcStream {
+    Box::pin(
+        source
+            .cdc_stream()
+            .await
+            .unwrap()
+            .timeout(Duration::from_secs(1)),
+    )
+}
 
+/// Collects the events of `cdc_stream` until it's idle.
+async fn collect_events(cdc_stream: &mut CdcStream) -> Result<Vec<Event>> {
     let mut events: Vec<Event> = vec![];
     while let Ok(Some(c)) = cdc_stream.try_next().await {
         events.push(c?);
@@ -54,6 +103,44 @@ async fn consume_cdc_events() -> Result<Vec<Event>> {
     Ok(events)
 }
 
+async fn snapshot_events(source: &Source) -> Result<Vec<Event>> {
+    source.snapshot_stream().await?.collect().await
+}
+
+fn data_changes(events: &[Event]) -> Vec<&RowChange> {
+    events
+        .iter()
+        .flat_map(|e| match e.data() {
+            EventData::DataChange(changes) => changes.iter().collect(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+fn row_values(events: &[Event]) -> Vec<Vec<Value>> {
+    data_changes(events)
+        .iter()
+        .map(|ch| ch.row().values().to_vec())
+        .collect()
+}
+
+/// Collects the values of the binlog rows of `cdc_stream`, and then of the snapshot rows of
+/// `source`.
+async fn binlog_and_snapshot_values(
+    source: &Source,
+    cdc_stream: &mut CdcStream,
+) -> (Vec<Vec<Value>>, Vec<Vec<Value>>) {
+    let events = collect_events(cdc_stream).await.unwrap();
+    let snapshot = snapshot_events(source).await.unwrap();
+    (row_values(&events), row_values(&snapshot))
+}
+
+/// This function behaves as a user of the chgcap. It consumes and collects all CDC events into a list.
+async fn consume_cdc_events(cfg: SourceConfigBuilder) -> Result<Vec<Event>> {
+    let source = Source::new(cfg.build()?).await?;
+    collect_events(&mut start_cdc_stream(&source).await).await
+}
+
 /// The template structure of `single_table_cdc.yaml`.
 struct TestSuite {
     _pool: Pool,
@@ -66,13 +153,7 @@ struct TestSuite {
 
 impl TestSuite {
     async fn load(path: impl Into<String>) -> Self {
-        let pool = mysql_async::Pool::new(
-            format!(
-                "mysql://root@0.0.0.0:{}/mysql",
-                MYSQL_CONTAINER.get_host_port_ipv4(3306)
-            )
-            .as_str(),
-        );
+        let pool = new_pool();
         let conn = pool.get_conn().await.unwrap();
 
         let tables: IndexMap<String, TableData> =
@@ -94,36 +175,25 @@ impl TestSuite {
         }
         sleep(Duration::from_secs(1)).await;
 
-        let events = consume_cdc_events().await?;
+        let events = consume_cdc_events(source_config()).await?;
+        self.table_events = table_rows(&events);
 
-        // Tables may be created multiple times. We use the latest.
-        let table_ids: IndexMap<String, u64> = events
-            .iter()
-            .map(|e| (e.table_name().clone(), e.table_id()))
-            .collect();
-
-        let mut table_events = IndexMap::<u64, Vec<String>>::new();
-        for e in events.iter() {
-            let evs = table_events.entry(e.table_id()).or_default();
-            match e.data() {
-                EventData::DataChange(changes) => {
-                    evs.extend(changes.iter().map(|ch| format!("{ch}")));
-                }
-                EventData::SchemaChange(_) => {
-                    todo!()
-                }
-            }
+        for (name, t) in self.tables.iter() {
+            let Some(decimal_mode) = &t.decimal_mode else {
+                continue;
+            };
+            let cfg = source_config()
+                .table_list(vec![format!("mysql.{name}")])
+                .decimal_mode(parse_decimal_mode(decimal_mode)?)
+                .build()?;
+            let source = Source::new(cfg).await?;
+            let events = collect_events(&mut start_cdc_stream(&source).await).await?;
+            let mut rows = table_rows(&events).shift_remove(name).unwrap_or_default();
+            let snapshot = snapshot_events(&source).await?;
+            rows.extend(table_rows(&snapshot).shift_remove(name).unwrap_or_default());
+            self.table_events.insert(name.clone(), rows);
         }
 
-        self.table_events = table_ids
-            .iter()
-            .filter_map(|(table_name, table_id)| {
-                table_events
-                    .get(table_id)
-                    .map(|events| (table_name.clone(), events.clone()))
-            })
-            .collect();
-
         Ok(())
     }
 
@@ -144,8 +214,8 @@ impl TestSuite {
     async fn check(&mut self) {
         let r = self.check_inner().await;
         self.teardown().await;
-        if r.is_err() {
-            panic!("Test failed: {:?}", r.unwrap_err());
+        if let Err(e) = r {
+            panic!("Test failed: {:?}", e);
         }
     }
 
@@ -164,6 +234,9 @@ impl TestSuite {
                 table.insert("comment".into(), comment.clone().into());
             }
             table.insert("prepare".into(), table_data.prepare.clone().into());
+            if let Some(decimal_mode) = &table_data.decimal_mode {
+                table.insert("decimal_mode".into(), decimal_mode.clone().into());
+            }
             table.insert("rows".into(), events.join("\n").into());
 
             tables.insert(key.clone().into(), serde_yaml::Value::Mapping(table));
@@ -183,6 +256,41 @@ impl TestSuite {
     }
 }
 
+/// Collects the rows of the data changes by table.
+fn table_rows(events: &[Event]) -> IndexMap<String, Vec<String>> {
+    // Tables may be created multiple times. We use the latest.
+    let table_ids: IndexMap<String, u64> = events
+        .iter()
+        .map(|e| (e.table_name().clone(), e.table_id()))
+        .collect();
+
+    let mut table_events = IndexMap::<u64, Vec<String>>::new();
+    for e in events.iter() {
+        let evs = table_events.entry(e.table_id()).or_default();
+        match e.data() {
+            EventData::DataChange(changes) => {
+                evs.extend(changes.iter().map(|ch| format!("{ch}")));
+            }
+            EventData::SchemaChange(_) => {
+                todo!()
+            }
+            EventData::SnapshotStarted(_)
+            | EventData::TableSnapshotStarted
+            | EventData::TableSnapshotCompleted(_)
+            | EventData::SnapshotCompleted(_) => {}
+        }
+    }
+
+    table_ids
+        .iter()
+        .filter_map(|(table_name, table_id)| {
+            table_events
+                .get(table_id)
+                .map(|events| (table_name.clone(), events.clone()))
+        })
+        .collect()
+}
+
 fn check_cdc_rows_eq(expected: &str, actual: &[String]) -> anyhow::Result<()> {
     let expected = expected.trim().to_string();
     let actual = actual.join("\n").trim().to_string();
@@ -205,3 +313,1122 @@ async fn fix_single_table_cdc() {
     t.fix().await;
     t.teardown().await;
 }
+
+/// Runs the `prepare` queries and collects the events of the snapshot of `mysql.{table}`.
+async fn collect_snapshot_events(
+    table: &str,
+    prepare: &str,
+    mut cfg: SourceConfigBuilder,
+) -> Result<Vec<Event>> {
+    let mut conn = connect().await;
+    prepare.ignore(&mut conn).await?;
+
+    let cfg = cfg.table_list(vec![format!("mysql.{table}")]).build()?;
+    let source = Source::new(cfg).await?;
+    let events = snapshot_events(&source).await;
+
+    drop_tables(&mut conn, &[table]).await;
+    events
+}
+
+/// Runs the `prepare` queries and collects the rows of the snapshot of `mysql.{table}`.
+async fn collect_snapshot_rows(
+    table: &str,
+    prepare: &str,
+    cfg: SourceConfigBuilder,
+) -> Result<Vec<String>> {
+    let events = collect_snapshot_events(table, prepare, cfg).await?;
+    let (last, events) = events.split_last().unwrap();
+    assert!(matches!(last.data(), EventData::SnapshotCompleted(_)));
+    Ok(data_changes(events)
+        .iter()
+        .map(|ch| ch.to_string())
+        .collect())
+}
+
+#[tokio::test]
+async fn test_snapshot_only() {
+    let rows = collect_snapshot_rows(
+        "snapshot_only_test",
+        "CREATE TABLE snapshot_only_test (id INT PRIMARY KEY, name VARCHAR(20));
+        INSERT INTO snapshot_only_test VALUES (1, 'a'), (2, 'b');",
+        source_config(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            "Read(INT(1),VAR_STRING('a'))",
+            "Read(INT(2),VAR_STRING('b'))"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_markers() {
+    let events = collect_snapshot_events(
+        "snapshot_markers_test",
+        "CREATE TABLE snapshot_markers_test (id INT PRIMARY KEY, name VARCHAR(20));
+        INSERT INTO snapshot_markers_test VALUES (1, 'a'), (2, 'b');",
+        source_config().split_size(1).clone(),
+    )
+    .await
+    .unwrap();
+    let markers: Vec<String> = events
+        .iter()
+        .map(|e| match e.data() {
+            EventData::SnapshotStarted(_) => "SnapshotStarted".to_string(),
+            EventData::TableSnapshotStarted => format!("TableSnapshotStarted({})", e.table_name()),
+            EventData::DataChange(changes) => changes
+                .iter()
+                .map(|ch| match ch {
+                    RowChange::Read { last, .. } => format!("{ch} last={last}"),
+                    _ => format!("{ch}"),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            EventData::TableSnapshotCompleted(rows) => {
+                format!("TableSnapshotCompleted({}, {rows})", e.table_name())
+            }
+            EventData::SnapshotCompleted(_) => "SnapshotCompleted".to_string(),
+            EventData::SchemaChange(_) => unreachable!(),
+        })
+        .collect();
+    assert_eq!(
+        markers,
+        vec![
+            "SnapshotStarted",
+            "TableSnapshotStarted(snapshot_markers_test)",
+            "Read(INT(1),VAR_STRING('a')) last=false",
+            "Read(INT(2),VAR_STRING('b')) last=true",
+            "TableSnapshotCompleted(snapshot_markers_test, 2)",
+            "SnapshotCompleted"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_row_filter_and_projection() {
+    let table = "mysql.snapshot_filter_test".to_string();
+    let rows = collect_snapshot_rows(
+        "snapshot_filter_test",
+        "CREATE TABLE snapshot_filter_test (id INT PRIMARY KEY, tenant_id INT, payload BLOB);
+        INSERT INTO snapshot_filter_test VALUES (1, 42, 'x'), (2, 7, 'y'), (3, 42, NULL);",
+        source_config()
+            .row_filters([(table.clone(), "tenant_id = 42".to_string())].into())
+            .column_projections([(table, vec!["id".to_string(), "tenant_id".to_string()])].into())
+            .clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows, vec!["Read(INT(1),INT(42))", "Read(INT(3),INT(42))"]);
+}
+
+#[tokio::test]
+async fn test_incremental_snapshot() {
+    let mut conn = connect().await;
+    "CREATE TABLE chgcap_signal (id VARCHAR(64) PRIMARY KEY, type VARCHAR(32) NOT NULL, data VARCHAR(2048) NULL);
+    CREATE TABLE incremental_snapshot_test (id INT PRIMARY KEY, name VARCHAR(20));
+    INSERT INTO incremental_snapshot_test VALUES (1, 'a'), (2, 'b');
+    INSERT INTO chgcap_signal VALUES ('1', 'execute-snapshot', '{\"data-collections\": [\"mysql.incremental_snapshot_test\"]}');"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let events = consume_cdc_events(
+        source_config()
+            .signal_table(Some("mysql.chgcap_signal".into()))
+            .split_size(1)
+            .clone(),
+    )
+    .await;
+
+    drop_tables(&mut conn, &["chgcap_signal", "incremental_snapshot_test"]).await;
+
+    let rows: Vec<String> = events
+        .unwrap()
+        .iter()
+        .filter(|e| e.table_name() == "incremental_snapshot_test")
+        .flat_map(|e| match e.data() {
+            EventData::DataChange(changes) => changes
+                .iter()
+                .map(|ch| match ch {
+                    RowChange::Read { last, .. } => format!("{ch} last={last}"),
+                    _ => format!("{ch}"),
+                })
+                .collect(),
+            _ => vec![],
+        })
+        .collect();
+    // The table has a multiple of `split_size` rows, whose last one is still flagged.
+    assert_eq!(
+        rows,
+        vec![
+            "Insert(INT(1),VARCHAR('a'))",
+            "Insert(INT(2),VARCHAR('b'))",
+            "Read(INT(1),VAR_STRING('a')) last=false",
+            "Read(INT(2),VAR_STRING('b')) last=true"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_scan_newly_added_table() {
+    let mut conn = connect().await;
+    "CREATE TABLE scan_new_table_src (id INT PRIMARY KEY, name VARCHAR(20));
+    INSERT INTO scan_new_table_src VALUES (1, 'a');"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.scan_new_table_dst".into()])
+        .scan_newly_added_table_enabled(true)
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+
+    // The renamed table is snapshotted once the stream reaches the rename, which is after the
+    // insert, so the inserted row is both read and emitted from the binlog.
+    "RENAME TABLE scan_new_table_src TO scan_new_table_dst;
+    INSERT INTO scan_new_table_dst VALUES (2, 'b');"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+
+    drop_tables(&mut conn, &["scan_new_table_dst"]).await;
+
+    let rows: Vec<String> = data_changes(&events)
+        .iter()
+        .map(|ch| ch.to_string())
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            "Read(INT(1),VAR_STRING('a'))",
+            "Read(INT(2),VAR_STRING('b'))",
+            "Insert(INT(2),VARCHAR('b'))"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_ack_commits_contiguous_offsets() {
+    let mut conn = connect().await;
+    "CREATE TABLE ack_test (id INT PRIMARY KEY);
+    INSERT INTO ack_test VALUES (1);
+    INSERT INTO ack_test VALUES (2);
+    INSERT INTO ack_test VALUES (3);"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("chgcap-ack-test-{}.offsets", std::process::id()));
+    let cfg = source_config()
+        .table_list(vec!["mysql.ack_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg)
+        .await
+        .unwrap()
+        .with_offset_store(FileOffsetStore::open(&path, 1).unwrap());
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+
+    drop_tables(&mut conn, &["ack_test"]).await;
+
+    assert_eq!(events.len(), 3);
+    let committed = || FileOffsetStore::open(&path, 1).unwrap().load().unwrap();
+    // The first event is not acknowledged, so nothing is committed.
+    events[2].ack_handle().unwrap().ack().unwrap();
+    events[1].ack_handle().unwrap().ack().unwrap();
+    assert_eq!(committed(), None);
+
+    events[0].ack_handle().unwrap().ack().unwrap();
+    assert_eq!(committed().as_ref(), Some(events[2].offset()));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_table_schema() {
+    let pool = new_pool();
+    let mut conn = pool.get_conn().await.unwrap();
+    "CREATE TABLE table_schema_test (
+        id INT NOT NULL,
+        name VARCHAR(20) DEFAULT 'none',
+        created_at TIMESTAMP NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (id)
+    )"
+    .ignore(&mut conn)
+    .await
+    .unwrap();
+
+    let mut schema = MysqlSchema::new(pool.clone());
+    let table = schema
+        .get_table_schema("mysql", "table_schema_test")
+        .await
+        .unwrap()
+        .clone();
+    drop_tables(&mut conn, &["table_schema_test"]).await;
+    // The definition is cached.
+    assert_eq!(
+        schema
+            .get_table_schema("mysql", "table_schema_test")
+            .await
+            .unwrap(),
+        &table
+    );
+
+    assert_eq!(table.table_name, "table_schema_test");
+    assert_eq!(table.primary_keys, vec!["id"]);
+    assert_eq!(table.column_names(), vec!["id", "name", "created_at"]);
+    let id = table.column("id").unwrap();
+    assert_eq!(id.data_type, DataType::Int(None));
+    assert!(!id.nullable);
+    assert_eq!(id.default, None);
+    let name = table.column("name").unwrap();
+    assert_eq!(
+        name.data_type,
+        DataType::Varchar(Some(CharacterLength::IntegerLength {
+            length: 20,
+            unit: None
+        }))
+    );
+    assert!(name.nullable);
+    assert_eq!(name.default.as_deref(), Some("none"));
+    let created_at = table.column("created_at").unwrap();
+    assert!(created_at.nullable);
+    assert_eq!(created_at.default.as_deref(), Some("CURRENT_TIMESTAMP"));
+
+    schema.invalidate("mysql.table_schema_test");
+    assert!(schema
+        .get_table_schema("mysql", "table_schema_test")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_schema_change_events() {
+    let mut conn = connect().await;
+    "CREATE TABLE schema_change_test (id INT PRIMARY KEY)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.schema_change_test".into()])
+        .include_schema_changes(true)
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+
+    // The stream catches up before the table is dropped, from which the altered definition is
+    // loaded.
+    "ALTER TABLE schema_change_test ADD COLUMN name VARCHAR(20)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let mut events = collect_events(&mut cdc_stream).await.unwrap();
+    drop_tables(&mut conn, &["schema_change_test"]).await;
+    events.extend(collect_events(&mut cdc_stream).await.unwrap());
+
+    let changes: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e.data() {
+            EventData::SchemaChange(change) => Some(change),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(changes.len(), 2);
+    let table = "mysql.schema_change_test".to_string();
+
+    let alter = changes[0];
+    assert!(alter.ddl().starts_with("ALTER TABLE"));
+    assert_eq!(alter.tables(), &vec![table.clone()]);
+    let before = alter.before()[&table].as_ref().unwrap();
+    assert_eq!(before.column_names(), vec!["id"]);
+    let after = alter.after()[&table].as_ref().unwrap();
+    assert_eq!(after.column_names(), vec!["id", "name"]);
+
+    let drop = changes[1];
+    assert!(drop.ddl().starts_with("DROP TABLE"));
+    assert_eq!(drop.before()[&table].as_ref(), Some(after));
+    assert_eq!(drop.after()[&table], None);
+}
+
+#[tokio::test]
+async fn test_instant_ddl_schema_mismatch() {
+    let mut conn = connect().await;
+    "CREATE TABLE instant_ddl_test (id INT PRIMARY KEY, a INT)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!(
+        "chgcap-instant-ddl-test-{}.offsets",
+        std::process::id()
+    ));
+    let cfg = source_config()
+        .table_list(vec!["mysql.instant_ddl_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg.clone())
+        .await
+        .unwrap()
+        .with_offset_store(FileOffsetStore::open(&path, 1).unwrap());
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    "INSERT INTO instant_ddl_test VALUES (1, 10);
+    INSERT INTO instant_ddl_test VALUES (2, 20);"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+    assert_eq!(events.len(), 2);
+    // Only the first insert is committed, so the second one is read again after the restart.
+    events[0].ack_handle().unwrap().ack().unwrap();
+
+    // Without a schema history, the restarted stream only knows the columns after the DDL.
+    "ALTER TABLE instant_ddl_test ADD COLUMN b INT, ALGORITHM=INSTANT"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let source = Source::new(cfg)
+        .await
+        .unwrap()
+        .with_offset_store(FileOffsetStore::open(&path, 1).unwrap());
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    let mut error = None;
+    while let Ok(Some(c)) = cdc_stream.try_next().await {
+        if let Err(e) = c {
+            error = Some(e);
+            break;
+        }
+    }
+    drop_tables(&mut conn, &["instant_ddl_test"]).await;
+    std::fs::remove_file(path).unwrap();
+
+    let error = error.expect("The restarted stream should fail");
+    let mismatch = error
+        .downcast_ref::<SchemaMismatch>()
+        .unwrap_or_else(|| panic!("Unexpected error {error}"));
+    assert_eq!(mismatch.table, "mysql.instant_ddl_test");
+    assert_eq!(mismatch.offset.filename(), events[1].offset().filename());
+    assert_eq!(mismatch.binlog_columns, 2);
+    assert_eq!(mismatch.schema_columns, 3);
+    assert_eq!(mismatch.column, None);
+}
+
+#[tokio::test]
+async fn test_row_column_names() {
+    let mut conn = connect().await;
+    "CREATE TABLE column_names_test (id INT PRIMARY KEY, name VARCHAR(20))"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.column_names_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+
+    // The columns are reordered after the first insert.
+    "INSERT INTO column_names_test VALUES (1, 'a');
+    ALTER TABLE column_names_test MODIFY COLUMN name VARCHAR(20) FIRST;
+    INSERT INTO column_names_test VALUES ('b', 2);"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+    drop_tables(&mut conn, &["column_names_test"]).await;
+
+    let changes = data_changes(&events);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].column_names(), vec!["id", "name"]);
+    assert_eq!(changes[1].column_names(), vec!["name", "id"]);
+    for (change, id) in changes.iter().zip([1, 2]) {
+        assert_eq!(change.get_by_name("ID"), Some(&Value::Int(id)));
+    }
+    assert!(changes[0].get_by_name("missing").is_none());
+}
+
+#[tokio::test]
+async fn test_row_keys() {
+    let mut conn = connect().await;
+    "CREATE TABLE row_keys_test (
+        id INT,
+        tenant INT,
+        name VARCHAR(20),
+        PRIMARY KEY (tenant, id)
+    );
+    CREATE TABLE row_keys_no_pk_test (code VARCHAR(10), amount INT);
+    INSERT INTO row_keys_test VALUES (1, 7, 'a');"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec![
+            "mysql.row_keys_test".into(),
+            "mysql.row_keys_no_pk_test".into(),
+        ])
+        .message_key_columns([("mysql.row_keys_no_pk_test".into(), vec!["code".into()])].into())
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let snapshot = snapshot_events(&source).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    "UPDATE row_keys_test SET name = 'b' WHERE id = 1;
+    UPDATE row_keys_test SET id = 2 WHERE id = 1;
+    INSERT INTO row_keys_no_pk_test VALUES ('x', 3);"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+    drop_tables(&mut conn, &["row_keys_test", "row_keys_no_pk_test"]).await;
+
+    let keys = |events: &[Event]| -> Vec<(Vec<String>, Vec<Value>)> {
+        data_changes(events)
+            .iter()
+            .map(|ch| {
+                let key = ch.key().unwrap();
+                assert!(ch.value() == ch.row());
+                (key.column_names(), key.values().to_vec())
+            })
+            .collect()
+    };
+    let key = |tenant, id| {
+        (
+            vec!["tenant".to_string(), "id".to_string()],
+            vec![Value::Int(tenant), Value::Int(id)],
+        )
+    };
+    assert_eq!(keys(&snapshot), vec![key(7, 1)]);
+
+    let events: Vec<Event> = events
+        .into_iter()
+        .filter(|e| matches!(e.data(), EventData::DataChange(_)))
+        .collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(keys(&events[..1]), vec![key(7, 1), key(7, 1)]);
+    assert!(!events[0].primary_key_changed());
+    assert_eq!(keys(&events[1..2]), vec![key(7, 1), key(7, 2)]);
+    assert!(events[1].primary_key_changed());
+    assert_eq!(
+        keys(&events[2..]),
+        vec![(vec!["code".to_string()], vec![Value::String("x".into())])]
+    );
+    assert!(!events[2].primary_key_changed());
+}
+
+#[tokio::test]
+async fn test_changed_columns() {
+    let mut conn = connect().await;
+    "CREATE TABLE changed_columns_test (
+        id INT PRIMARY KEY,
+        n INT,
+        s VARCHAR(20),
+        j JSON
+    );
+    INSERT INTO changed_columns_test VALUES (1, 1, NULL, '{\"a\": 1, \"b\": [1, 2]}')"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let new_source = |skip_noop_updates, server_id| {
+        let mut cfg = source_config();
+        cfg.table_list(vec!["mysql.changed_columns_test".into()])
+            .skip_noop_updates(skip_noop_updates)
+            .server_id(server_id);
+        if skip_noop_updates {
+            cfg.column_projections(
+                [(
+                    "mysql.changed_columns_test".into(),
+                    vec!["id".into(), "s".into(), "j".into()],
+                )]
+                .into(),
+            );
+        }
+        Source::new(cfg.build().unwrap())
+    };
+    let all_source = new_source(false, 1).await.unwrap();
+    let skip_source = new_source(true, 2).await.unwrap();
+    let mut all_stream = start_cdc_stream(&all_source).await;
+    let mut skip_stream = start_cdc_stream(&skip_source).await;
+    // The JSON documents are updated in place, so that only their diffs are logged.
+    "SET SESSION binlog_row_value_options = 'PARTIAL_JSON';
+    UPDATE changed_columns_test SET n = 2;
+    UPDATE changed_columns_test SET n = 3, s = NULL;
+    UPDATE changed_columns_test SET j = JSON_SET(j, '$.a', 2);
+    UPDATE changed_columns_test SET j = JSON_REPLACE(j, '$.b[1]', 3);
+    UPDATE changed_columns_test SET j = JSON_REMOVE(j, '$.a');
+    UPDATE changed_columns_test SET s = 'x'"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let all_events = collect_events(&mut all_stream).await.unwrap();
+    let skip_events = collect_events(&mut skip_stream).await.unwrap();
+    drop_tables(&mut conn, &["changed_columns_test"]).await;
+
+    // The changed columns of the updates, and the documents after them.
+    let updates = |events: &[Event]| -> Vec<(Vec<String>, Option<Value>)> {
+        data_changes(events)
+            .into_iter()
+            .filter_map(|ch| match ch {
+                RowChange::Insert(row) => {
+                    let changed_columns = ch.changed_columns().unwrap();
+                    Some((changed_columns, row.get_by_name("j").cloned()))
+                }
+                RowChange::Delete(_) => {
+                    assert!(ch.changed_columns().is_some());
+                    None
+                }
+                RowChange::Read { .. } => None,
+            })
+            .collect()
+    };
+    let changed = |columns: &[&str], doc| {
+        (
+            columns.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            Some(Value::Json(doc)),
+        )
+    };
+    let all_updates = updates(&all_events);
+    assert_eq!(
+        all_updates,
+        vec![
+            changed(&["n"], serde_json::json!({"a": 1, "b": [1, 2]})),
+            changed(&["n"], serde_json::json!({"a": 1, "b": [1, 2]})),
+            changed(&["j"], serde_json::json!({"a": 2, "b": [1, 2]})),
+            changed(&["j"], serde_json::json!({"a": 2, "b": [1, 3]})),
+            changed(&["j"], serde_json::json!({"b": [1, 3]})),
+            changed(&["s"], serde_json::json!({"b": [1, 3]})),
+        ]
+    );
+    // The updates of only the projected-out column are dropped.
+    assert_eq!(updates(&skip_events), all_updates[2..].to_vec());
+}
+
+#[tokio::test]
+async fn test_serde_round_trip() {
+    let mut conn = connect().await;
+    "CREATE TABLE serde_round_trip_test (
+        id INT PRIMARY KEY,
+        amount DECIMAL(10, 3),
+        name VARCHAR(20),
+        data BLOB,
+        created_at DATETIME(6),
+        doc JSON,
+        shape GEOMETRY,
+        state ENUM('a', 'b'),
+        flags BIT(4)
+    );
+    INSERT INTO serde_round_trip_test VALUES
+        (1, -12.34, 'héllo', x'00ff', '2024-01-02 03:04:05.000006', '{\"a\": [1, 2.5, null]}',
+            ST_GeomFromText('POINT(1 2)'), 'b', b'1010'),
+        (2, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.serde_round_trip_test".into()])
+        .include_schema_changes(true)
+        .build()
+        .unwrap();
+    // The binlog events have ack handles, which are neither serialized nor compared.
+    let path =
+        std::env::temp_dir().join(format!("chgcap-serde-test-{}.offsets", std::process::id()));
+    let source = Source::new(cfg)
+        .await
+        .unwrap()
+        .with_offset_store(FileOffsetStore::open(&path, 1).unwrap());
+    let mut events: Vec<Event> = snapshot_events(&source).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    "SET SESSION binlog_row_value_options = 'PARTIAL_JSON';
+    UPDATE serde_round_trip_test SET doc = JSON_SET(doc, '$.a[0]', 3) WHERE id = 1;
+    UPDATE serde_round_trip_test SET name = 'x', amount = 1.5 WHERE id = 2;
+    ALTER TABLE serde_round_trip_test ADD COLUMN note TEXT;
+    DELETE FROM serde_round_trip_test"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    events.extend(collect_events(&mut cdc_stream).await.unwrap());
+    drop_tables(&mut conn, &["serde_round_trip_test"]).await;
+    std::fs::remove_file(path).unwrap();
+
+    assert!(events
+        .iter()
+        .any(|e| matches!(e.data(), EventData::SchemaChange(_))));
+    assert!(events.iter().any(|e| e.ack_handle().is_some()));
+    for event in &events {
+        let json = serde_json::to_string(event).unwrap();
+        let deserialized: Event = serde_json::from_str(&json).unwrap();
+        assert!(deserialized.ack_handle().is_none());
+        assert!(deserialized == *event, "{json}");
+        if let (EventData::DataChange(changes), EventData::DataChange(deserialized)) =
+            (event.data(), deserialized.data())
+        {
+            for (change, deserialized) in changes.iter().zip(deserialized) {
+                assert_eq!(change.to_string(), deserialized.to_string());
+                assert_eq!(change.changed_columns(), deserialized.changed_columns());
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_row_values() {
+    let mut conn = connect().await;
+    "CREATE TABLE row_values_test (
+        id BIGINT UNSIGNED PRIMARY KEY,
+        price DECIMAL(10, 3),
+        born DATE,
+        elapsed TIME(3),
+        updated_at DATETIME(6),
+        created_at TIMESTAMP,
+        name VARCHAR(20),
+        data VARBINARY(4),
+        doc JSON,
+        flags BIT(4)
+    )"
+    .ignore(&mut conn)
+    .await
+    .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.row_values_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    "INSERT INTO row_values_test VALUES (1, -12.5, '2024-02-29', '-100:00:01.5',
+        '2024-01-02 03:04:05.000006', '2024-01-02 03:04:05', 'a', x'0001', '{\"a\": [1]}', b'101')"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    // The snapshot row and the binlog row are decoded to the same values.
+    let (rows, snapshot_rows) = binlog_and_snapshot_values(&source, &mut cdc_stream).await;
+    drop_tables(&mut conn, &["row_values_test"]).await;
+
+    let expected = [
+        Value::UInt(1),
+        Value::Decimal("-12.500".parse().unwrap()),
+        Value::Date {
+            year: 2024,
+            month: 2,
+            day: 29,
+        },
+        Value::Time {
+            negative: true,
+            hours: 100,
+            minutes: 0,
+            seconds: 1,
+            micros: 500_000,
+        },
+        Value::DateTime {
+            year: 2024,
+            month: 1,
+            day: 2,
+            hour: 3,
+            minute: 4,
+            second: 5,
+            micros: 6,
+        },
+        // The server runs in UTC.
+        Value::Timestamp(1_704_164_645_000_000),
+        Value::String("a".into()),
+        Value::Bytes(vec![0, 1]),
+        Value::Json(serde_json::json!({"a": [1]})),
+        Value::Bit(0b101),
+    ];
+    assert_eq!(rows, vec![expected.to_vec()]);
+    assert_eq!(snapshot_rows, rows);
+}
+
+#[tokio::test]
+async fn test_row_charsets() {
+    let mut conn = connect().await;
+    "CREATE TABLE row_charsets_test (
+        id INT PRIMARY KEY,
+        latin VARCHAR(20),
+        chinese VARCHAR(20) CHARACTER SET gbk,
+        utf TEXT CHARACTER SET utf8mb4,
+        bin_collated VARCHAR(20) COLLATE latin1_bin,
+        data BLOB
+    ) DEFAULT CHARSET=latin1"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.row_charsets_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    "INSERT INTO row_charsets_test VALUES (1, 'café', '中文', '😀', 'Ünï', x'e9')"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let (rows, snapshot_rows) = binlog_and_snapshot_values(&source, &mut cdc_stream).await;
+    drop_tables(&mut conn, &["row_charsets_test"]).await;
+
+    let expected = [
+        Value::Int(1),
+        Value::String("café".into()),
+        Value::String("中文".into()),
+        Value::String("😀".into()),
+        Value::String("Ünï".into()),
+        Value::Bytes(vec![0xe9]),
+    ];
+    assert_eq!(rows, vec![expected.to_vec()]);
+    assert_eq!(snapshot_rows, rows);
+}
+
+#[tokio::test]
+async fn test_temporal_modes() {
+    let mut conn = connect().await;
+    // Allows the zero dates.
+    "SET SESSION sql_mode = ''".ignore(&mut conn).await.unwrap();
+    "CREATE TABLE temporal_modes_test (
+        id INT PRIMARY KEY,
+        born DATE,
+        updated_at DATETIME(6),
+        elapsed TIME(6),
+        created_at TIMESTAMP(6) NULL
+    )"
+    .ignore(&mut conn)
+    .await
+    .unwrap();
+
+    let new_source = |temporal_mode, zero_date_mode, server_timezone: &str, server_id| {
+        let cfg = source_config()
+            .table_list(vec!["mysql.temporal_modes_test".into()])
+            .temporal_mode(temporal_mode)
+            .zero_date_mode(zero_date_mode)
+            .server_timezone(server_timezone.into())
+            .server_id(server_id)
+            .build()
+            .unwrap();
+        Source::new(cfg)
+    };
+    let iso_source = new_source(
+        TemporalMode::IsoString,
+        ZeroDateMode::Epoch,
+        "America/Los_Angeles",
+        1,
+    )
+    .await
+    .unwrap();
+    let micros_source = new_source(TemporalMode::Microseconds, ZeroDateMode::Null, "", 2)
+        .await
+        .unwrap();
+    let mut iso_stream = start_cdc_stream(&iso_source).await;
+    let mut micros_stream = start_cdc_stream(&micros_source).await;
+    "INSERT INTO temporal_modes_test VALUES
+        (1, '2024-02-29', '2024-01-02 03:04:05.000006', '-100:00:01.5', '2024-01-02 03:04:05.5'),
+        (2, '0000-00-00', '0000-00-00 00:00:00', '200:00:00', '0000-00-00 00:00:00'),
+        (3, NULL, NULL, '838:59:59', NULL),
+        (4, NULL, NULL, '-838:59:59', NULL)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    // The snapshot rows and the binlog rows are decoded to the same values.
+    let (iso_rows, iso_snapshot_rows) =
+        binlog_and_snapshot_values(&iso_source, &mut iso_stream).await;
+    let (micros_rows, micros_snapshot_rows) =
+        binlog_and_snapshot_values(&micros_source, &mut micros_stream).await;
+    drop_tables(&mut conn, &["temporal_modes_test"]).await;
+
+    let iso_expected = vec![
+        vec![
+            Value::Int(1),
+            Value::String("2024-02-29".into()),
+            Value::String("2024-01-02T03:04:05.000006".into()),
+            Value::String("-100:00:01.500000".into()),
+            // The TIMESTAMP is in UTC whatever the server time zone.
+            Value::String("2024-01-02T03:04:05.500000Z".into()),
+        ],
+        vec![
+            Value::Int(2),
+            Value::String("1970-01-01".into()),
+            Value::String("1970-01-01T00:00:00".into()),
+            Value::String("200:00:00".into()),
+            Value::String("1970-01-01T00:00:00Z".into()),
+        ],
+        // The hours over 255 are kept.
+        vec![
+            Value::Int(3),
+            Value::Null,
+            Value::Null,
+            Value::String("838:59:59".into()),
+            Value::Null,
+        ],
+        vec![
+            Value::Int(4),
+            Value::Null,
+            Value::Null,
+            Value::String("-838:59:59".into()),
+            Value::Null,
+        ],
+    ];
+    assert_eq!(iso_rows, iso_expected);
+    assert_eq!(iso_snapshot_rows, iso_expected);
+    let micros_expected = vec![
+        vec![
+            Value::Int(1),
+            Value::Int(1_709_164_800_000_000),
+            Value::Int(1_704_164_645_000_006),
+            Value::Int(-360_001_500_000),
+            Value::Int(1_704_164_645_500_000),
+        ],
+        vec![
+            Value::Int(2),
+            Value::Null,
+            Value::Null,
+            Value::Int(720_000_000_000),
+            Value::Null,
+        ],
+        vec![
+            Value::Int(3),
+            Value::Null,
+            Value::Null,
+            Value::Int(3_020_399_000_000),
+            Value::Null,
+        ],
+        vec![
+            Value::Int(4),
+            Value::Null,
+            Value::Null,
+            Value::Int(-3_020_399_000_000),
+            Value::Null,
+        ],
+    ];
+    assert_eq!(micros_rows, micros_expected);
+    assert_eq!(micros_snapshot_rows, micros_expected);
+}
+
+#[tokio::test]
+async fn test_binary_modes() {
+    let mut conn = connect().await;
+    "CREATE TABLE binary_modes_test (
+        id INT PRIMARY KEY,
+        flag BIT(1),
+        mask BIT(10),
+        bin BINARY(3),
+        varbin VARBINARY(8),
+        blb BLOB
+    )"
+    .ignore(&mut conn)
+    .await
+    .unwrap();
+
+    let modes = [
+        (BinaryMode::Bytes, BitMode::Bitmask, 1),
+        (BinaryMode::Hex, BitMode::Boolean, 2),
+        (BinaryMode::Base64, BitMode::Binary, 3),
+    ];
+    let mut sources = vec![];
+    for (binary_mode, bit_mode, server_id) in modes {
+        let cfg = source_config()
+            .table_list(vec!["mysql.binary_modes_test".into()])
+            .binary_mode(binary_mode)
+            .bit_mode(bit_mode)
+            .server_id(server_id)
+            .build()
+            .unwrap();
+        let source = Source::new(cfg).await.unwrap();
+        let cdc_stream = start_cdc_stream(&source).await;
+        sources.push((source, cdc_stream));
+    }
+    "INSERT INTO binary_modes_test VALUES (1, b'1', b'1000000011', 'ab', x'00ff10', 'hello')"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let mut rows = vec![];
+    for (source, cdc_stream) in &mut sources {
+        // The snapshot rows and the binlog rows are decoded to the same values.
+        let (mut mode_rows, snapshot_rows) = binlog_and_snapshot_values(source, cdc_stream).await;
+        assert_eq!(mode_rows.len(), 1);
+        assert_eq!(snapshot_rows, mode_rows);
+        rows.push(mode_rows.remove(0));
+    }
+    drop_tables(&mut conn, &["binary_modes_test"]).await;
+
+    assert_eq!(
+        rows[0][1..],
+        [
+            Value::Bit(1),
+            Value::Bit(0b1000000011),
+            Value::Bytes(b"ab\0".to_vec()),
+            Value::Bytes(vec![0x00, 0xff, 0x10]),
+            Value::Bytes(b"hello".to_vec()),
+        ]
+    );
+    assert_eq!(
+        rows[1][1..],
+        [
+            Value::Bool(true),
+            Value::Bit(0b1000000011),
+            Value::String("616200".into()),
+            Value::String("00ff10".into()),
+            Value::String("68656c6c6f".into()),
+        ]
+    );
+    assert_eq!(
+        rows[2][1..],
+        [
+            Value::String("AQ==".into()),
+            Value::String("AgM=".into()),
+            Value::String("YWIA".into()),
+            Value::String("AP8Q".into()),
+            Value::String("aGVsbG8=".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_generated_and_invisible_columns() {
+    let mut conn = connect().await;
+    "CREATE TABLE generated_columns_test (
+        id INT PRIMARY KEY,
+        a INT,
+        v INT AS (a * 2) VIRTUAL,
+        s INT AS (a + 1) STORED,
+        h INT INVISIBLE
+    )"
+    .ignore(&mut conn)
+    .await
+    .unwrap();
+
+    let modes = [
+        (GeneratedColumnMode::AsLogged, 1),
+        (GeneratedColumnMode::Omit, 2),
+    ];
+    let mut sources = vec![];
+    for (generated_column_mode, server_id) in modes {
+        let cfg = source_config()
+            .table_list(vec!["mysql.generated_columns_test".into()])
+            .generated_column_mode(generated_column_mode)
+            .server_id(server_id)
+            .build()
+            .unwrap();
+        let source = Source::new(cfg).await.unwrap();
+        let cdc_stream = start_cdc_stream(&source).await;
+        sources.push((source, cdc_stream));
+    }
+    "INSERT INTO generated_columns_test (id, a, h) VALUES (1, 10, 7)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let mut rows = vec![];
+    for (source, cdc_stream) in &mut sources {
+        // The snapshot has the invisible column as the binlog does.
+        let (mut mode_rows, snapshot_rows) = binlog_and_snapshot_values(source, cdc_stream).await;
+        assert_eq!(mode_rows.len(), 1);
+        assert_eq!(snapshot_rows, mode_rows);
+        rows.push(mode_rows.remove(0));
+    }
+    drop_tables(&mut conn, &["generated_columns_test"]).await;
+
+    assert_eq!(
+        rows[0],
+        [
+            Value::Int(1),
+            Value::Int(10),
+            Value::Int(20),
+            Value::Int(11),
+            Value::Int(7)
+        ]
+    );
+    assert_eq!(
+        rows[1],
+        [Value::Int(1), Value::Int(10), Value::Int(11), Value::Int(7)]
+    );
+}
+
+#[tokio::test]
+async fn test_geometry_values() {
+    let mut conn = connect().await;
+    "CREATE TABLE geometry_values_test (id INT PRIMARY KEY, g GEOMETRY)"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+
+    let cfg = source_config()
+        .table_list(vec!["mysql.geometry_values_test".into()])
+        .build()
+        .unwrap();
+    let source = Source::new(cfg).await.unwrap();
+    let mut cdc_stream = start_cdc_stream(&source).await;
+    let wkts = [
+        "POINT(1 2.5)",
+        "LINESTRING(0 0,1 1,2 -1)",
+        "POLYGON((0 0,4 0,4 4,0 0),(1 1,2 1,2 2,1 1))",
+        "MULTIPOINT((0 0),(1 2))",
+        "MULTILINESTRING((0 0,1 1),(2 2,3 3))",
+        "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((2 2,3 2,3 3,2 2)))",
+        "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,1 1))",
+        "GEOMETRYCOLLECTION EMPTY",
+    ];
+    for (i, wkt) in wkts.iter().enumerate() {
+        format!("INSERT INTO geometry_values_test VALUES ({i}, ST_GeomFromText('{wkt}'))")
+            .ignore(&mut conn)
+            .await
+            .unwrap();
+    }
+    "INSERT INTO geometry_values_test VALUES (100, ST_SRID(POINT(1, 2), 3857))"
+        .ignore(&mut conn)
+        .await
+        .unwrap();
+    let events = collect_events(&mut cdc_stream).await.unwrap();
+    let snapshot = snapshot_events(&source).await.unwrap();
+    drop_tables(&mut conn, &["geometry_values_test"]).await;
+
+    let geometries = |events: &[Event]| -> Vec<Geometry> {
+        data_changes(events)
+            .iter()
+            .map(|ch| match ch.get_by_name("g") {
+                Some(Value::Geometry(g)) => g.clone(),
+                v => panic!("Unexpected value {v:?}"),
+            })
+            .collect()
+    };
+    // The snapshot rows and the binlog rows are decoded to the same values.
+    let binlog_geometries = geometries(&events);
+    assert_eq!(binlog_geometries, geometries(&snapshot));
+    let (point, geometries) = binlog_geometries.split_last().unwrap();
+    assert_eq!(
+        geometries.iter().map(|g| g.to_wkt()).collect::<Vec<_>>(),
+        wkts
+    );
+    assert!(geometries.iter().all(|g| g.srid() == 0));
+    assert_eq!(point.srid(), 3857);
+    assert_eq!(point.to_wkt(), "POINT(1 2)");
+    assert_eq!(
+        geometries[2].to_geojson(),
+        serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [
+                [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 0.0]],
+                [[1.0, 1.0], [2.0, 1.0], [2.0, 2.0], [1.0, 1.0]],
+            ],
+        })
+    );
+    assert_eq!(
+        geometries[6].to_geojson(),
+        serde_json::json!({
+            "type": "GeometryCollection",
+            "geometries": [
+                {"type": "Point", "coordinates": [1.0, 1.0]},
+                {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 1.0]]},
+            ],
+        })
+    );
+}
@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chgcap_mysql::{
+    BinlogPosition, FileOffsetStore, FileSchemaHistory, MysqlTableDef, OffsetStore, SchemaHistory,
+    SchemaRecord,
+};
+
+fn temp_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("chgcap-{name}-{nanos}.offsets"))
+}
+
+#[test]
+fn test_file_offset_store_resume() {
+    let path = temp_path("resume");
+    let mut store = FileOffsetStore::open(&path, 3).unwrap();
+    assert_eq!(store.load().unwrap(), None);
+    store
+        .commit(&BinlogPosition::new("binlog.000001", 157))
+        .unwrap();
+    store
+        .commit(&BinlogPosition::new("binlog.000002", 4))
+        .unwrap();
+
+    let store = FileOffsetStore::open(&path, 3).unwrap();
+    assert_eq!(
+        store.load().unwrap(),
+        Some(BinlogPosition::new("binlog.000002", 4))
+    );
+    assert!(!PathBuf::from(format!("{}.tmp", path.display())).exists());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_offset_store_rollback() {
+    let path = temp_path("rollback");
+    let mut store = FileOffsetStore::open(&path, 2).unwrap();
+    for position in [100, 200, 300] {
+        store
+            .commit(&BinlogPosition::new("binlog.000001", position))
+            .unwrap();
+    }
+    let history: Vec<u64> = store.history().map(|o| o.position()).collect();
+    assert_eq!(history, vec![300, 200]);
+
+    assert_eq!(
+        store.rollback(1).unwrap(),
+        Some(BinlogPosition::new("binlog.000001", 200))
+    );
+    assert!(store.rollback(2).is_err());
+
+    let store = FileOffsetStore::open(&path, 2).unwrap();
+    assert_eq!(
+        store.load().unwrap(),
+        Some(BinlogPosition::new("binlog.000001", 200))
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Builds the definition of `databaseName.tableName` with INT columns.
+fn table_def(table: &str, columns: &[&str]) -> MysqlTableDef {
+    let (database_name, table_name) = table.split_once('.').unwrap();
+    let columns: Vec<String> = columns.iter().map(|c| format!("`{c}` int")).collect();
+    let sql = format!("CREATE TABLE `{table_name}` ({})", columns.join(", "));
+    MysqlTableDef::from_create_table(database_name, &sql).unwrap()
+}
+
+fn schema_record(
+    position: u64,
+    ddl: Option<&str>,
+    tables: &[(&str, Option<&[&str]>)],
+) -> SchemaRecord {
+    SchemaRecord {
+        offset: BinlogPosition::new("binlog.000001", position),
+        ddl: ddl.map(str::to_string),
+        tables: tables
+            .iter()
+            .map(|(t, c)| (t.to_string(), c.map(|c| table_def(t, c))))
+            .collect::<BTreeMap<_, _>>(),
+    }
+}
+
+fn columns_at(history: &FileSchemaHistory, position: u64, table: &str) -> Option<Vec<String>> {
+    let schema = history
+        .schema_at(&BinlogPosition::new("binlog.000001", position))
+        .unwrap()?;
+    schema.get(table).map(MysqlTableDef::column_names)
+}
+
+#[test]
+fn test_file_schema_history_rebuild() {
+    let path = temp_path("schema-rebuild");
+    let mut history = FileSchemaHistory::open(&path, 10).unwrap();
+    history
+        .append(schema_record(100, None, &[("db.t", Some(&["id"]))]))
+        .unwrap();
+    history
+        .append(schema_record(
+            200,
+            Some("ALTER TABLE t ADD name TEXT"),
+            &[("db.t", Some(&["id", "name"]))],
+        ))
+        .unwrap();
+    history
+        .append(schema_record(300, Some("DROP TABLE t"), &[("db.t", None)]))
+        .unwrap();
+    assert!(history.append(schema_record(250, None, &[])).is_err());
+
+    let history = FileSchemaHistory::open(&path, 10).unwrap();
+    assert!(history
+        .schema_at(&BinlogPosition::new("binlog.000001", 50))
+        .unwrap()
+        .is_none());
+    assert_eq!(columns_at(&history, 150, "db.t"), Some(vec!["id".into()]));
+    assert_eq!(
+        columns_at(&history, 200, "db.t"),
+        Some(vec!["id".into(), "name".into()])
+    );
+    assert_eq!(columns_at(&history, 300, "db.t"), None);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_schema_history_partial_record() {
+    let path = temp_path("schema-partial");
+    let mut history = FileSchemaHistory::open(&path, 10).unwrap();
+    history
+        .append(schema_record(100, None, &[("db.t", Some(&["id"]))]))
+        .unwrap();
+    // A crash during an append leaves a partial last line.
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"{\"offset\":").unwrap();
+
+    let mut history = FileSchemaHistory::open(&path, 10).unwrap();
+    assert_eq!(history.load().unwrap().len(), 1);
+    history
+        .append(schema_record(
+            200,
+            Some("ALTER TABLE t ADD name TEXT"),
+            &[("db.t", Some(&["id", "name"]))],
+        ))
+        .unwrap();
+
+    let history = FileSchemaHistory::open(&path, 10).unwrap();
+    assert_eq!(history.load().unwrap().len(), 2);
+    assert_eq!(
+        columns_at(&history, 200, "db.t"),
+        Some(vec!["id".into(), "name".into()])
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_schema_history_compaction() {
+    let path = temp_path("schema-compaction");
+    let mut history = FileSchemaHistory::open(&path, 2).unwrap();
+    history
+        .append(schema_record(100, None, &[("db.t", Some(&["id"]))]))
+        .unwrap();
+    history
+        .append(schema_record(
+            200,
+            Some("CREATE TABLE u (id INT)"),
+            &[("db.u", Some(&["id"]))],
+        ))
+        .unwrap();
+    history
+        .append(schema_record(
+            300,
+            Some("ALTER TABLE t ADD name TEXT"),
+            &[("db.t", Some(&["id", "name"]))],
+        ))
+        .unwrap();
+
+    // Only one DDL precedes the offset, which is not enough to compact.
+    history
+        .compact(&BinlogPosition::new("binlog.000001", 250))
+        .unwrap();
+    assert_eq!(history.load().unwrap().len(), 3);
+
+    history
+        .compact(&BinlogPosition::new("binlog.000001", 300))
+        .unwrap();
+    let history = FileSchemaHistory::open(&path, 2).unwrap();
+    let records = history.load().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].ddl, None);
+    assert_eq!(
+        columns_at(&history, 300, "db.t"),
+        Some(vec!["id".into(), "name".into()])
+    );
+    assert_eq!(columns_at(&history, 300, "db.u"), Some(vec!["id".into()]));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_state_store_commits_progress_with_offset() {
+    use chgcap_mysql::{SnapshotProgress, SnapshotProgressStore, SqliteStateStore};
+    use mysql_async::Value;
+
+    let path = temp_path("sqlite");
+    let mut store = SqliteStateStore::open(&path, 1).unwrap();
+    let progress = SnapshotProgress {
+        last_key: Some(vec![
+            Value::Int(-1),
+            Value::Bytes(b"key".to_vec()),
+            Value::Date(2024, 1, 2, 3, 4, 5, 6),
+            Value::Time(true, 1, 2, 3, 4, 5),
+        ]),
+        rows: 10,
+    };
+    store
+        .save(
+            &BinlogPosition::new("binlog.000001", 200),
+            "db.t1",
+            Some(progress.clone()),
+        )
+        .unwrap();
+    store
+        .save(
+            &BinlogPosition::new("binlog.000001", 300),
+            "db.t2",
+            Some(SnapshotProgress {
+                last_key: None,
+                rows: 0,
+            }),
+        )
+        .unwrap();
+
+    // The progress is only written with an offset that's later than the one it was saved at.
+    store
+        .commit(&BinlogPosition::new("binlog.000001", 200))
+        .unwrap();
+    assert!(SnapshotProgressStore::load(&store).unwrap().is_empty());
+    store
+        .commit(&BinlogPosition::new("binlog.000001", 250))
+        .unwrap();
+
+    let mut store = SqliteStateStore::open(&path, 1).unwrap();
+    assert_eq!(
+        OffsetStore::load(&store).unwrap(),
+        Some(BinlogPosition::new("binlog.000001", 250))
+    );
+    assert_eq!(
+        SnapshotProgressStore::load(&store).unwrap(),
+        BTreeMap::from([("db.t1".to_string(), progress)])
+    );
+
+    store
+        .save(&BinlogPosition::new("binlog.000001", 300), "db.t1", None)
+        .unwrap();
+    store
+        .commit(&BinlogPosition::new("binlog.000002", 4))
+        .unwrap();
+    assert!(SnapshotProgressStore::load(&store).unwrap().is_empty());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_state_store_schema_history() {
+    use chgcap_mysql::SqliteStateStore;
+
+    let path = temp_path("sqlite-schema");
+    let mut store = SqliteStateStore::open(&path, 1).unwrap();
+    let record =
+        |position, ddl: Option<&str>, table: &str, columns: Option<Vec<&str>>| SchemaRecord {
+            offset: BinlogPosition::new("binlog.000001", position),
+            ddl: ddl.map(str::to_string),
+            tables: BTreeMap::from([(table.to_string(), columns.map(|c| table_def(table, &c)))]),
+        };
+    store
+        .append(record(100, None, "db.t1", Some(vec!["a"])))
+        .unwrap();
+    store
+        .append(record(
+            200,
+            Some("ALTER TABLE t1 ADD b INT"),
+            "db.t1",
+            Some(vec!["a", "b"]),
+        ))
+        .unwrap();
+    store
+        .append(record(
+            300,
+            Some("CREATE TABLE t2 (c INT)"),
+            "db.t2",
+            Some(vec!["c"]),
+        ))
+        .unwrap();
+    assert!(store.append(record(150, None, "db.t1", None)).is_err());
+
+    store
+        .compact(&BinlogPosition::new("binlog.000001", 250))
+        .unwrap();
+    let store = SqliteStateStore::open(&path, 1).unwrap();
+    let records = SchemaHistory::load(&store).unwrap();
+    assert_eq!(
+        records,
+        vec![
+            record(250, None, "db.t1", Some(vec!["a", "b"])),
+            record(
+                300,
+                Some("CREATE TABLE t2 (c INT)"),
+                "db.t2",
+                Some(vec!["c"])
+            ),
+        ]
+    );
+    std::fs::remove_file(path).unwrap();
+}